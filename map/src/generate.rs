@@ -0,0 +1,381 @@
+//! Seeded generator for whole maps based on the original map.
+//!
+//! Platforms are placed one after another, each within jumping range of an already placed
+//! platform of a cluster which rises at the same time or earlier, without overlapping any
+//! other placed platform.
+//! Buttons, cubes and springpads are put on top of random platforms of their cluster.
+//! Lifts and pipes keep their location relative to the first platform of their cluster and are
+//! neither rotated nor resized, as that breaks their mechanics. Platforms don't overlap them either.
+
+use thiserror::Error;
+use crate::{check_reachability, snap_value, validate, Aabb, Element, ReachabilityParams, RefunctMap};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeneratorParams {
+    /// area the tops of platforms are placed in, `None` for the area of the original map, grown
+    /// if the platforms wouldn't fit
+    pub area: Option<Aabb>,
+    /// range of platform sizes along x and y
+    pub min_size: f32,
+    pub max_size: f32,
+    /// grid locations and sizes are snapped to, `0` disables snapping
+    pub grid: f32,
+    /// the generated map must allow reaching every button with these parameters
+    pub reachability: ReachabilityParams,
+    /// number of maps generated until one is valid and all buttons can be reached
+    pub attempts: u32,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        GeneratorParams {
+            area: None,
+            min_size: 250.,
+            max_size: 1000.,
+            grid: 125.,
+            reachability: ReachabilityParams::default(),
+            attempts: 100,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum GenerateError {
+    #[error("no valid map with all buttons reachable was found within {0} attempts")]
+    NoReachableMap(u32),
+    #[error("the platform sizes must be positive and the minimum must not exceed the maximum")]
+    InvalidSizes,
+}
+
+/// Number of random locations and sizes tried for a platform until one doesn't overlap other
+/// platforms, lifts or pipes, before the whole map is discarded.
+const PLACEMENT_TRIES: u32 = 300;
+/// Maximum fraction of the default area covered by platforms on average.
+/// The default area is grown if necessary, as denser maps rarely fit all platforms.
+const MAX_COVERAGE: f32 = 1. / 3.;
+
+/// SplitMix64, implemented here so that the same seed always generates the same map.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// uniform in `[min, max)`
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        let unit = (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+        min + unit * (max - min)
+    }
+    /// uniform in `0..len`, `len` must not be 0
+    fn index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// Top face of a placed platform in world coordinates.
+#[derive(Debug, Clone, Copy)]
+struct Placed {
+    cluster: usize,
+    x: f32,
+    y: f32,
+    sizex: f32,
+    sizey: f32,
+    top: f32,
+}
+
+impl Placed {
+    fn of(cluster: usize, cluster_z: f32, e: &Element) -> Placed {
+        Placed { cluster, x: e.x, y: e.y, sizex: e.sizex, sizey: e.sizey, top: cluster_z + e.z + e.sizez }
+    }
+
+    /// whether the top faces overlap when seen from above, touching edges don't overlap
+    fn overlaps(&self, other: &Placed) -> bool {
+        self.x < other.x + other.sizex && other.x < self.x + self.sizex
+            && self.y < other.y + other.sizey && other.y < self.y + self.sizey
+    }
+
+    fn offset_from(&self, other: &Placed) -> (f32, f32, f32) {
+        (self.x - other.x, self.y - other.y, self.top - other.top)
+    }
+
+    fn moved(&self, (dx, dy, dz): (f32, f32, f32)) -> Placed {
+        Placed { x: self.x + dx, y: self.y + dy, top: self.top + dz, ..*self }
+    }
+}
+
+/// Generate a map from a seed, which is valid and whose buttons can all be reached.
+///
+/// The same seed and parameters always result in the same map for the same original map.
+pub fn generate(original: &RefunctMap, seed: u64, params: &GeneratorParams) -> Result<RefunctMap, GenerateError> {
+    if !(params.min_size > 0. && params.min_size <= params.max_size) {
+        return Err(GenerateError::InvalidSizes);
+    }
+    let area = params.area.unwrap_or_else(|| default_area(original, params));
+    let mut rng = Rng(seed);
+    for _ in 0..params.attempts {
+        let Some(map) = generate_once(original, &mut rng, params, &area) else { continue };
+        if validate(&map, original).is_empty() && check_reachability(&map, &params.reachability).is_empty() {
+            return Ok(map);
+        }
+    }
+    Err(GenerateError::NoReachableMap(params.attempts))
+}
+
+/// Area of the original map, grown such that the platforms cover at most `MAX_COVERAGE` of it.
+fn default_area(original: &RefunctMap, params: &GeneratorParams) -> Aabb {
+    let mut area = original_area(original);
+    let platforms: usize = original.clusters.iter().map(|cluster| cluster.platforms.len()).sum();
+    let mean_size = (params.min_size + params.max_size) / 2.;
+    let needed = platforms as f32 * mean_size * mean_size / MAX_COVERAGE;
+    let available = 4. * area.extent.0 * area.extent.1;
+    if needed > available && available > 0. {
+        let factor = (needed / available).sqrt();
+        area.extent.0 *= factor;
+        area.extent.1 *= factor;
+    }
+    area
+}
+
+/// Bounds of the tops of all platforms.
+fn original_area(original: &RefunctMap) -> Aabb {
+    let (mut min, mut max) = ((f32::INFINITY, f32::INFINITY, f32::INFINITY), (f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY));
+    for cluster in &original.clusters {
+        for p in &cluster.platforms {
+            let top = cluster.z + p.z + p.sizez;
+            min = (min.0.min(p.x), min.1.min(p.y), min.2.min(top));
+            max = (max.0.max(p.x + p.sizex), max.1.max(p.y + p.sizey), max.2.max(top));
+        }
+    }
+    if min.0 > max.0 {
+        return Aabb { origin: (0., 0., 0.), extent: (0., 0., 0.) };
+    }
+    Aabb {
+        origin: ((min.0 + max.0) / 2., (min.1 + max.1) / 2., (min.2 + max.2) / 2.),
+        extent: ((max.0 - min.0) / 2., (max.1 - min.1) / 2., (max.2 - min.2) / 2.),
+    }
+}
+
+/// Returns `None` if a platform couldn't be placed without overlapping other platforms.
+fn generate_once(original: &RefunctMap, rng: &mut Rng, params: &GeneratorParams, area: &Aabb) -> Option<RefunctMap> {
+    let GeneratorParams { min_size, max_size, grid, reachability, .. } = *params;
+    let (min_x, min_y, min_top) = (area.origin.0 - area.extent.0, area.origin.1 - area.extent.1, area.origin.2 - area.extent.2);
+    let (max_x, max_y, max_top) = (area.origin.0 + area.extent.0, area.origin.1 + area.extent.1, area.origin.2 + area.extent.2);
+    // stay well within the jumping range, as the reachability check is strict
+    let (max_gap, max_rise) = (reachability.jump_distance / 2., reachability.jump_height / 2.);
+
+    let mut map = original.clone();
+    let rise_orders: Vec<usize> = map.clusters.iter().map(|cluster| cluster.rise_order).collect();
+    let mut order: Vec<usize> = (0..map.clusters.len()).collect();
+    order.sort_by_key(|&cluster_index| rise_orders[cluster_index]);
+
+    let mut placed: Vec<Placed> = Vec::new();
+    // lifts and pipes, which platforms must not overlap, but which aren't anchors
+    let mut obstacles: Vec<Placed> = Vec::new();
+    let is_free = |placed: &[Placed], obstacles: &[Placed], candidate: &Placed| {
+        !placed.iter().chain(obstacles).any(|other| candidate.overlaps(other))
+    };
+    for cluster_index in order {
+        let rise_order = rise_orders[cluster_index];
+        let cluster_z = map.clusters[cluster_index].z;
+        let first_placed = placed.len();
+        let fixed: Vec<Placed> = {
+            let cluster = &map.clusters[cluster_index];
+            cluster.lifts.iter().chain(&cluster.pipes).map(|e| Placed::of(cluster_index, cluster_z, e)).collect()
+        };
+        // movement of the first platform, which the lifts and pipes follow
+        let mut offset = None;
+        for p in &mut map.clusters[cluster_index].platforms {
+            let before = Placed::of(cluster_index, cluster_z, p);
+            let fits = |candidate: &Placed| {
+                if !is_free(&placed, &obstacles, candidate) {
+                    return false;
+                }
+                if offset.is_some() {
+                    return true;
+                }
+                let offset = candidate.offset_from(&before);
+                fixed.iter().map(|f| f.moved(offset))
+                    .all(|f| !f.overlaps(candidate) && is_free(&placed, &obstacles, &f))
+            };
+            // only platforms which are risen when this cluster rises
+            let anchors: Vec<&Placed> = placed.iter()
+                .filter(|anchor| rise_orders[anchor.cluster] <= rise_order)
+                .collect();
+            let mut candidate = || {
+                let sizex = snap_value(rng.range(min_size, max_size), grid).max(min_size);
+                let sizey = snap_value(rng.range(min_size, max_size), grid).max(min_size);
+                if anchors.is_empty() {
+                    // the first platform stays at the spawn
+                    return Placed { sizex, sizey, ..before };
+                }
+                let anchor = anchors[rng.index(anchors.len())];
+                let gap = rng.range(0., max_gap);
+                // along the side, overlapping at least a bit with the anchor
+                let along_x = rng.range(anchor.x - sizex + grid.max(1.), anchor.x + anchor.sizex - grid.max(1.));
+                let along_y = rng.range(anchor.y - sizey + grid.max(1.), anchor.y + anchor.sizey - grid.max(1.));
+                let (x, y) = match rng.index(4) {
+                    0 => (anchor.x + anchor.sizex + gap, along_y),
+                    1 => (anchor.x - sizex - gap, along_y),
+                    2 => (along_x, anchor.y + anchor.sizey + gap),
+                    _ => (along_x, anchor.y - sizey - gap),
+                };
+                let top = anchor.top + rng.range(-2. * max_rise, max_rise);
+                Placed {
+                    cluster: cluster_index,
+                    x: snap_value(x, grid).clamp(min_x, (max_x - sizex).max(min_x)),
+                    y: snap_value(y, grid).clamp(min_y, (max_y - sizey).max(min_y)),
+                    sizex,
+                    sizey,
+                    top: snap_value(top, grid).clamp(min_top, max_top),
+                }
+            };
+            let platform = (0..PLACEMENT_TRIES).map(|_| candidate()).find(|candidate| fits(candidate))?;
+            if offset.is_none() {
+                let moved = platform.offset_from(&before);
+                obstacles.extend(fixed.iter().map(|f| f.moved(moved)));
+                offset = Some(moved);
+            }
+            let Placed { x, y, sizex, sizey, top, .. } = platform;
+            *p = Element { x, y, z: top - cluster_z - p.sizez, pitch: 0., yaw: 0., roll: 0., sizex, sizey, sizez: p.sizez, hidden: p.hidden };
+            placed.push(platform);
+        }
+        let (dx, dy, dz) = match offset {
+            Some(offset) => offset,
+            // without platforms, the lifts and pipes stay where they are
+            None => {
+                if !fixed.iter().all(|f| is_free(&placed, &obstacles, f)) {
+                    return None;
+                }
+                obstacles.extend(fixed);
+                (0., 0., 0.)
+            }
+        };
+        let cluster = &mut map.clusters[cluster_index];
+        for e in cluster.lifts.iter_mut().chain(&mut cluster.pipes) {
+            e.x += dx;
+            e.y += dy;
+            e.z += dz;
+        }
+
+        // put the other elements on platforms of the cluster, or on any risen one if it has none
+        let cluster_platforms = if placed.len() > first_placed { &placed[first_placed..] } else { &placed[..] };
+        if cluster_platforms.is_empty() {
+            continue;
+        }
+        let cluster = &mut map.clusters[cluster_index];
+        for (e, height) in cluster.buttons.iter_mut().map(|e| (e, 0.))
+            .chain(cluster.springpads.iter_mut().map(|e| (e, 0.)))
+            // cubes float above the platform
+            .chain(cluster.cubes.iter_mut().map(|e| (e, 100.)))
+        {
+            let platform = cluster_platforms[rng.index(cluster_platforms.len())];
+            e.x = platform.x + rng.range(0., (platform.sizex - e.sizex).max(0.));
+            e.y = platform.y + rng.range(0., (platform.sizey - e.sizey).max(0.));
+            e.z = platform.top + height - cluster_z;
+            e.pitch = 0.;
+            e.yaw = 0.;
+            e.roll = 0.;
+        }
+    }
+    Some(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use crate::test_maps::{game_sized, original};
+    use super::*;
+
+    /// top faces of all platforms, lifts and pipes
+    fn top_faces(map: &RefunctMap) -> Vec<Placed> {
+        map.clusters.iter().enumerate()
+            .flat_map(|(cluster, c)| c.platforms.iter().chain(&c.lifts).chain(&c.pipes).map(move |e| Placed::of(cluster, c.z, e)))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_generates_same_map() {
+        let params = GeneratorParams::default();
+        let map = generate(&original(), 42, &params).unwrap();
+        assert_eq!(generate(&original(), 42, &params).unwrap(), map);
+        assert_ne!(generate(&original(), 43, &params).unwrap(), map);
+    }
+
+    #[test]
+    fn generated_maps_are_valid_and_reachable() {
+        let params = GeneratorParams::default();
+        for seed in 0..20 {
+            let map = generate(&original(), seed, &params).unwrap();
+            assert_eq!(validate(&map, &original()), Vec::new());
+            assert_eq!(check_reachability(&map, &params.reachability), Vec::new());
+        }
+    }
+
+    #[test]
+    fn platforms_lifts_and_pipes_dont_overlap() {
+        for seed in 0..20 {
+            let map = generate(&original(), seed, &GeneratorParams::default()).unwrap();
+            let faces = top_faces(&map);
+            for (i, a) in faces.iter().enumerate() {
+                assert!(faces[i + 1..].iter().all(|b| !a.overlaps(b)), "seed {seed}: {a:?} overlaps another element");
+            }
+        }
+    }
+
+    #[test]
+    fn lifts_and_pipes_move_with_their_cluster() {
+        let original = original();
+        for seed in 0..20 {
+            let map = generate(&original, seed, &GeneratorParams::default()).unwrap();
+            let (before, after) = (&original.clusters[1], &map.clusters[1]);
+            let offset = |a: &Element, b: &Element| (b.x - a.x, b.y - a.y, b.z + b.sizez - a.z - a.sizez);
+            let moved = offset(&before.platforms[0], &after.platforms[0]);
+            assert_eq!(offset(&before.lifts[0], &after.lifts[0]), moved, "seed {seed}");
+            assert_eq!(offset(&before.pipes[0], &after.pipes[0]), moved, "seed {seed}");
+            assert_eq!(after.lifts[0].sizex, before.lifts[0].sizex);
+        }
+    }
+
+    #[test]
+    fn game_sized_maps_are_generated_within_the_default_attempts() {
+        let original = game_sized();
+        assert_eq!(original.clusters.len(), 31);
+        let params = GeneratorParams::default();
+        for seed in 0..5 {
+            let start = Instant::now();
+            let map = generate(&original, seed, &params).unwrap();
+            // generating happens while the game waits, even in debug builds
+            assert!(start.elapsed() < Duration::from_secs(5), "seed {seed} took {:?}", start.elapsed());
+            assert_eq!(check_reachability(&map, &params.reachability), Vec::new());
+            let faces = top_faces(&map);
+            for (i, a) in faces.iter().enumerate() {
+                assert!(faces[i + 1..].iter().all(|b| !a.overlaps(b)), "seed {seed}: {a:?} overlaps another element");
+            }
+        }
+    }
+
+    #[test]
+    fn platforms_rise_after_their_anchor() {
+        // cluster 1 rises last, so cluster 2 can only be placed next to cluster 0
+        let mut original = original();
+        original.clusters[1].rise_order = 2;
+        original.clusters[2].rise_order = 1;
+        let params = GeneratorParams::default();
+        for seed in 0..20 {
+            let map = generate(&original, seed, &params).unwrap();
+            assert_eq!(check_reachability(&map, &params.reachability), Vec::new());
+        }
+    }
+
+    #[test]
+    fn invalid_params_are_rejected() {
+        let params = GeneratorParams { min_size: 500., max_size: 250., ..GeneratorParams::default() };
+        assert!(matches!(generate(&original(), 0, &params), Err(GenerateError::InvalidSizes)));
+        let params = GeneratorParams { attempts: 0, ..GeneratorParams::default() };
+        assert!(matches!(generate(&original(), 0, &params), Err(GenerateError::NoReachableMap(0))));
+    }
+}
@@ -0,0 +1,169 @@
+//! Undo/redo history of map edits.
+
+use std::collections::VecDeque;
+use serde::{Serialize, Deserialize};
+use crate::{apply_patch, diff, MapError, MapPatch, PatchError, RefunctMap};
+
+/// Default number of edits which can be undone.
+pub const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Each edit is stored as pair of patches, so the history stays small even for large maps.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    current: RefunctMap,
+    /// oldest edit first
+    undo: VecDeque<Edit>,
+    /// most recently undone edit last
+    redo: Vec<Edit>,
+    limit: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Edit {
+    forward: MapPatch,
+    backward: MapPatch,
+}
+
+impl History {
+    pub fn new(map: RefunctMap, limit: usize) -> History {
+        History { current: map, undo: VecDeque::new(), redo: Vec::new(), limit }
+    }
+
+    pub fn current(&self) -> &RefunctMap {
+        &self.current
+    }
+
+    /// Record the change from the current map to `map`, which becomes the current map.
+    ///
+    /// Clears the redo history.
+    /// Changes of editor settings like `snap` aren't recorded, but still update the current map.
+    /// Returns `false` if nothing changed, or an error if `map` doesn't have the same clusters and
+    /// elements as the current map.
+    pub fn record(&mut self, map: RefunctMap) -> Result<bool, MapError> {
+        if map == self.current {
+            return Ok(false);
+        }
+        let forward = diff(&self.current, &map)?;
+        if !forward.is_empty() {
+            let backward = diff(&map, &self.current)?;
+            self.redo.clear();
+            self.undo.push_back(Edit { forward, backward });
+            while self.undo.len() > self.limit {
+                self.undo.pop_front();
+            }
+        }
+        self.current = map;
+        Ok(true)
+    }
+
+    /// Revert the last edit, returning the new current map.
+    ///
+    /// Returns an error without changing the history if the edit doesn't match the current map,
+    /// which can only happen if the history was modified after loading it.
+    pub fn undo(&mut self) -> Result<Option<&RefunctMap>, PatchError> {
+        let edit = match self.undo.back() {
+            Some(edit) => edit,
+            None => return Ok(None),
+        };
+        self.current = apply_patch(&self.current, &edit.backward)?;
+        self.redo.extend(self.undo.pop_back());
+        Ok(Some(&self.current))
+    }
+
+    /// Reapply the last undone edit, returning the new current map.
+    ///
+    /// Errors like `undo`.
+    pub fn redo(&mut self) -> Result<Option<&RefunctMap>, PatchError> {
+        let edit = match self.redo.last() {
+            Some(edit) => edit,
+            None => return Ok(None),
+        };
+        self.current = apply_patch(&self.current, &edit.forward)?;
+        self.undo.extend(self.redo.pop());
+        Ok(Some(&self.current))
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::original;
+    use super::*;
+
+    fn moved(x: f32) -> RefunctMap {
+        let mut map = original();
+        map.clusters[1].platforms[0].x = x;
+        map
+    }
+
+    #[test]
+    fn edits_can_be_undone_and_redone() {
+        let mut history = History::new(original(), DEFAULT_HISTORY_LIMIT);
+        assert!(history.record(moved(1.)).unwrap());
+        assert!(history.record(moved(2.)).unwrap());
+        assert!(!history.record(moved(2.)).unwrap());
+        assert_eq!(history.undo().unwrap(), Some(&moved(1.)));
+        assert_eq!(history.undo().unwrap(), Some(&original()));
+        assert_eq!(history.undo().unwrap(), None);
+        assert_eq!(history.redo().unwrap(), Some(&moved(1.)));
+        assert_eq!((history.undo_len(), history.redo_len()), (1, 1));
+        assert_eq!(history.current(), &moved(1.));
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut history = History::new(original(), DEFAULT_HISTORY_LIMIT);
+        history.record(moved(1.)).unwrap();
+        history.undo().unwrap();
+        history.record(moved(2.)).unwrap();
+        assert_eq!(history.redo_len(), 0);
+        assert_eq!(history.redo().unwrap(), None);
+    }
+
+    #[test]
+    fn only_the_last_edits_are_kept() {
+        let mut history = History::new(original(), 3);
+        for x in 1..=5 {
+            history.record(moved(x as f32)).unwrap();
+        }
+        assert_eq!(history.undo_len(), 3);
+        while history.undo().unwrap().is_some() {}
+        assert_eq!(history.current(), &moved(2.));
+    }
+
+    #[test]
+    fn snap_changes_are_not_recorded() {
+        let mut history = History::new(original(), DEFAULT_HISTORY_LIMIT);
+        let mut map = original();
+        map.snap.size = 10.;
+        assert!(history.record(map.clone()).unwrap());
+        assert_eq!(history.undo_len(), 0);
+        assert_eq!(history.current(), &map);
+    }
+
+    #[test]
+    fn maps_with_different_elements_are_not_recorded() {
+        let mut history = History::new(original(), DEFAULT_HISTORY_LIMIT);
+        let mut map = original();
+        map.clusters[0].buttons.clear();
+        assert!(matches!(history.record(map), Err(MapError::Invalid(_))));
+        assert_eq!(history.current(), &original());
+    }
+
+    #[test]
+    fn mismatching_edits_keep_the_history() {
+        let mut history = History::new(original(), DEFAULT_HISTORY_LIMIT);
+        history.record(moved(1.)).unwrap();
+        history.undo.back_mut().unwrap().backward.clusters[0].cluster = 3;
+        assert_eq!(history.undo(), Err(PatchError::NoSuchCluster(3)));
+        assert_eq!((history.undo_len(), history.redo_len()), (1, 0));
+        assert_eq!(history.current(), &moved(1.));
+    }
+}
@@ -0,0 +1,327 @@
+//! The map format used by the map editor to store custom maps.
+//!
+//! The original map is read from the game at runtime, so everything depending on it
+//! takes it as argument.
+
+use std::fmt::{Display, Formatter};
+use serde::{Serialize, Deserialize};
+
+mod generate;
+mod history;
+mod migration;
+mod patch;
+mod reachability;
+mod share;
+mod snap;
+#[cfg(test)]
+mod test_maps;
+mod transform;
+mod validation;
+
+pub use generate::{generate, GenerateError, GeneratorParams};
+pub use history::{History, DEFAULT_HISTORY_LIMIT};
+pub use patch::{apply_patch, diff, ClusterPatch, ElementPatch, MapPatch, PatchError, CURRENT_PATCH_VERSION};
+pub use reachability::{check_reachability, ReachabilityParams, UnreachableButton};
+pub use share::{export, import, ImportError};
+pub use snap::{align, snap_element, snap_value, Aabb, Alignment, SnapSettings};
+pub use transform::{selection_center, transform_elements, ElementRef, GroupTransform};
+pub use validation::{validate, Diagnostic, ElementPath, FieldPath};
+
+/// Version written by `save_map`.
+pub const CURRENT_VERSION: u32 = 4;
+/// JSON schema of the v1 map format, see `docs/map-format.md`.
+pub const SCHEMA_V1: &str = include_str!("../schema/refunct-map-v1.schema.json");
+/// JSON schema of the v2 map format, see `docs/map-format.md`.
+pub const SCHEMA_V2: &str = include_str!("../schema/refunct-map-v2.schema.json");
+/// JSON schema of the v3 map format, see `docs/map-format.md`.
+pub const SCHEMA_V3: &str = include_str!("../schema/refunct-map-v3.schema.json");
+/// JSON schema of the v4 map format, see `docs/map-format.md`.
+pub const SCHEMA_V4: &str = include_str!("../schema/refunct-map-v4.schema.json");
+/// Maximum number of elements which can be spawned per cluster.
+pub const MAX_SPAWNED_PER_CLUSTER: usize = 256;
+/// Maximum number of diagnostics included in the `Display` of `MapError::Invalid`.
+const MAX_DISPLAYED_DIAGNOSTICS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefunctMap {
+    pub version: u32,
+    pub clusters: Vec<Cluster>,
+    /// editor setting, doesn't change the map itself
+    pub snap: SnapSettings,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cluster {
+    pub z: f32,
+    pub rise_speed: f32,
+    /// value of the level counter at which the cluster rises, the original cluster index by default
+    pub rise_order: usize,
+    /// seconds between reaching the `rise_order` and the cluster starting to rise
+    pub rise_delay: f32,
+    pub platforms: Vec<Element>,
+    pub cubes: Vec<Element>,
+    pub buttons: Vec<Element>,
+    pub lifts: Vec<Element>,
+    pub pipes: Vec<Element>,
+    pub springpads: Vec<Element>,
+    /// elements which don't exist in the original map
+    pub spawned: Vec<SpawnedElement>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpawnedElement {
+    pub element_type: ElementType,
+    #[serde(flatten)]
+    pub element: Element,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Element {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+    pub sizex: f32,
+    pub sizey: f32,
+    pub sizez: f32,
+    /// hidden elements are invisible and don't collide
+    pub hidden: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ElementType {
+    Platform,
+    Cube,
+    Button,
+    Lift,
+    Pipe,
+    Springpad,
+}
+
+impl ElementType {
+    pub const ALL: [ElementType; 6] = [
+        ElementType::Platform,
+        ElementType::Cube,
+        ElementType::Button,
+        ElementType::Lift,
+        ElementType::Pipe,
+        ElementType::Springpad,
+    ];
+
+    /// Element types which can be added to a cluster as `SpawnedElement`.
+    pub const SPAWNABLE: [ElementType; 4] = [
+        ElementType::Platform,
+        ElementType::Cube,
+        ElementType::Lift,
+        ElementType::Springpad,
+    ];
+
+    pub fn is_spawnable(self) -> bool {
+        ElementType::SPAWNABLE.contains(&self)
+    }
+
+    /// name of the list of this element type in a `Cluster`
+    pub fn field_name(self) -> &'static str {
+        match self {
+            ElementType::Platform => "platforms",
+            ElementType::Cube => "cubes",
+            ElementType::Button => "buttons",
+            ElementType::Lift => "lifts",
+            ElementType::Pipe => "pipes",
+            ElementType::Springpad => "springpads",
+        }
+    }
+}
+
+impl Cluster {
+    pub fn elements(&self, element_type: ElementType) -> &[Element] {
+        match element_type {
+            ElementType::Platform => &self.platforms,
+            ElementType::Cube => &self.cubes,
+            ElementType::Button => &self.buttons,
+            ElementType::Lift => &self.lifts,
+            ElementType::Pipe => &self.pipes,
+            ElementType::Springpad => &self.springpads,
+        }
+    }
+
+    pub fn elements_mut(&mut self, element_type: ElementType) -> &mut Vec<Element> {
+        match element_type {
+            ElementType::Platform => &mut self.platforms,
+            ElementType::Cube => &mut self.cubes,
+            ElementType::Button => &mut self.buttons,
+            ElementType::Lift => &mut self.lifts,
+            ElementType::Pipe => &mut self.pipes,
+            ElementType::Springpad => &mut self.springpads,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MapError {
+    #[error("map is not valid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("the map lives in the future (unknown map version {0})")]
+    UnknownVersion(u32),
+    #[error("{}", DisplayDiagnostics(.0))]
+    Invalid(Vec<Diagnostic>),
+    #[error("map string {0}")]
+    Import(#[from] ImportError),
+    #[error("the patch lives in the future (unknown patch version {0})")]
+    UnknownPatchVersion(u32),
+    #[error(transparent)]
+    Patch(#[from] PatchError),
+    #[error("patches and version 0 maps can't be used as original map, they need the original map themselves")]
+    NotAnOriginal,
+}
+
+struct DisplayDiagnostics<'a>(&'a [Diagnostic]);
+impl Display for DisplayDiagnostics<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "map is invalid:")?;
+        for diagnostic in self.0.iter().take(MAX_DISPLAYED_DIAGNOSTICS) {
+            write!(f, "\n  {diagnostic}")?;
+        }
+        if self.0.len() > MAX_DISPLAYED_DIAGNOSTICS {
+            write!(f, "\n  ... and {} more", self.0.len() - MAX_DISPLAYED_DIAGNOSTICS)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a map file of any known version, migrate it to the current version and validate
+/// it against the original map.
+///
+/// Patch files are applied on top of the original map.
+pub fn parse(content: &str, original: &RefunctMap) -> Result<RefunctMap, MapError> {
+    let version: Version = serde_json::from_str(content)?;
+    let map = match (version.patch_version, version.version) {
+        (Some(CURRENT_PATCH_VERSION), _) => apply_patch(original, &serde_json::from_str(content)?)?,
+        (Some(patch_version), _) => return Err(MapError::UnknownPatchVersion(patch_version)),
+        (None, version) => parse_map(content, version, Some(original))?,
+    };
+    let diagnostics = validate(&map, original);
+    if !diagnostics.is_empty() {
+        return Err(MapError::Invalid(diagnostics));
+    }
+    Ok(map)
+}
+
+/// Parse a copy of the original map, e.g. a new map saved from the map editor, like `parse`.
+///
+/// The map is only validated against itself.
+pub fn parse_original(content: &str) -> Result<RefunctMap, MapError> {
+    let version: Version = serde_json::from_str(content)?;
+    if version.patch_version.is_some() {
+        return Err(MapError::NotAnOriginal);
+    }
+    let map = parse_map(content, version.version, None)?;
+    let diagnostics = validate(&map, &map);
+    if !diagnostics.is_empty() {
+        return Err(MapError::Invalid(diagnostics));
+    }
+    Ok(map)
+}
+
+#[derive(Deserialize)]
+struct Version {
+    #[serde(default)]
+    version: u32,
+    patch_version: Option<u32>,
+}
+
+/// `original` is needed for v0 maps.
+fn parse_map(content: &str, version: u32, original: Option<&RefunctMap>) -> Result<RefunctMap, MapError> {
+    Ok(match version {
+        0 => {
+            let original = original.ok_or(MapError::NotAnOriginal)?;
+            let map = migration::migrate_v0_to_v1(serde_json::from_str(content)?, original)?;
+            migrate_from_v1(map)
+        }
+        1 => migrate_from_v1(serde_json::from_str(content)?),
+        2 => migrate_from_v2(serde_json::from_str(content)?),
+        3 => migration::migrate_v3_to_v4(serde_json::from_str(content)?),
+        4 => serde_json::from_str(content)?,
+        version => return Err(MapError::UnknownVersion(version)),
+    })
+}
+
+fn migrate_from_v1(map: migration::RefunctMapV1) -> RefunctMap {
+    migrate_from_v2(migration::migrate_v1_to_v2(map))
+}
+
+fn migrate_from_v2(map: migration::RefunctMapV2) -> RefunctMap {
+    migration::migrate_v3_to_v4(migration::migrate_v2_to_v3(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::test_maps::original;
+    use super::*;
+
+    #[test]
+    fn current_version_round_trips() {
+        let mut map = original();
+        map.clusters[1].platforms[0].hidden = true;
+        let content = serde_json::to_string(&map).unwrap();
+        assert_eq!(parse(&content, &original()).unwrap(), map);
+    }
+
+    #[test]
+    fn v0_is_migrated_to_the_current_version() {
+        let element = json!({ "x": 0., "y": 0., "z": 0., "pitch": 0., "yaw": 0., "roll": 0., "xscale": 1., "yscale": 1., "zscale": 1. });
+        let content = json!({ "clusters": [
+            { "platforms": [element], "cubes": [], "buttons": [element] },
+            { "platforms": [element, element], "cubes": [element], "buttons": [element] },
+            { "platforms": [element], "cubes": [], "buttons": [element] },
+        ]}).to_string();
+        let map = parse(&content, &original()).unwrap();
+        assert_eq!(map.version, CURRENT_VERSION);
+        assert_eq!(map.clusters[1].cubes[0].sizex, 50.);
+        assert_eq!(map.clusters[2].rise_order, 2);
+    }
+
+    #[test]
+    fn unknown_version_is_rejected() {
+        let content = json!({ "version": CURRENT_VERSION + 1, "clusters": [] }).to_string();
+        assert!(matches!(parse(&content, &original()), Err(MapError::UnknownVersion(version)) if version == CURRENT_VERSION + 1));
+    }
+
+    #[test]
+    fn invalid_map_is_rejected() {
+        let mut map = original();
+        for cluster in &mut map.clusters {
+            cluster.buttons[0].sizex = -1.;
+        }
+        let content = serde_json::to_string(&map).unwrap();
+        let err = parse(&content, &original()).unwrap_err();
+        assert!(matches!(&err, MapError::Invalid(diagnostics) if diagnostics.len() == 3));
+        assert_eq!(err.to_string().lines().next(), Some("map is invalid:"));
+    }
+
+    #[test]
+    fn original_is_parsed_without_original() {
+        let mut content = serde_json::to_value(original()).unwrap();
+        assert_eq!(parse_original(&content.to_string()).unwrap(), original());
+        content["version"] = json!(3);
+        assert_eq!(parse_original(&content.to_string()).unwrap(), original());
+        content["version"] = json!(CURRENT_VERSION + 1);
+        assert!(matches!(parse_original(&content.to_string()), Err(MapError::UnknownVersion(_))));
+        content["version"] = json!(0);
+        assert!(matches!(parse_original(&content.to_string()), Err(MapError::NotAnOriginal)));
+        let patch = json!({ "patch_version": CURRENT_PATCH_VERSION, "clusters": [] }).to_string();
+        assert!(matches!(parse_original(&patch), Err(MapError::NotAnOriginal)));
+
+        let mut map = original();
+        map.clusters[0].buttons[0].sizex = -1.;
+        assert!(matches!(parse_original(&serde_json::to_string(&map).unwrap()), Err(MapError::Invalid(_))));
+    }
+
+    #[test]
+    fn displayed_diagnostics_are_limited() {
+        let diagnostics = vec![Diagnostic::ClusterCount { expected: 1, found: 2 }; MAX_DISPLAYED_DIAGNOSTICS + 3];
+        let text = MapError::Invalid(diagnostics).to_string();
+        assert_eq!(text.lines().count(), MAX_DISPLAYED_DIAGNOSTICS + 2);
+        assert!(text.ends_with("... and 3 more"));
+    }
+}
@@ -0,0 +1,89 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use refunct_map::{check_reachability, ReachabilityParams};
+
+fn usage(program: &str) -> ! {
+    eprintln!("Usage: {program} [--jump-height <units>] [--jump-distance <units>] [--springpad-height <units>] [--spawn <x>,<y>,<z>] <original-map> <map>...");
+    process::exit(2);
+}
+
+fn parse_number(program: &str, flag: &str, value: Option<String>) -> f32 {
+    match value.as_deref().map(str::parse::<f32>) {
+        Some(Ok(value)) if value.is_finite() => value,
+        _ => {
+            eprintln!("{flag} expects a finite number");
+            usage(program)
+        }
+    }
+}
+
+/// Check map files for buttons the player can't reach.
+///
+/// The original map can be saved from the map editor by creating a new map, which is a copy of
+/// the original map.
+fn main() {
+    let mut args = env::args();
+    let program = args.next().unwrap_or_else(|| "refunct-map".to_string());
+    let mut params = ReachabilityParams::default();
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--jump-height" => params.jump_height = parse_number(&program, &arg, args.next()),
+            "--jump-distance" => params.jump_distance = parse_number(&program, &arg, args.next()),
+            "--springpad-height" => params.springpad_height = parse_number(&program, &arg, args.next()),
+            "--spawn" => {
+                let spawn = args.next().unwrap_or_default();
+                let coords: Result<Vec<f32>, _> = spawn.split(',').map(|c| c.trim().parse()).collect();
+                match coords.as_deref() {
+                    Ok(&[x, y, z]) if [x, y, z].iter().all(|c| c.is_finite()) => params.spawn = Some((x, y, z)),
+                    _ => {
+                        eprintln!("--spawn expects <x>,<y>,<z> with finite numbers");
+                        usage(&program)
+                    }
+                }
+            }
+            "-h" | "--help" => usage(&program),
+            _ => files.push(arg),
+        }
+    }
+    if files.len() < 2 {
+        usage(&program);
+    }
+
+    let read = |path: &str| fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("{path}: {e}");
+        process::exit(1);
+    });
+    let original = match refunct_map::parse_original(&read(&files[0])) {
+        Ok(original) => original,
+        Err(e) => {
+            eprintln!("{}: not a valid original map: {e}", files[0]);
+            process::exit(1);
+        }
+    };
+
+    let mut ok = true;
+    for path in &files[1..] {
+        let map = match refunct_map::parse(&read(path), &original) {
+            Ok(map) => map,
+            Err(e) => {
+                eprintln!("{path}: {e}");
+                ok = false;
+                continue;
+            }
+        };
+        let unreachable = check_reachability(&map, &params);
+        if unreachable.is_empty() {
+            println!("{path}: all buttons can be reached");
+        }
+        for button in unreachable {
+            ok = false;
+            println!("{path}: {button}");
+        }
+    }
+    if !ok {
+        process::exit(1);
+    }
+}
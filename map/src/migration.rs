@@ -0,0 +1,393 @@
+use serde::{Serialize, Deserialize};
+use crate::{Cluster, Diagnostic, Element, ElementType, MapError, RefunctMap, SnapSettings, SpawnedElement};
+use crate::validation::check_element_counts;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RefunctMapV0 {
+    clusters: Vec<ClusterV0>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClusterV0 {
+    platforms: Vec<ElementV0>,
+    cubes: Vec<ElementV0>,
+    buttons: Vec<ElementV0>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ElementV0 {
+    x: f32,
+    y: f32,
+    z: f32,
+    pitch: f32,
+    yaw: f32,
+    roll: f32,
+    xscale: f32,
+    yscale: f32,
+    zscale: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RefunctMapV1 {
+    version: u32,
+    clusters: Vec<ClusterV1>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClusterV1 {
+    z: f32,
+    rise_speed: f32,
+    platforms: Vec<ElementV1>,
+    cubes: Vec<ElementV1>,
+    buttons: Vec<ElementV1>,
+    lifts: Vec<ElementV1>,
+    pipes: Vec<ElementV1>,
+    springpads: Vec<ElementV1>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ElementV1 {
+    x: f32,
+    y: f32,
+    z: f32,
+    pitch: f32,
+    yaw: f32,
+    roll: f32,
+    sizex: f32,
+    sizey: f32,
+    sizez: f32,
+}
+
+/// v0 maps store scales instead of sizes and only contain platforms, cubes and buttons,
+/// everything else is taken from the original map.
+pub(crate) fn migrate_v0_to_v1(map: RefunctMapV0, original: &RefunctMap) -> Result<RefunctMapV1, MapError> {
+    fn migrate_element(e: ElementV0, orig: &Element) -> ElementV1 {
+        ElementV1 {
+            x: e.x,
+            y: e.y,
+            z: e.z,
+            pitch: e.pitch,
+            yaw: e.yaw,
+            roll: e.roll,
+            sizex: e.xscale * orig.sizex,
+            sizey: e.yscale * orig.sizey,
+            sizez: e.zscale * orig.sizez,
+        }
+    }
+    fn migrate_elements(elements: Vec<ElementV0>, orig: &[Element]) -> Vec<ElementV1> {
+        elements.into_iter().zip(orig).map(|(e, orig)| migrate_element(e, orig)).collect()
+    }
+    fn original_elements(orig: &[Element]) -> Vec<ElementV1> {
+        orig.iter().map(|e| ElementV1 {
+            x: e.x,
+            y: e.y,
+            z: e.z,
+            pitch: e.pitch,
+            yaw: e.yaw,
+            roll: e.roll,
+            sizex: e.sizex,
+            sizey: e.sizey,
+            sizez: e.sizez,
+        }).collect()
+    }
+
+    // the scales need the original elements, so their counts must match before migrating
+    let mut diagnostics = Vec::new();
+    if map.clusters.len() != original.clusters.len() {
+        diagnostics.push(Diagnostic::ClusterCount { expected: original.clusters.len(), found: map.clusters.len() });
+    }
+    for (cluster_index, (cluster, orig)) in map.clusters.iter().zip(&original.clusters).enumerate() {
+        let types = [ElementType::Platform, ElementType::Cube, ElementType::Button];
+        check_element_counts(&mut diagnostics, cluster_index, orig, &types, |element_type| match element_type {
+            ElementType::Platform => cluster.platforms.len(),
+            ElementType::Cube => cluster.cubes.len(),
+            ElementType::Button => cluster.buttons.len(),
+            _ => unreachable!(),
+        });
+    }
+    if !diagnostics.is_empty() {
+        return Err(MapError::Invalid(diagnostics));
+    }
+
+    Ok(RefunctMapV1 {
+        version: 1,
+        clusters: map.clusters.into_iter().zip(&original.clusters).map(|(cluster, orig)| ClusterV1 {
+            z: orig.z,
+            rise_speed: orig.rise_speed,
+            platforms: migrate_elements(cluster.platforms, &orig.platforms),
+            cubes: migrate_elements(cluster.cubes, &orig.cubes),
+            buttons: migrate_elements(cluster.buttons, &orig.buttons),
+            lifts: original_elements(&orig.lifts),
+            pipes: original_elements(&orig.pipes),
+            springpads: original_elements(&orig.springpads),
+        }).collect(),
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RefunctMapV2 {
+    version: u32,
+    clusters: Vec<ClusterV2>,
+    #[serde(default)]
+    snap: SnapSettingsV2,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClusterV2 {
+    z: f32,
+    rise_speed: f32,
+    platforms: Vec<ElementV2>,
+    cubes: Vec<ElementV2>,
+    buttons: Vec<ElementV2>,
+    lifts: Vec<ElementV2>,
+    pipes: Vec<ElementV2>,
+    springpads: Vec<ElementV2>,
+    spawned: Vec<SpawnedElementV2>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SpawnedElementV2 {
+    element_type: ElementTypeV2,
+    #[serde(flatten)]
+    element: ElementV2,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ElementV2 {
+    x: f32,
+    y: f32,
+    z: f32,
+    pitch: f32,
+    yaw: f32,
+    roll: f32,
+    sizex: f32,
+    sizey: f32,
+    sizez: f32,
+    hidden: bool,
+}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+enum ElementTypeV2 {
+    Platform,
+    Cube,
+    Button,
+    Lift,
+    Pipe,
+    Springpad,
+}
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct SnapSettingsV2 {
+    location: f32,
+    rotation: f32,
+    size: f32,
+}
+
+/// v1 maps can't hide or spawn elements.
+pub(crate) fn migrate_v1_to_v2(map: RefunctMapV1) -> RefunctMapV2 {
+    fn migrate_elements(elements: Vec<ElementV1>) -> Vec<ElementV2> {
+        elements.into_iter().map(|e| ElementV2 {
+            x: e.x,
+            y: e.y,
+            z: e.z,
+            pitch: e.pitch,
+            yaw: e.yaw,
+            roll: e.roll,
+            sizex: e.sizex,
+            sizey: e.sizey,
+            sizez: e.sizez,
+            hidden: false,
+        }).collect()
+    }
+    RefunctMapV2 {
+        version: 2,
+        clusters: map.clusters.into_iter().map(|cluster| ClusterV2 {
+            z: cluster.z,
+            rise_speed: cluster.rise_speed,
+            platforms: migrate_elements(cluster.platforms),
+            cubes: migrate_elements(cluster.cubes),
+            buttons: migrate_elements(cluster.buttons),
+            lifts: migrate_elements(cluster.lifts),
+            pipes: migrate_elements(cluster.pipes),
+            springpads: migrate_elements(cluster.springpads),
+            spawned: Vec::new(),
+        }).collect(),
+        snap: SnapSettingsV2::default(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RefunctMapV3 {
+    version: u32,
+    clusters: Vec<ClusterV3>,
+    #[serde(default)]
+    snap: SnapSettingsV2,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ClusterV3 {
+    z: f32,
+    rise_speed: f32,
+    rise_order: usize,
+    rise_delay: f32,
+    platforms: Vec<ElementV2>,
+    cubes: Vec<ElementV2>,
+    buttons: Vec<ElementV2>,
+    lifts: Vec<ElementV2>,
+    pipes: Vec<ElementV2>,
+    springpads: Vec<ElementV2>,
+    spawned: Vec<SpawnedElementV2>,
+}
+
+/// v2 maps keep the original rise order and rise without delay.
+pub(crate) fn migrate_v2_to_v3(map: RefunctMapV2) -> RefunctMapV3 {
+    RefunctMapV3 {
+        version: 3,
+        clusters: map.clusters.into_iter().enumerate().map(|(cluster_index, cluster)| ClusterV3 {
+            z: cluster.z,
+            rise_speed: cluster.rise_speed,
+            rise_order: cluster_index,
+            rise_delay: 0.,
+            platforms: cluster.platforms,
+            cubes: cluster.cubes,
+            buttons: cluster.buttons,
+            lifts: cluster.lifts,
+            pipes: cluster.pipes,
+            springpads: cluster.springpads,
+            spawned: cluster.spawned,
+        }).collect(),
+        snap: map.snap,
+    }
+}
+
+/// v3 maps keep their optional snap settings, snapping is disabled for maps without them.
+pub(crate) fn migrate_v3_to_v4(map: RefunctMapV3) -> RefunctMap {
+    fn migrate_element(e: ElementV2) -> Element {
+        Element {
+            x: e.x,
+            y: e.y,
+            z: e.z,
+            pitch: e.pitch,
+            yaw: e.yaw,
+            roll: e.roll,
+            sizex: e.sizex,
+            sizey: e.sizey,
+            sizez: e.sizez,
+            hidden: e.hidden,
+        }
+    }
+    fn migrate_elements(elements: Vec<ElementV2>) -> Vec<Element> {
+        elements.into_iter().map(migrate_element).collect()
+    }
+    fn migrate_element_type(element_type: ElementTypeV2) -> ElementType {
+        match element_type {
+            ElementTypeV2::Platform => ElementType::Platform,
+            ElementTypeV2::Cube => ElementType::Cube,
+            ElementTypeV2::Button => ElementType::Button,
+            ElementTypeV2::Lift => ElementType::Lift,
+            ElementTypeV2::Pipe => ElementType::Pipe,
+            ElementTypeV2::Springpad => ElementType::Springpad,
+        }
+    }
+    let SnapSettingsV2 { location, rotation, size } = map.snap;
+    RefunctMap {
+        version: 4,
+        clusters: map.clusters.into_iter().map(|cluster| Cluster {
+            z: cluster.z,
+            rise_speed: cluster.rise_speed,
+            rise_order: cluster.rise_order,
+            rise_delay: cluster.rise_delay,
+            platforms: migrate_elements(cluster.platforms),
+            cubes: migrate_elements(cluster.cubes),
+            buttons: migrate_elements(cluster.buttons),
+            lifts: migrate_elements(cluster.lifts),
+            pipes: migrate_elements(cluster.pipes),
+            springpads: migrate_elements(cluster.springpads),
+            spawned: cluster.spawned.into_iter().map(|spawned| SpawnedElement {
+                element_type: migrate_element_type(spawned.element_type),
+                element: migrate_element(spawned.element),
+            }).collect(),
+        }).collect(),
+        snap: SnapSettings { location, rotation, size },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use crate::test_maps::original;
+    use super::*;
+
+    fn v0_element(x: f32, scale: f32) -> serde_json::Value {
+        json!({ "x": x, "y": 0., "z": 0., "pitch": 0., "yaw": 0., "roll": 0., "xscale": scale, "yscale": 1., "zscale": 1. })
+    }
+
+    fn v0_map() -> RefunctMapV0 {
+        serde_json::from_value(json!({ "clusters": [
+            { "platforms": [v0_element(10., 2.)], "cubes": [], "buttons": [v0_element(0., 1.)] },
+            { "platforms": [v0_element(0., 1.), v0_element(0., 1.)], "cubes": [v0_element(0., 1.)], "buttons": [v0_element(0., 1.)] },
+            { "platforms": [v0_element(0., 1.)], "cubes": [], "buttons": [v0_element(0., 0.5)] },
+        ]})).unwrap()
+    }
+
+    #[test]
+    fn v0_scales_become_sizes() {
+        let map = migrate_v0_to_v1(v0_map(), &original()).unwrap();
+        let platform = &map.clusters[0].platforms[0];
+        assert_eq!((platform.x, platform.sizex, platform.sizey), (10., 2000., 1000.));
+        assert_eq!(map.clusters[2].buttons[0].sizex, 50.);
+        // everything v0 doesn't store is taken from the original
+        assert_eq!(map.clusters[2].z, 200.);
+        assert_eq!(map.clusters[1].lifts.len(), 1);
+        assert_eq!(map.clusters[1].springpads[0].x, 2200.);
+    }
+
+    #[test]
+    fn v0_counts_must_match_the_original() {
+        let mut map = v0_map();
+        map.clusters[1].platforms.pop();
+        let diagnostics = match migrate_v0_to_v1(map, &original()) {
+            Err(MapError::Invalid(diagnostics)) => diagnostics,
+            res => panic!("expected invalid map, got {res:?}"),
+        };
+        assert_eq!(diagnostics, vec![
+            Diagnostic::ElementCount { cluster: 1, element_type: ElementType::Platform, expected: 2, found: 1 },
+        ]);
+    }
+
+    #[test]
+    fn v1_elements_are_visible_and_nothing_is_spawned() {
+        let map = migrate_v1_to_v2(migrate_v0_to_v1(v0_map(), &original()).unwrap());
+        assert_eq!(map.version, 2);
+        for cluster in &map.clusters {
+            assert!(cluster.spawned.is_empty());
+            let elements = [&cluster.platforms, &cluster.cubes, &cluster.buttons, &cluster.lifts, &cluster.pipes, &cluster.springpads];
+            assert!(elements.into_iter().flatten().all(|e| !e.hidden));
+        }
+        assert_eq!(map.clusters[0].platforms[0].sizex, 2000.);
+    }
+
+    #[test]
+    fn v2_clusters_rise_in_original_order_without_delay() {
+        let map = migrate_v2_to_v3(migrate_v1_to_v2(migrate_v0_to_v1(v0_map(), &original()).unwrap()));
+        assert_eq!(map.version, 3);
+        let rise = map.clusters.iter().map(|c| (c.rise_order, c.rise_delay)).collect::<Vec<_>>();
+        assert_eq!(rise, vec![(0, 0.), (1, 0.), (2, 0.)]);
+    }
+
+    #[test]
+    fn v3_snap_settings_are_kept() {
+        let mut content = serde_json::to_value(original()).unwrap();
+        content["version"] = json!(3);
+        content["snap"] = json!({ "location": 50., "rotation": 15., "size": 10. });
+        let map = migrate_v3_to_v4(serde_json::from_value(content.clone()).unwrap());
+        assert_eq!(map.version, 4);
+        assert_eq!(map.snap, SnapSettings { location: 50., rotation: 15., size: 10. });
+        assert_eq!(map.clusters, original().clusters);
+        // the snap settings were optional in v3
+        content.as_object_mut().unwrap().remove("snap");
+        let map = migrate_v3_to_v4(serde_json::from_value(content).unwrap());
+        assert_eq!(map.snap, SnapSettings::default());
+    }
+
+    #[test]
+    fn v3_spawned_elements_are_kept() {
+        let mut expected = original();
+        let element = Element { x: 1., y: 2., z: 3., pitch: 0., yaw: 90., roll: 0., sizex: 10., sizey: 20., sizez: 30., hidden: true };
+        expected.clusters[1].spawned.push(SpawnedElement { element_type: ElementType::Springpad, element });
+        let mut content = serde_json::to_value(&expected).unwrap();
+        content["version"] = json!(3);
+        let map = migrate_v3_to_v4(serde_json::from_value(content).unwrap());
+        assert_eq!(map, expected);
+    }
+}
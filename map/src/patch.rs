@@ -0,0 +1,282 @@
+//! Patches list only the fields of a map which differ from a base map, usually the original map.
+
+use std::fmt::{Display, Formatter};
+use serde::{Serialize, Deserialize};
+use crate::{Diagnostic, Element, ElementType, MapError, RefunctMap, SpawnedElement};
+use crate::validation::check_element_counts;
+
+/// Version of the patch format, stored as `patch_version` to distinguish patch files from map files.
+pub const CURRENT_PATCH_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MapPatch {
+    pub patch_version: u32,
+    /// only clusters with changes, ordered by index
+    pub clusters: Vec<ClusterPatch>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterPatch {
+    pub cluster: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rise_speed: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rise_order: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rise_delay: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elements: Vec<ElementPatch>,
+    /// replaces all spawned elements of the cluster if set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spawned: Option<Vec<SpawnedElement>>,
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ElementPatch {
+    pub element_type: ElementType,
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pitch: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yaw: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roll: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sizex: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sizey: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sizez: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PatchError {
+    #[error("patch changes clusters[{0}], which doesn't exist")]
+    NoSuchCluster(usize),
+    #[error("patch changes clusters[{cluster}].{}[{index}], which doesn't exist", .element_type.field_name())]
+    NoSuchElement { cluster: usize, element_type: ElementType, index: usize },
+}
+
+impl MapPatch {
+    pub fn is_empty(&self) -> bool {
+        self.clusters.is_empty()
+    }
+}
+
+fn changed<T: PartialEq + Copy>(a: T, b: T) -> Option<T> {
+    (a != b).then_some(b)
+}
+
+impl ElementPatch {
+    fn diff(element_type: ElementType, index: usize, a: &Element, b: &Element) -> Option<ElementPatch> {
+        let patch = ElementPatch {
+            element_type,
+            index,
+            x: changed(a.x, b.x),
+            y: changed(a.y, b.y),
+            z: changed(a.z, b.z),
+            pitch: changed(a.pitch, b.pitch),
+            yaw: changed(a.yaw, b.yaw),
+            roll: changed(a.roll, b.roll),
+            sizex: changed(a.sizex, b.sizex),
+            sizey: changed(a.sizey, b.sizey),
+            sizez: changed(a.sizez, b.sizez),
+            hidden: changed(a.hidden, b.hidden),
+        };
+        (!patch.is_empty()).then_some(patch)
+    }
+    fn is_empty(&self) -> bool {
+        self.float_fields().iter().all(|(_, value)| value.is_none()) && self.hidden.is_none()
+    }
+    fn float_fields(&self) -> [(&'static str, Option<f32>); 9] {
+        [
+            ("x", self.x), ("y", self.y), ("z", self.z),
+            ("pitch", self.pitch), ("yaw", self.yaw), ("roll", self.roll),
+            ("sizex", self.sizex), ("sizey", self.sizey), ("sizez", self.sizez),
+        ]
+    }
+    fn apply(&self, e: &mut Element) {
+        let ElementPatch { element_type: _, index: _, x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden } = *self;
+        e.x = x.unwrap_or(e.x);
+        e.y = y.unwrap_or(e.y);
+        e.z = z.unwrap_or(e.z);
+        e.pitch = pitch.unwrap_or(e.pitch);
+        e.yaw = yaw.unwrap_or(e.yaw);
+        e.roll = roll.unwrap_or(e.roll);
+        e.sizex = sizex.unwrap_or(e.sizex);
+        e.sizey = sizey.unwrap_or(e.sizey);
+        e.sizez = sizez.unwrap_or(e.sizez);
+        e.hidden = hidden.unwrap_or(e.hidden);
+    }
+}
+
+/// Create a patch which turns `a` into `b`.
+///
+/// Both maps must have the same number of clusters and original elements, e.g. because they
+/// were validated against the same original map.
+/// Otherwise, the differences of `b` to `a` are returned as `MapError::Invalid`.
+pub fn diff(a: &RefunctMap, b: &RefunctMap) -> Result<MapPatch, MapError> {
+    let mut diagnostics = Vec::new();
+    if a.clusters.len() != b.clusters.len() {
+        diagnostics.push(Diagnostic::ClusterCount { expected: a.clusters.len(), found: b.clusters.len() });
+    }
+    for (cluster_index, (a, b)) in a.clusters.iter().zip(&b.clusters).enumerate() {
+        check_element_counts(&mut diagnostics, cluster_index, a, &ElementType::ALL, |element_type| b.elements(element_type).len());
+    }
+    if !diagnostics.is_empty() {
+        return Err(MapError::Invalid(diagnostics));
+    }
+
+    let clusters = a.clusters.iter().zip(&b.clusters).enumerate()
+        .map(|(cluster_index, (a, b))| {
+            let mut elements = Vec::new();
+            for element_type in ElementType::ALL {
+                let (a, b) = (a.elements(element_type), b.elements(element_type));
+                elements.extend(a.iter().zip(b).enumerate()
+                    .filter_map(|(index, (a, b))| ElementPatch::diff(element_type, index, a, b)));
+            }
+            ClusterPatch {
+                cluster: cluster_index,
+                z: changed(a.z, b.z),
+                rise_speed: changed(a.rise_speed, b.rise_speed),
+                rise_order: (a.rise_order != b.rise_order).then_some(b.rise_order),
+                rise_delay: changed(a.rise_delay, b.rise_delay),
+                elements,
+                spawned: (a.spawned != b.spawned).then(|| b.spawned.clone()),
+            }
+        }).filter(|patch| patch.z.is_some() || patch.rise_speed.is_some() || patch.rise_order.is_some()
+            || patch.rise_delay.is_some() || !patch.elements.is_empty() || patch.spawned.is_some())
+        .collect();
+    Ok(MapPatch { patch_version: CURRENT_PATCH_VERSION, clusters })
+}
+
+/// Apply a patch on top of a map.
+///
+/// Patches can be layered by applying them one after another.
+/// The result isn't validated.
+pub fn apply_patch(map: &RefunctMap, patch: &MapPatch) -> Result<RefunctMap, PatchError> {
+    let mut map = map.clone();
+    for cluster_patch in &patch.clusters {
+        let cluster = map.clusters.get_mut(cluster_patch.cluster)
+            .ok_or(PatchError::NoSuchCluster(cluster_patch.cluster))?;
+        cluster.z = cluster_patch.z.unwrap_or(cluster.z);
+        cluster.rise_speed = cluster_patch.rise_speed.unwrap_or(cluster.rise_speed);
+        cluster.rise_order = cluster_patch.rise_order.unwrap_or(cluster.rise_order);
+        cluster.rise_delay = cluster_patch.rise_delay.unwrap_or(cluster.rise_delay);
+        for element_patch in &cluster_patch.elements {
+            let element = cluster.elements_mut(element_patch.element_type).get_mut(element_patch.index)
+                .ok_or(PatchError::NoSuchElement { cluster: cluster_patch.cluster, element_type: element_patch.element_type, index: element_patch.index })?;
+            element_patch.apply(element);
+        }
+        if let Some(spawned) = &cluster_patch.spawned {
+            cluster.spawned = spawned.clone();
+        }
+    }
+    Ok(map)
+}
+
+/// One line per changed field, e.g. `clusters[3].platforms[2].x = 100`.
+impl Display for MapPatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for cluster in &self.clusters {
+            let i = cluster.cluster;
+            if let Some(z) = cluster.z {
+                writeln!(f, "clusters[{i}].z = {z}")?;
+            }
+            if let Some(rise_speed) = cluster.rise_speed {
+                writeln!(f, "clusters[{i}].rise_speed = {rise_speed}")?;
+            }
+            if let Some(rise_order) = cluster.rise_order {
+                writeln!(f, "clusters[{i}].rise_order = {rise_order}")?;
+            }
+            if let Some(rise_delay) = cluster.rise_delay {
+                writeln!(f, "clusters[{i}].rise_delay = {rise_delay}")?;
+            }
+            for e in &cluster.elements {
+                let (name, index) = (e.element_type.field_name(), e.index);
+                for (field, value) in e.float_fields() {
+                    if let Some(value) = value {
+                        writeln!(f, "clusters[{i}].{name}[{index}].{field} = {value}")?;
+                    }
+                }
+                if let Some(hidden) = e.hidden {
+                    writeln!(f, "clusters[{i}].{name}[{index}].hidden = {hidden}")?;
+                }
+            }
+            if let Some(spawned) = &cluster.spawned {
+                writeln!(f, "clusters[{i}].spawned = {} elements", spawned.len())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::{element, original};
+    use super::*;
+
+    fn edited() -> RefunctMap {
+        let mut map = original();
+        map.clusters[0].platforms[0].x = 100.;
+        map.clusters[1].cubes[0].hidden = true;
+        map.clusters[1].rise_delay = 2.5;
+        map.clusters[2].spawned.push(SpawnedElement { element_type: ElementType::Platform, element: element(0., 0., 0., 1., 1., 1.) });
+        map
+    }
+
+    #[test]
+    fn patch_turns_a_into_b() {
+        let patch = diff(&original(), &edited()).unwrap();
+        assert_eq!(patch.clusters.iter().map(|c| c.cluster).collect::<Vec<_>>(), vec![0, 1, 2]);
+        assert_eq!(apply_patch(&original(), &patch).unwrap(), edited());
+        let backward = diff(&edited(), &original()).unwrap();
+        assert_eq!(apply_patch(&edited(), &backward).unwrap(), original());
+    }
+
+    #[test]
+    fn equal_maps_have_an_empty_patch() {
+        assert!(diff(&original(), &original()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn patch_lists_changed_fields() {
+        let patch = diff(&original(), &edited()).unwrap();
+        assert_eq!(patch.to_string(), "\
+            clusters[0].platforms[0].x = 100\n\
+            clusters[1].rise_delay = 2.5\n\
+            clusters[1].cubes[0].hidden = true\n\
+            clusters[2].spawned = 1 elements\n");
+    }
+
+    #[test]
+    fn maps_with_different_elements_cant_be_diffed() {
+        let mut map = original();
+        map.clusters[1].lifts.clear();
+        match diff(&original(), &map) {
+            Err(MapError::Invalid(diagnostics)) => assert_eq!(diagnostics, vec![
+                Diagnostic::ElementCount { cluster: 1, element_type: ElementType::Lift, expected: 1, found: 0 },
+            ]),
+            res => panic!("expected invalid map, got {res:?}"),
+        }
+        map.clusters.pop();
+        assert!(matches!(diff(&original(), &map), Err(MapError::Invalid(_))));
+    }
+
+    #[test]
+    fn patch_must_match_the_map() {
+        let mut patch = diff(&original(), &edited()).unwrap();
+        patch.clusters[0].elements[0].index = 1;
+        assert_eq!(apply_patch(&original(), &patch), Err(PatchError::NoSuchElement { cluster: 0, element_type: ElementType::Platform, index: 1 }));
+        patch.clusters[0].cluster = 3;
+        assert_eq!(apply_patch(&original(), &patch), Err(PatchError::NoSuchCluster(3)));
+    }
+}
@@ -0,0 +1,224 @@
+//! Offline estimate which buttons of a map the player can reach.
+//!
+//! The player is modelled as jumping from the top face of one element to the top face of
+//! another one.
+//! Elements are boxes spanning from their location to their location plus their size,
+//! rotated around their location by their yaw; pitch and roll are ignored.
+//! Pipes and cubes can't be stood on, lifts are treated as if they didn't move.
+
+use std::fmt::{Display, Formatter};
+use crate::{Element, ElementType, RefunctMap};
+
+/// Movement capabilities of the player in Unreal units.
+///
+/// The defaults are rough estimates of the vanilla movement including ledge grabs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReachabilityParams {
+    /// how much higher than the current element the player can get onto
+    pub jump_height: f32,
+    /// largest horizontal gap between two elements the player can jump over
+    pub jump_distance: f32,
+    /// additional jump height when jumping from a springpad
+    pub springpad_height: f32,
+    /// location of the player's feet at the start, `None` to start on any element of the
+    /// clusters which are risen at the start
+    pub spawn: Option<(f32, f32, f32)>,
+}
+
+impl Default for ReachabilityParams {
+    fn default() -> Self {
+        ReachabilityParams {
+            jump_height: 250.,
+            jump_distance: 1000.,
+            springpad_height: 1500.,
+            spawn: None,
+        }
+    }
+}
+
+/// Button which can't be reached once its cluster is risen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnreachableButton {
+    pub cluster: usize,
+    /// index into the cluster's `buttons`
+    pub index: usize,
+}
+
+impl Display for UnreachableButton {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "clusters[{}].buttons[{}] can't be reached", self.cluster, self.index)
+    }
+}
+
+/// Top face of an element which can be stood on.
+#[derive(Debug, Clone, Copy)]
+struct Surface {
+    cluster: usize,
+    /// index into the cluster's `buttons` if this is a button
+    button: Option<usize>,
+    springpad: bool,
+    min: (f32, f32),
+    max: (f32, f32),
+    top: f32,
+}
+
+impl Surface {
+    fn new(cluster_index: usize, cluster_z: f32, element_type: ElementType, button: Option<usize>, e: &Element) -> Surface {
+        let (sin, cos) = e.yaw.to_radians().sin_cos();
+        let corners = [(0., 0.), (e.sizex, 0.), (0., e.sizey), (e.sizex, e.sizey)]
+            .map(|(dx, dy)| (e.x + dx * cos - dy * sin, e.y + dx * sin + dy * cos));
+        let min = corners.iter().fold((f32::INFINITY, f32::INFINITY), |(x, y), &(cx, cy)| (x.min(cx), y.min(cy)));
+        let max = corners.iter().fold((f32::NEG_INFINITY, f32::NEG_INFINITY), |(x, y), &(cx, cy)| (x.max(cx), y.max(cy)));
+        Surface {
+            cluster: cluster_index,
+            button,
+            springpad: element_type == ElementType::Springpad,
+            min,
+            max,
+            top: cluster_z + e.z + e.sizez,
+        }
+    }
+
+    fn point(x: f32, y: f32, z: f32) -> Surface {
+        Surface { cluster: usize::MAX, button: None, springpad: false, min: (x, y), max: (x, y), top: z }
+    }
+
+    fn gap(&self, other: &Surface) -> f32 {
+        let dx = (other.min.0 - self.max.0).max(self.min.0 - other.max.0).max(0.);
+        let dy = (other.min.1 - self.max.1).max(self.min.1 - other.max.1).max(0.);
+        dx.hypot(dy)
+    }
+
+    fn can_jump_to(&self, other: &Surface, params: &ReachabilityParams) -> bool {
+        let height = params.jump_height + if self.springpad { params.springpad_height } else { 0. };
+        other.top - self.top <= height && self.gap(other) <= params.jump_distance
+    }
+}
+
+fn surfaces(map: &RefunctMap) -> Vec<Surface> {
+    let mut surfaces = Vec::new();
+    for (cluster_index, cluster) in map.clusters.iter().enumerate() {
+        for element_type in [ElementType::Platform, ElementType::Button, ElementType::Lift, ElementType::Springpad] {
+            for (index, element) in cluster.elements(element_type).iter().enumerate() {
+                if !element.hidden {
+                    let button = (element_type == ElementType::Button).then_some(index);
+                    surfaces.push(Surface::new(cluster_index, cluster.z, element_type, button, element));
+                }
+            }
+        }
+        for spawned in &cluster.spawned {
+            if !spawned.element.hidden && spawned.element_type != ElementType::Cube {
+                surfaces.push(Surface::new(cluster_index, cluster.z, spawned.element_type, None, &spawned.element));
+            }
+        }
+    }
+    surfaces
+}
+
+/// Find all buttons which can't be reached from the spawn when their cluster rises.
+///
+/// Only the clusters whose `rise_order` is at most the one of the button's cluster are
+/// risen at that time.
+/// Hidden buttons can never be pressed and are always reported.
+/// This is an estimate: skilled movement may reach more and imprecise element sizes may
+/// reach less.
+pub fn check_reachability(map: &RefunctMap, params: &ReachabilityParams) -> Vec<UnreachableButton> {
+    let surfaces = surfaces(map);
+    let mut rise_orders: Vec<usize> = map.clusters.iter().map(|cluster| cluster.rise_order).collect();
+    rise_orders.sort_unstable();
+    rise_orders.dedup();
+
+    let mut reachable_buttons = Vec::new();
+    for rise_order in rise_orders {
+        let risen: Vec<&Surface> = surfaces.iter()
+            .filter(|surface| map.clusters[surface.cluster].rise_order <= rise_order)
+            .collect();
+        let mut reached: Vec<bool> = risen.iter()
+            .map(|surface| params.spawn.is_none() && map.clusters[surface.cluster].rise_order == 0)
+            .collect();
+        let mut todo: Vec<Surface> = risen.iter().zip(&reached)
+            .filter(|(_, &reached)| reached)
+            .map(|(&&surface, _)| surface)
+            .collect();
+        if let Some((x, y, z)) = params.spawn {
+            todo.push(Surface::point(x, y, z));
+        }
+        while let Some(from) = todo.pop() {
+            for (to, reached) in risen.iter().zip(&mut reached) {
+                if !*reached && from.can_jump_to(to, params) {
+                    *reached = true;
+                    todo.push(**to);
+                }
+            }
+        }
+        reachable_buttons.extend(risen.iter().zip(&reached)
+            .filter(|(surface, &reached)| reached && map.clusters[surface.cluster].rise_order == rise_order)
+            .filter_map(|(surface, _)| surface.button.map(|index| (surface.cluster, index))));
+    }
+
+    let mut unreachable = Vec::new();
+    for (cluster_index, cluster) in map.clusters.iter().enumerate() {
+        for index in 0..cluster.buttons.len() {
+            if !reachable_buttons.contains(&(cluster_index, index)) {
+                unreachable.push(UnreachableButton { cluster: cluster_index, index });
+            }
+        }
+    }
+    unreachable
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::original;
+    use super::*;
+
+    fn unreachable(map: &RefunctMap, params: &ReachabilityParams) -> Vec<(usize, usize)> {
+        check_reachability(map, params).into_iter().map(|button| (button.cluster, button.index)).collect()
+    }
+
+    #[test]
+    fn original_buttons_are_reachable() {
+        assert_eq!(unreachable(&original(), &ReachabilityParams::default()), Vec::new());
+    }
+
+    #[test]
+    fn springpads_jump_higher() {
+        let mut map = original();
+        map.clusters[2].z = 500.;
+        assert_eq!(unreachable(&map, &ReachabilityParams::default()), Vec::new());
+        map.clusters[1].springpads[0].hidden = true;
+        assert_eq!(unreachable(&map, &ReachabilityParams::default()), vec![(2, 0)]);
+    }
+
+    #[test]
+    fn gaps_must_be_jumpable() {
+        let params = ReachabilityParams { jump_distance: 400., ..ReachabilityParams::default() };
+        assert_eq!(unreachable(&original(), &params), vec![(1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn hidden_buttons_are_unreachable() {
+        let mut map = original();
+        map.clusters[0].buttons[0].hidden = true;
+        let buttons = check_reachability(&map, &ReachabilityParams::default());
+        assert_eq!(buttons, vec![UnreachableButton { cluster: 0, index: 0 }]);
+        assert_eq!(buttons[0].to_string(), "clusters[0].buttons[0] can't be reached");
+    }
+
+    #[test]
+    fn only_risen_clusters_can_be_used() {
+        let mut map = original();
+        map.clusters[1].rise_order = 2;
+        map.clusters[2].rise_order = 1;
+        // cluster 2 rises before cluster 1, which bridges the gap to cluster 0
+        assert_eq!(unreachable(&map, &ReachabilityParams::default()), vec![(2, 0)]);
+    }
+
+    #[test]
+    fn player_starts_at_the_spawn() {
+        let params = ReachabilityParams { spawn: Some((500., 500., 100.)), ..ReachabilityParams::default() };
+        assert_eq!(unreachable(&original(), &params), Vec::new());
+        let params = ReachabilityParams { spawn: Some((10000., 0., 100.)), ..ReachabilityParams::default() };
+        assert_eq!(unreachable(&original(), &params), vec![(0, 0), (1, 0), (2, 0)]);
+    }
+}
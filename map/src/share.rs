@@ -0,0 +1,142 @@
+//! Compact strings to share maps e.g. via chat.
+//!
+//! A map string is `PREFIX` followed by the URL-safe base64 of a format byte,
+//! the CRC32 of the JSON map as little endian and the deflated JSON map.
+
+use std::io::{Read, Write};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use crate::{MapError, RefunctMap};
+
+/// Start of every map string, to recognize them and to detect truncation at the start.
+pub const PREFIX: &str = "refunct-map:";
+const FORMAT: u8 = 1;
+/// format byte + CRC32
+const HEADER_LEN: usize = 5;
+/// Maximum size of the decompressed JSON map, to not exhaust memory on crafted strings.
+///
+/// Maps with `MAX_SPAWNED_PER_CLUSTER` spawned elements in each of the ~30 clusters are about 1 MiB.
+const MAX_JSON_LEN: u64 = 8 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("doesn't start with {PREFIX:?}")]
+    MissingPrefix,
+    #[error("not valid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("too short")]
+    Truncated,
+    #[error("unknown format {0}, maybe it was exported by a newer version")]
+    UnknownFormat(u8),
+    #[error("can't be decompressed: {0}")]
+    Decompress(std::io::Error),
+    #[error("checksum mismatch, the string is probably incomplete")]
+    Checksum,
+    #[error("decompresses to more than {} MiB", MAX_JSON_LEN / 1024 / 1024)]
+    TooLarge,
+}
+
+/// Encode a map as string, which can be imported again with `import`.
+pub fn export(map: &RefunctMap) -> String {
+    let json = serde_json::to_vec(map).expect("maps are always serializable");
+    let mut bytes = vec![FORMAT];
+    bytes.extend_from_slice(&crc32fast::hash(&json).to_le_bytes());
+    let mut encoder = DeflateEncoder::new(bytes, Compression::best());
+    encoder.write_all(&json).expect("writing to a Vec can't fail");
+    let bytes = encoder.finish().expect("writing to a Vec can't fail");
+    format!("{PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Decode a string created with `export`, migrating and validating the contained map like `parse`.
+///
+/// Surrounding whitespace is ignored, as it is often added when copying from a chat.
+pub fn import(s: &str, original: &RefunctMap) -> Result<RefunctMap, MapError> {
+    let encoded = s.trim().strip_prefix(PREFIX).ok_or(ImportError::MissingPrefix)?;
+    let bytes = URL_SAFE_NO_PAD.decode(encoded).map_err(ImportError::from)?;
+    if bytes.len() < HEADER_LEN {
+        return Err(ImportError::Truncated.into());
+    }
+    let (header, compressed) = bytes.split_at(HEADER_LEN);
+    if header[0] != FORMAT {
+        return Err(ImportError::UnknownFormat(header[0]).into());
+    }
+    let checksum = u32::from_le_bytes(header[1..].try_into().unwrap());
+    let mut json = String::new();
+    DeflateDecoder::new(compressed).take(MAX_JSON_LEN + 1).read_to_string(&mut json).map_err(ImportError::Decompress)?;
+    if json.len() as u64 > MAX_JSON_LEN {
+        return Err(ImportError::TooLarge.into());
+    }
+    if crc32fast::hash(json.as_bytes()) != checksum {
+        return Err(ImportError::Checksum.into());
+    }
+    crate::parse(&json, original)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::original;
+    use super::*;
+
+    fn encode(format: u8, checksum: u32, json: &[u8]) -> String {
+        let mut bytes = vec![format];
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        let mut encoder = DeflateEncoder::new(bytes, Compression::best());
+        encoder.write_all(json).unwrap();
+        format!("{PREFIX}{}", URL_SAFE_NO_PAD.encode(encoder.finish().unwrap()))
+    }
+
+    fn import_error(s: &str) -> ImportError {
+        match import(s, &original()) {
+            Err(MapError::Import(err)) => err,
+            res => panic!("expected import error, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn exported_map_can_be_imported() {
+        let mut map = original();
+        map.clusters[2].platforms[0].x = 1234.5;
+        map.snap.location = 50.;
+        let exported = export(&map);
+        assert!(exported.starts_with(PREFIX));
+        assert_eq!(import(&exported, &original()).unwrap(), map);
+        assert_eq!(import(&format!(" \n{exported}\t\n"), &original()).unwrap(), map);
+    }
+
+    #[test]
+    fn broken_strings_are_rejected() {
+        let exported = export(&original());
+        assert!(matches!(import_error(&exported[1..]), ImportError::MissingPrefix));
+        assert!(matches!(import_error(&format!("{exported}!")), ImportError::Base64(_)));
+        assert!(matches!(import_error(&exported[..PREFIX.len() + 4]), ImportError::Truncated));
+        assert!(matches!(import_error(&exported[..exported.len() - 10]), ImportError::Decompress(_) | ImportError::Checksum));
+    }
+
+    #[test]
+    fn header_is_checked() {
+        let json = serde_json::to_vec(&original()).unwrap();
+        let checksum = crc32fast::hash(&json);
+        assert!(import(&encode(FORMAT, checksum, &json), &original()).is_ok());
+        assert!(matches!(import_error(&encode(FORMAT + 1, checksum, &json)), ImportError::UnknownFormat(format) if format == FORMAT + 1));
+        assert!(matches!(import_error(&encode(FORMAT, checksum ^ 1, &json)), ImportError::Checksum));
+    }
+
+    #[test]
+    fn decompressed_size_is_limited() {
+        let json = vec![b' '; MAX_JSON_LEN as usize + 1];
+        let encoded = encode(FORMAT, crc32fast::hash(&json), &json);
+        // compresses well enough to fit into a chat message
+        assert!(encoded.len() < 16 * 1024);
+        assert!(matches!(import_error(&encoded), ImportError::TooLarge));
+    }
+
+    #[test]
+    fn imported_maps_are_validated() {
+        let mut map = original();
+        map.clusters.pop();
+        assert!(matches!(import(&export(&map), &original()), Err(MapError::Invalid(_))));
+    }
+}
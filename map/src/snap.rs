@@ -0,0 +1,117 @@
+//! Grid snapping and alignment of elements.
+
+use serde::{Serialize, Deserialize};
+use crate::Element;
+
+/// Grid sizes elements are snapped to when editing them, `0` disables snapping.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapSettings {
+    pub location: f32,
+    /// in degrees
+    pub rotation: f32,
+    pub size: f32,
+}
+
+/// Round `value` to the closest multiple of `grid`, or return it unchanged if `grid` is `0`.
+pub fn snap_value(value: f32, grid: f32) -> f32 {
+    if grid == 0. || !grid.is_finite() {
+        return value;
+    }
+    (value / grid).round() * grid
+}
+
+/// Snap location, rotation and size of an element.
+///
+/// Sizes are never snapped to 0, but to the grid size instead.
+pub fn snap_element(e: &mut Element, settings: &SnapSettings) {
+    let SnapSettings { location, rotation, size } = *settings;
+    e.x = snap_value(e.x, location);
+    e.y = snap_value(e.y, location);
+    e.z = snap_value(e.z, location);
+    e.pitch = snap_value(e.pitch, rotation);
+    e.yaw = snap_value(e.yaw, rotation);
+    e.roll = snap_value(e.roll, rotation);
+    let snap_size = |value: f32| {
+        let snapped = snap_value(value, size);
+        if snapped > 0. { snapped } else { size.abs() }
+    };
+    e.sizex = snap_size(e.sizex);
+    e.sizey = snap_size(e.sizey);
+    e.sizez = snap_size(e.sizez);
+}
+
+/// World-space axis-aligned bounding box as returned by `GetActorBounds`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub origin: (f32, f32, f32),
+    /// half-size
+    pub extent: (f32, f32, f32),
+}
+
+/// How to place an element relative to another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// bottom face flush with the target's top face
+    OnTop,
+    /// side face flush with the target's side face in positive x direction
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+}
+
+/// Translation moving `element` to the given alignment relative to `target`.
+///
+/// Only the axis of the alignment changes, e.g. `OnTop` keeps x and y.
+pub fn align(element: &Aabb, target: &Aabb, alignment: Alignment) -> (f32, f32, f32) {
+    let (eo, ee) = (element.origin, element.extent);
+    let (to, te) = (target.origin, target.extent);
+    match alignment {
+        Alignment::OnTop => (0., 0., (to.2 + te.2) - (eo.2 - ee.2)),
+        Alignment::PosX => ((to.0 + te.0) - (eo.0 - ee.0), 0., 0.),
+        Alignment::NegX => ((to.0 - te.0) - (eo.0 + ee.0), 0., 0.),
+        Alignment::PosY => (0., (to.1 + te.1) - (eo.1 - ee.1), 0.),
+        Alignment::NegY => (0., (to.1 - te.1) - (eo.1 + ee.1), 0.),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::element;
+    use super::*;
+
+    #[test]
+    fn values_snap_to_the_closest_multiple() {
+        assert_eq!(snap_value(124., 50.), 100.);
+        assert_eq!(snap_value(-126., 50.), -150.);
+        assert_eq!(snap_value(124., 0.), 124.);
+        assert_eq!(snap_value(124., f32::NAN), 124.);
+    }
+
+    #[test]
+    fn sizes_dont_snap_to_zero() {
+        let mut e = element(12., 37., -5., 20., 260., 80.);
+        e.yaw = 50.;
+        snap_element(&mut e, &SnapSettings { location: 25., rotation: 45., size: 100. });
+        assert_eq!((e.x, e.y, e.z, e.yaw), (0., 25., 0., 45.));
+        assert_eq!((e.sizex, e.sizey, e.sizez), (100., 300., 100.));
+    }
+
+    #[test]
+    fn disabled_snapping_keeps_elements() {
+        let mut e = element(12., 37., -5., 20., 260., 80.);
+        snap_element(&mut e, &SnapSettings::default());
+        assert_eq!(e, element(12., 37., -5., 20., 260., 80.));
+    }
+
+    #[test]
+    fn elements_are_aligned_flush() {
+        let element = Aabb { origin: (10., 20., 30.), extent: (5., 5., 5.) };
+        let target = Aabb { origin: (0., 0., 0.), extent: (50., 50., 10.) };
+        assert_eq!(align(&element, &target, Alignment::OnTop), (0., 0., -15.));
+        assert_eq!(align(&element, &target, Alignment::PosX), (45., 0., 0.));
+        assert_eq!(align(&element, &target, Alignment::NegX), (-65., 0., 0.));
+        assert_eq!(align(&element, &target, Alignment::PosY), (0., 35., 0.));
+        assert_eq!(align(&element, &target, Alignment::NegY), (0., -75., 0.));
+    }
+}
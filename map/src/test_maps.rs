@@ -0,0 +1,85 @@
+//! Small synthetic maps used by the tests instead of the original map read from the game.
+
+use crate::{Cluster, Element, RefunctMap, SnapSettings, CURRENT_VERSION};
+
+pub fn element(x: f32, y: f32, z: f32, sizex: f32, sizey: f32, sizez: f32) -> Element {
+    Element { x, y, z, pitch: 0., yaw: 0., roll: 0., sizex, sizey, sizez, hidden: false }
+}
+
+fn cluster(z: f32, rise_order: usize) -> Cluster {
+    Cluster {
+        z,
+        rise_speed: 100.,
+        rise_order,
+        rise_delay: 0.,
+        platforms: Vec::new(),
+        cubes: Vec::new(),
+        buttons: Vec::new(),
+        lifts: Vec::new(),
+        pipes: Vec::new(),
+        springpads: Vec::new(),
+        spawned: Vec::new(),
+    }
+}
+
+/// Three clusters in a row along x, each reachable from the previous one with the default
+/// `ReachabilityParams`.
+/// Cluster 1 contains every element type, cluster 2 is located 200 units higher.
+pub fn original() -> RefunctMap {
+    let mut first = cluster(0., 0);
+    first.platforms.push(element(0., 0., 0., 1000., 1000., 100.));
+    first.buttons.push(element(450., 450., 100., 100., 100., 20.));
+
+    let mut second = cluster(0., 1);
+    second.platforms.push(element(1500., 0., 0., 1000., 1000., 100.));
+    second.platforms.push(element(1500., 1500., 0., 500., 500., 100.));
+    second.cubes.push(element(1600., 100., 200., 50., 50., 50.));
+    second.buttons.push(element(1950., 450., 100., 100., 100., 20.));
+    second.lifts.push(element(2000., 1500., 0., 400., 400., 50.));
+    second.pipes.push(element(1500., 900., 100., 100., 100., 300.));
+    second.springpads.push(element(2200., 200., 100., 100., 100., 10.));
+
+    let mut third = cluster(200., 2);
+    third.platforms.push(element(3000., 0., 0., 1000., 1000., 100.));
+    third.buttons.push(element(3450., 450., 100., 100., 100., 20.));
+
+    RefunctMap { version: CURRENT_VERSION, clusters: vec![first, second, third], snap: SnapSettings::default() }
+}
+
+/// Map with the 31 clusters, 251 platforms and the buttons of the game, read from the
+/// teleport data of the tool.
+///
+/// The tool doesn't know lifts and pipes, so every fifth cluster gets a lift and a pipe next to
+/// its first platform.
+pub fn game_sized() -> RefunctMap {
+    const TELEPORT: &str = include_str!("../../tool/teleport.re");
+    // all numbers of the entries starting with `entry`, e.g. `Button { cluster: 0, loc: Location { x: ... } }`
+    fn entries<'a>(entry: &'a str) -> impl Iterator<Item = Vec<f32>> + 'a {
+        TELEPORT.lines().map(str::trim).filter(move |line| line.starts_with(entry)).map(|line| {
+            line.split(": ").skip(1)
+                .filter_map(|value| value.split([',', ' ']).next().unwrap().parse().ok())
+                .collect()
+        })
+    }
+
+    let mut clusters: Vec<Cluster> = Vec::new();
+    for numbers in entries("Platform { cluster:") {
+        let &[cluster_index, x, y, z, sizex, sizey, sizez] = &numbers[..] else { panic!("unexpected platform {numbers:?}") };
+        let cluster_index = cluster_index as usize;
+        if cluster_index == clusters.len() {
+            clusters.push(cluster(0., cluster_index));
+        }
+        // the tool stores the center and half the size
+        clusters[cluster_index].platforms.push(element(x - sizex, y - sizey, z - sizez, 2. * sizex, 2. * sizey, 2. * sizez));
+    }
+    for numbers in entries("Button { cluster:") {
+        let &[cluster_index, x, y, z] = &numbers[..] else { panic!("unexpected button {numbers:?}") };
+        clusters[cluster_index as usize].buttons.push(element(x - 50., y - 50., z - 20., 100., 100., 20.));
+    }
+    for cluster in clusters.iter_mut().step_by(5) {
+        let first = cluster.platforms[0].clone();
+        cluster.lifts.push(element(first.x + first.sizex + 250., first.y, first.z, 400., 400., 50.));
+        cluster.pipes.push(element(first.x - 350., first.y, first.z, 100., 100., 300.));
+    }
+    RefunctMap { version: CURRENT_VERSION, clusters, snap: SnapSettings::default() }
+}
@@ -0,0 +1,151 @@
+//! Transformations of several elements at once.
+
+use crate::{Element, ElementPath, RefunctMap};
+
+/// Element within a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElementRef {
+    pub cluster: usize,
+    pub element: ElementPath,
+}
+
+/// Translation, rotation around the vertical axis through `pivot` and uniform scaling from `pivot`.
+///
+/// The scaling and rotation are applied first, then the translation.
+/// The `pivot` is cluster-absolute, i.e. its `z` includes the `z` of the clusters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupTransform {
+    pub translation: (f32, f32, f32),
+    /// in degrees
+    pub yaw: f32,
+    pub scale: f32,
+    pub pivot: (f32, f32, f32),
+}
+
+impl RefunctMap {
+    pub fn element(&self, element: ElementRef) -> Option<&Element> {
+        let cluster = self.clusters.get(element.cluster)?;
+        match element.element {
+            ElementPath::Original(element_type, index) => cluster.elements(element_type).get(index),
+            ElementPath::Spawned(index) => cluster.spawned.get(index).map(|spawned| &spawned.element),
+        }
+    }
+
+    pub fn element_mut(&mut self, element: ElementRef) -> Option<&mut Element> {
+        let cluster = self.clusters.get_mut(element.cluster)?;
+        match element.element {
+            ElementPath::Original(element_type, index) => cluster.elements_mut(element_type).get_mut(index),
+            ElementPath::Spawned(index) => cluster.spawned.get_mut(index).map(|spawned| &mut spawned.element),
+        }
+    }
+}
+
+/// Mean cluster-absolute location of the given elements, e.g. as pivot of a `GroupTransform`.
+///
+/// Elements which don't exist are ignored.
+/// The elements' `z` are relative to their clusters, so the `z` of their clusters is added.
+pub fn selection_center(map: &RefunctMap, selection: &[ElementRef]) -> Option<(f32, f32, f32)> {
+    let locations: Vec<_> = selection.iter()
+        .filter_map(|&element| map.element(element).map(|e| (e.x, e.y, map.clusters[element.cluster].z + e.z)))
+        .collect();
+    if locations.is_empty() {
+        return None;
+    }
+    let n = locations.len() as f32;
+    let (x, y, z) = locations.iter().fold((0., 0., 0.), |(x, y, z), &(ex, ey, ez)| (x + ex, y + ey, z + ez));
+    Some((x / n, y / n, z / n))
+}
+
+/// Transform all selected elements, ignoring elements which don't exist.
+///
+/// Elements selected multiple times are only transformed once.
+pub fn transform_elements(map: &mut RefunctMap, selection: &[ElementRef], transform: &GroupTransform) {
+    let GroupTransform { translation: (tx, ty, tz), yaw, scale, pivot: (px, py, pz) } = *transform;
+    let (sin, cos) = yaw.to_radians().sin_cos();
+    let mut done = Vec::with_capacity(selection.len());
+    for &element_ref in selection {
+        if done.contains(&element_ref) {
+            continue;
+        }
+        done.push(element_ref);
+        let Some(cluster_z) = map.clusters.get(element_ref.cluster).map(|cluster| cluster.z) else { continue };
+        let Some(e) = map.element_mut(element_ref) else { continue };
+        // only touch what changes, so that translations don't introduce rounding errors elsewhere
+        if scale != 1. || yaw != 0. {
+            let (dx, dy, dz) = ((e.x - px) * scale, (e.y - py) * scale, (cluster_z + e.z - pz) * scale);
+            e.x = px + dx * cos - dy * sin;
+            e.y = py + dx * sin + dy * cos;
+            e.z = pz + dz - cluster_z;
+        }
+        if yaw != 0. {
+            e.yaw = normalize_degrees(e.yaw + yaw);
+        }
+        if scale != 1. {
+            e.sizex *= scale;
+            e.sizey *= scale;
+            e.sizez *= scale;
+        }
+        e.x += tx;
+        e.y += ty;
+        e.z += tz;
+    }
+}
+
+/// map to (-180, 180]
+fn normalize_degrees(degrees: f32) -> f32 {
+    let degrees = degrees.rem_euclid(360.);
+    if degrees > 180. { degrees - 360. } else { degrees }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::original;
+    use crate::ElementType;
+    use super::*;
+
+    fn platform(cluster: usize, index: usize) -> ElementRef {
+        ElementRef { cluster, element: ElementPath::Original(ElementType::Platform, index) }
+    }
+
+    fn assert_close(a: (f32, f32, f32), b: (f32, f32, f32)) {
+        assert!((a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3 && (a.2 - b.2).abs() < 1e-3, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn center_is_cluster_absolute() {
+        let map = original();
+        // cluster 2 is 200 units higher than cluster 1
+        assert_eq!(selection_center(&map, &[platform(1, 0), platform(2, 0)]), Some((2250., 0., 100.)));
+        assert_eq!(selection_center(&map, &[platform(2, 0), platform(2, 5)]), Some((3000., 0., 200.)));
+        assert_eq!(selection_center(&map, &[platform(0, 5)]), None);
+    }
+
+    #[test]
+    fn elements_are_rotated_and_scaled_around_the_pivot() {
+        let mut map = original();
+        let selection = [platform(1, 0), platform(2, 0)];
+        let pivot = selection_center(&map, &selection).unwrap();
+        transform_elements(&mut map, &selection, &GroupTransform { translation: (0., 0., 10.), yaw: 90., scale: 2., pivot });
+        let (a, b) = (&map.clusters[1].platforms[0], &map.clusters[2].platforms[0]);
+        assert_close((a.x, a.y, a.z), (2250., -1500., -90.));
+        assert_close((b.x, b.y, b.z), (2250., 1500., 110.));
+        assert_eq!((a.yaw, a.sizex, b.sizez), (90., 2000., 200.));
+        // the clusters themselves don't move
+        assert_eq!(map.clusters[2].z, 200.);
+    }
+
+    #[test]
+    fn elements_are_transformed_once() {
+        let mut map = original();
+        let transform = GroupTransform { translation: (5., 0., 0.), yaw: 0., scale: 1., pivot: (0., 0., 0.) };
+        transform_elements(&mut map, &[platform(0, 0), platform(0, 0), platform(0, 9)], &transform);
+        assert_eq!(map.clusters[0].platforms[0].x, 5.);
+    }
+
+    #[test]
+    fn degrees_are_normalized() {
+        assert_eq!(normalize_degrees(190.), -170.);
+        assert_eq!(normalize_degrees(-180.), 180.);
+        assert_eq!(normalize_degrees(720.), 0.);
+    }
+}
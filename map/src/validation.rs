@@ -0,0 +1,225 @@
+use std::fmt::{Display, Formatter};
+use crate::{Cluster, Element, ElementType, RefunctMap, MAX_SPAWNED_PER_CLUSTER};
+
+/// Location of a value within a map, displayed like `clusters[3].platforms[2].sizex`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldPath {
+    pub cluster: usize,
+    pub element: Option<ElementPath>,
+    pub field: &'static str,
+}
+
+/// Element within a cluster.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementPath {
+    Original(ElementType, usize),
+    Spawned(usize),
+}
+
+impl Display for FieldPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "clusters[{}]", self.cluster)?;
+        match self.element {
+            Some(ElementPath::Original(element_type, index)) => write!(f, ".{}[{index}]", element_type.field_name())?,
+            Some(ElementPath::Spawned(index)) => write!(f, ".spawned[{index}]")?,
+            None => (),
+        }
+        write!(f, ".{}", self.field)
+    }
+}
+
+/// A single problem found in a map.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Diagnostic {
+    ClusterCount { expected: usize, found: usize },
+    ElementCount { cluster: usize, element_type: ElementType, expected: usize, found: usize },
+    NonFinite { path: FieldPath, value: f32 },
+    NonPositiveSize { path: FieldPath, value: f32 },
+    NotSpawnable { cluster: usize, index: usize, element_type: ElementType },
+    TooManySpawned { cluster: usize, found: usize },
+    RiseOrderOutOfRange { cluster: usize, found: usize, max: usize },
+    NegativeRiseDelay { cluster: usize, found: f32 },
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Diagnostic::ClusterCount { expected, found } => write!(f, "map has {found} clusters, but Refunct has {expected}"),
+            Diagnostic::ElementCount { cluster, element_type, expected, found } => {
+                let name = element_type.field_name();
+                write!(f, "clusters[{cluster}] has {found} {name}, but Refunct has {expected}")
+            }
+            Diagnostic::NonFinite { path, value } => write!(f, "{path} must be finite, but is {value}"),
+            Diagnostic::NonPositiveSize { path, value } => write!(f, "{path} must be greater than 0, but is {value}"),
+            Diagnostic::NotSpawnable { cluster, index, element_type } => write!(f, "clusters[{cluster}].spawned[{index}] is a {element_type:?}, which can't be spawned"),
+            Diagnostic::TooManySpawned { cluster, found } => write!(f, "clusters[{cluster}] spawns {found} elements, but at most {MAX_SPAWNED_PER_CLUSTER} are allowed"),
+            Diagnostic::RiseOrderOutOfRange { cluster, found, max } => write!(f, "clusters[{cluster}].rise_order must be at most {max}, but is {found}"),
+            Diagnostic::NegativeRiseDelay { cluster, found } => write!(f, "clusters[{cluster}].rise_delay must not be negative, but is {found}"),
+        }
+    }
+}
+
+/// Check a map against the original map, returning all problems found.
+///
+/// The number of clusters and original elements must match the original map, all values must be
+/// finite and all sizes positive.
+/// Only `ElementType::SPAWNABLE` elements can be spawned.
+/// Rise orders must be lower than the number of clusters and rise delays must not be negative.
+pub fn validate(map: &RefunctMap, original: &RefunctMap) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    if map.clusters.len() != original.clusters.len() {
+        diagnostics.push(Diagnostic::ClusterCount { expected: original.clusters.len(), found: map.clusters.len() });
+    }
+    for (cluster_index, cluster) in map.clusters.iter().enumerate() {
+        if let Some(original) = original.clusters.get(cluster_index) {
+            check_element_counts(&mut diagnostics, cluster_index, original, &ElementType::ALL, |element_type| cluster.elements(element_type).len());
+        }
+        check_finite(&mut diagnostics, FieldPath { cluster: cluster_index, element: None, field: "z" }, cluster.z);
+        check_finite(&mut diagnostics, FieldPath { cluster: cluster_index, element: None, field: "rise_speed" }, cluster.rise_speed);
+        let max_rise_order = original.clusters.len().saturating_sub(1);
+        if cluster.rise_order > max_rise_order {
+            diagnostics.push(Diagnostic::RiseOrderOutOfRange { cluster: cluster_index, found: cluster.rise_order, max: max_rise_order });
+        }
+        if !cluster.rise_delay.is_finite() {
+            check_finite(&mut diagnostics, FieldPath { cluster: cluster_index, element: None, field: "rise_delay" }, cluster.rise_delay);
+        } else if cluster.rise_delay < 0. {
+            diagnostics.push(Diagnostic::NegativeRiseDelay { cluster: cluster_index, found: cluster.rise_delay });
+        }
+        for element_type in ElementType::ALL {
+            for (element_index, element) in cluster.elements(element_type).iter().enumerate() {
+                check_element(&mut diagnostics, cluster_index, ElementPath::Original(element_type, element_index), element);
+            }
+        }
+        if cluster.spawned.len() > MAX_SPAWNED_PER_CLUSTER {
+            diagnostics.push(Diagnostic::TooManySpawned { cluster: cluster_index, found: cluster.spawned.len() });
+        }
+        for (index, spawned) in cluster.spawned.iter().enumerate() {
+            if !spawned.element_type.is_spawnable() {
+                diagnostics.push(Diagnostic::NotSpawnable { cluster: cluster_index, index, element_type: spawned.element_type });
+            }
+            check_element(&mut diagnostics, cluster_index, ElementPath::Spawned(index), &spawned.element);
+        }
+    }
+    diagnostics
+}
+
+/// `count` returns the number of elements of the given type in the checked cluster
+pub(crate) fn check_element_counts(diagnostics: &mut Vec<Diagnostic>, cluster_index: usize, original: &Cluster, element_types: &[ElementType], count: impl Fn(ElementType) -> usize) {
+    for &element_type in element_types {
+        let expected = original.elements(element_type).len();
+        let found = count(element_type);
+        if expected != found {
+            diagnostics.push(Diagnostic::ElementCount { cluster: cluster_index, element_type, expected, found });
+        }
+    }
+}
+
+fn check_element(diagnostics: &mut Vec<Diagnostic>, cluster: usize, element: ElementPath, e: &Element) {
+    let path = |field| FieldPath { cluster, element: Some(element), field };
+    for (field, value) in [("x", e.x), ("y", e.y), ("z", e.z), ("pitch", e.pitch), ("yaw", e.yaw), ("roll", e.roll)] {
+        check_finite(diagnostics, path(field), value);
+    }
+    for (field, value) in [("sizex", e.sizex), ("sizey", e.sizey), ("sizez", e.sizez)] {
+        if !value.is_finite() {
+            check_finite(diagnostics, path(field), value);
+        } else if value <= 0. {
+            diagnostics.push(Diagnostic::NonPositiveSize { path: path(field), value });
+        }
+    }
+}
+
+fn check_finite(diagnostics: &mut Vec<Diagnostic>, path: FieldPath, value: f32) {
+    if !value.is_finite() {
+        diagnostics.push(Diagnostic::NonFinite { path, value });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_maps::{element, original};
+    use crate::{ElementType, SpawnedElement};
+    use super::*;
+
+    #[test]
+    fn original_is_valid() {
+        assert_eq!(validate(&original(), &original()), Vec::new());
+    }
+
+    #[test]
+    fn counts_must_match_the_original() {
+        let mut map = original();
+        map.clusters[1].cubes.clear();
+        map.clusters.pop();
+        assert_eq!(validate(&map, &original()), vec![
+            Diagnostic::ClusterCount { expected: 3, found: 2 },
+            Diagnostic::ElementCount { cluster: 1, element_type: ElementType::Cube, expected: 1, found: 0 },
+        ]);
+    }
+
+    #[test]
+    fn values_must_be_finite_and_sizes_positive() {
+        let mut map = original();
+        map.clusters[0].z = f32::NAN;
+        map.clusters[1].platforms[1].yaw = f32::INFINITY;
+        map.clusters[2].buttons[0].sizez = 0.;
+        let diagnostics = validate(&map, &original());
+        assert_eq!(diagnostics.len(), 3);
+        assert!(matches!(diagnostics[0], Diagnostic::NonFinite { path: FieldPath { cluster: 0, element: None, field: "z" }, .. }));
+        assert_eq!(diagnostics[1], Diagnostic::NonFinite {
+            path: FieldPath { cluster: 1, element: Some(ElementPath::Original(ElementType::Platform, 1)), field: "yaw" },
+            value: f32::INFINITY,
+        });
+        assert_eq!(diagnostics[2].to_string(), "clusters[2].buttons[0].sizez must be greater than 0, but is 0");
+    }
+
+    #[test]
+    fn only_spawnable_elements_can_be_spawned() {
+        let mut map = original();
+        for element_type in ElementType::ALL {
+            map.clusters[0].spawned.push(SpawnedElement { element_type, element: element(0., 0., 0., 1., 1., 1.) });
+        }
+        assert_eq!(validate(&map, &original()), vec![
+            Diagnostic::NotSpawnable { cluster: 0, index: 2, element_type: ElementType::Button },
+            Diagnostic::NotSpawnable { cluster: 0, index: 4, element_type: ElementType::Pipe },
+        ]);
+    }
+
+    #[test]
+    fn spawned_elements_are_limited_per_cluster() {
+        let mut map = original();
+        let spawned = SpawnedElement { element_type: ElementType::Cube, element: element(0., 0., 0., 1., 1., 1.) };
+        map.clusters[1].spawned = vec![spawned.clone(); MAX_SPAWNED_PER_CLUSTER];
+        assert_eq!(validate(&map, &original()), Vec::new());
+        map.clusters[1].spawned.push(spawned);
+        assert_eq!(validate(&map, &original()), vec![
+            Diagnostic::TooManySpawned { cluster: 1, found: MAX_SPAWNED_PER_CLUSTER + 1 },
+        ]);
+    }
+
+    #[test]
+    fn spawned_elements_are_checked() {
+        let mut map = original();
+        let mut spawned = SpawnedElement { element_type: ElementType::Lift, element: element(0., 0., 0., 1., 1., 1.) };
+        spawned.element.sizey = -5.;
+        map.clusters[2].spawned.push(spawned);
+        let diagnostics = validate(&map, &original());
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].to_string(), "clusters[2].spawned[0].sizey must be greater than 0, but is -5");
+    }
+
+    #[test]
+    fn rise_order_and_delay_are_checked() {
+        let mut map = original();
+        map.clusters[0].rise_order = 2;
+        map.clusters[0].rise_delay = 1.5;
+        assert_eq!(validate(&map, &original()), Vec::new());
+        map.clusters[1].rise_order = 3;
+        map.clusters[2].rise_delay = -0.5;
+        assert_eq!(validate(&map, &original()), vec![
+            Diagnostic::RiseOrderOutOfRange { cluster: 1, found: 3, max: 2 },
+            Diagnostic::NegativeRiseDelay { cluster: 2, found: -0.5 },
+        ]);
+        map.clusters[2].rise_delay = f32::NAN;
+        assert!(matches!(validate(&map, &original())[1], Diagnostic::NonFinite { path: FieldPath { cluster: 2, element: None, field: "rise_delay" }, .. }));
+    }
+}
@@ -1,6 +1,15 @@
 use std::sync::atomic::{AtomicU32, Ordering};
 use serde::{Serialize, Deserialize};
 
+pub mod recording;
+
+/// Highest protocol version understood by this crate.
+///
+/// Clients announce their version when connecting to `/ws?version=<n>`, see `Response::ProtocolVersion`.
+/// * 1: `MoveSelf` and `MoveOther` only
+/// * 2: `MoveSelfV2` and `MoveOtherV2`
+pub const PROTOCOL_VERSION: u32 = 2;
+
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct PlayerId(u32);
 
@@ -17,13 +26,55 @@ impl PlayerId {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+/// Movement state of a player at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Movement {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+    pub vel_x: f32,
+    pub vel_y: f32,
+    pub vel_z: f32,
+    pub acc_x: f32,
+    pub acc_y: f32,
+    pub acc_z: f32,
+    /// UE's `EMovementMode`, e.g. 1 = walking, 3 = falling
+    pub movement_mode: u8,
+    /// unix timestamp in ms of the client when the state was captured, 0 if unknown
+    pub timestamp: u64,
+}
+
+impl Movement {
+    /// `EMovementMode::MOVE_None`, used if the movement mode is unknown
+    pub const MOVEMENT_MODE_NONE: u8 = 0;
+    /// `EMovementMode::MOVE_MAX`, all valid movement modes are below this
+    pub const MOVEMENT_MODE_MAX: u8 = 7;
+
+    /// Movement of an old client, which only sends its location and rotation.
+    pub fn from_location(x: f32, y: f32, z: f32, pitch: f32, yaw: f32, roll: f32) -> Movement {
+        Movement {
+            x, y, z, pitch, yaw, roll,
+            vel_x: 0., vel_y: 0., vel_z: 0.,
+            acc_x: 0., acc_y: 0., acc_z: 0.,
+            movement_mode: Movement::MOVEMENT_MODE_NONE,
+            timestamp: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Request {
     GetServerTime,
     /// room-name, player-name, red, green, blue, x, y, z, pitch, yaw, roll
     JoinRoom(String, String, f32, f32, f32, f32, f32, f32, f32, f32, f32),
     /// x, y, z, pitch, yaw, roll
+    ///
+    /// Only sent by old clients, which don't know about `MoveSelfV2`.
     MoveSelf(f32, f32, f32, f32, f32, f32),
+    MoveSelfV2(Movement),
     /// platform-id
     PressPlatform(u8),
     /// button-id
@@ -31,7 +82,7 @@ pub enum Request {
     NewGamePressed,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Response {
     /// unix timestamp
     ServerTime(u64),
@@ -39,7 +90,12 @@ pub enum Response {
     PlayerJoinedRoom(PlayerId, String, f32, f32, f32, f32, f32, f32, f32, f32, f32),
     PlayerLeftRoom(PlayerId),
     /// id, x, y, z, pitch, yaw, roll
+    ///
+    /// Sent to clients until they send their first `MoveSelfV2`.
     MoveOther(PlayerId, f32, f32, f32, f32, f32, f32),
+    /// Sent to clients which sent a `MoveSelfV2`.
+    /// Moves of old clients are forwarded without velocity and acceleration.
+    MoveOtherV2(PlayerId, Movement),
     /// platform-id
     PressPlatform(u8),
     /// button-id
@@ -48,4 +104,14 @@ pub enum Response {
     /// unix timestamp
     StartNewGameAt(u64),
     RoomNameTooLong,
+    /// The last request was dropped because the client sent too many requests.
+    /// Repeated violations result in a disconnect.
+    RateLimitExceeded,
+    /// The last request was dropped because it contained invalid values.
+    /// Repeated violations result in a disconnect.
+    InvalidRequest,
+    /// Sent first to clients which announced their protocol version when connecting,
+    /// the highest version understood by both sides.
+    /// Old servers don't send it, in which case version 1 must be used.
+    ProtocolVersion(u32),
 }
@@ -0,0 +1,59 @@
+use serde::{Serialize, Deserialize};
+use crate::PlayerId;
+
+/// Metadata of a recorded round, as listed by the server at `/recordings`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordingInfo {
+    pub id: String,
+    pub room: String,
+    /// unix timestamp of the synchronized new game start
+    pub start: u64,
+    /// unix timestamp of the last recorded event
+    pub end: u64,
+    pub players: Vec<String>,
+}
+
+/// A single round of a multiplayer room, from one synchronized new game start until
+/// the next one or until the room became empty.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Recording {
+    pub info: RecordingInfo,
+    /// players in the room when the round started
+    pub players: Vec<RecordedPlayer>,
+    pub events: Vec<RecordedEvent>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedPlayer {
+    pub id: PlayerId,
+    pub name: String,
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub pitch: f32,
+    pub yaw: f32,
+    pub roll: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// unix timestamp of the server when it received the event
+    pub timestamp: u64,
+    pub event: Event,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Event {
+    PlayerJoined(RecordedPlayer),
+    PlayerLeft(PlayerId),
+    /// id, x, y, z, pitch, yaw, roll
+    Move(PlayerId, f32, f32, f32, f32, f32, f32),
+    /// id, platform-id
+    PressPlatform(PlayerId, u8),
+    /// id, button-id
+    PressButton(PlayerId, u8),
+    NewGamePressed(PlayerId),
+}
@@ -3,8 +3,8 @@ use std::ffi::c_void;
 use std::mem;
 use std::sync::atomic::{AtomicPtr, Ordering};
 use crate::native::ue::{FVector, FRotator, FString, UeU64};
-use crate::native::{AMYCHARACTER_STATICCLASS, Args, REBO_DOESNT_START_SEMAPHORE, APLAYERCONTROLLER_GETVIEWPORTSIZE, ActorWrapper, ObjectWrapper, StructValueWrapper, BoolValueWrapper};
-use crate::native::reflection::UClass;
+use crate::native::{AMYCHARACTER_STATICCLASS, Args, REBO_DOESNT_START_SEMAPHORE, APLAYERCONTROLLER_GETVIEWPORTSIZE, ActorWrapper, ObjectWrapper, StructValueWrapper, BoolValueWrapper, UeScope, WeakObjectHandle};
+use crate::native::reflection::{UClass, UObject};
 
 static CURRENT_PLAYER: AtomicPtr<AMyCharacterUE> = AtomicPtr::new(std::ptr::null_mut());
 
@@ -43,6 +43,15 @@ impl AMyCharacter {
         self.0
     }
 
+    /// weak handle, which detects when the character is destroyed, e.g. by a level reload
+    pub fn handle(&self) -> WeakObjectHandle {
+        UeScope::with(|scope| scope.weak_handle(&unsafe { ObjectWrapper::new(self.0 as *mut UObject) }))
+    }
+    /// `None` if the character was destroyed
+    pub fn resolve(handle: WeakObjectHandle) -> Option<AMyCharacter> {
+        UeScope::with(|scope| scope.resolve(handle).ok().map(|object| AMyCharacter(object.as_ptr() as *mut AMyCharacterUE)))
+    }
+
     pub fn get_player() -> AMyCharacter {
         let current_player = CURRENT_PLAYER.load(Ordering::SeqCst);
         if current_player.is_null() {
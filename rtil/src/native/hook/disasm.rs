@@ -0,0 +1,251 @@
+//! Length disassembler for x86 and x86_64.
+//!
+//! Only decodes as much as needed to relocate the first instructions of a function:
+//! the length of each instruction, relative branches and rip-relative memory operands.
+//! Opcodes which are invalid but not listed as such are decoded as if they had a ModRM byte.
+
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    X86,
+    X64,
+}
+impl Mode {
+    /// mode of the code we are running in
+    #[cfg(target_arch = "x86_64")]
+    pub const HOST: Mode = Mode::X64;
+    #[cfg(target_arch = "x86")]
+    pub const HOST: Mode = Mode::X86;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Branch {
+    Jmp,
+    Call,
+    /// conditional jump with the condition code of `jcc` (`0x70 + cc` / `0x0f 0x80 + cc`)
+    Jcc(u8),
+    /// `loopne`, `loope`, `loop` or `jecxz` by their opcode, which only exist with a rel8
+    Loop(u8),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// nothing which depends on the location of the instruction
+    None,
+    /// relative branch with a displacement of `disp_size` bytes at the end of the instruction
+    Branch { branch: Branch, disp_size: usize },
+    /// memory operand relative to the end of the instruction with a 32bit displacement at `disp_offset`
+    RipRelative { disp_offset: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub len: usize,
+    /// number of legacy and REX prefix bytes before the opcode
+    pub prefix_len: usize,
+    pub operand: Operand,
+    /// execution doesn't continue with the next instruction, e.g. `ret` or `jmp`
+    pub ends_flow: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum DecodeError {
+    #[error("the instruction continues after the end of the code")]
+    Truncated,
+    #[error("the instruction is longer than 15 bytes")]
+    TooLong,
+    #[error("invalid or unsupported opcode {0:#04x}")]
+    UnsupportedOpcode(u8),
+}
+
+struct Reader<'a> {
+    code: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.code.get(self.pos).ok_or(DecodeError::Truncated)?;
+        self.pos += 1;
+        Ok(byte)
+    }
+    fn peek(&self) -> Result<u8, DecodeError> {
+        self.code.get(self.pos).copied().ok_or(DecodeError::Truncated)
+    }
+    fn skip(&mut self, len: usize) -> Result<(), DecodeError> {
+        if self.pos + len > self.code.len() {
+            return Err(DecodeError::Truncated);
+        }
+        self.pos += len;
+        Ok(())
+    }
+}
+
+/// Decode the first instruction of `code`.
+pub fn decode(code: &[u8], mode: Mode) -> Result<Instruction, DecodeError> {
+    let mut reader = Reader { code, pos: 0 };
+    let mut operand_size_override = false;
+    let mut address_size_override = false;
+    let mut opcode = loop {
+        match reader.byte()? {
+            0x66 => operand_size_override = true,
+            0x67 => address_size_override = true,
+            0xf0 | 0xf2 | 0xf3 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 => (),
+            opcode => break opcode,
+        }
+    };
+    let mut rex_w = false;
+    if mode == Mode::X64 && opcode & 0xf0 == 0x40 {
+        rex_w = opcode & 0x08 != 0;
+        opcode = reader.byte()?;
+    }
+    let prefix_len = reader.pos - 1;
+
+    let imm_z = if operand_size_override { 2 } else { 4 };
+    let x64_invalid = |opcode| if mode == Mode::X64 { Err(DecodeError::UnsupportedOpcode(opcode)) } else { Ok(()) };
+
+    let mut has_modrm = false;
+    let mut imm_size = 0;
+    let mut branch = None;
+    let mut ends_flow = false;
+    // `test` has an immediate, `not`, `neg`, `mul`, `imul`, `div` and `idiv` sharing its opcode don't
+    let mut imm_size_of_test = None;
+
+    match opcode {
+        0x0f => {
+            let opcode = reader.byte()?;
+            match opcode {
+                // 3DNow! has its opcode as suffix
+                0x0f => { has_modrm = true; imm_size = 1 },
+                0x38 => { reader.byte()?; has_modrm = true },
+                0x3a => { reader.byte()?; has_modrm = true; imm_size = 1 },
+                0x05..=0x09 | 0x0e | 0x30..=0x37 | 0x77 | 0xa0..=0xa2 | 0xa8..=0xaa | 0xc8..=0xcf => (),
+                0x0b => ends_flow = true,
+                0x80..=0x8f => {
+                    if operand_size_override {
+                        return Err(DecodeError::UnsupportedOpcode(opcode));
+                    }
+                    branch = Some(Branch::Jcc(opcode & 0x0f));
+                    imm_size = 4;
+                },
+                0x70..=0x73 | 0xa4 | 0xac | 0xba | 0xc2 | 0xc4..=0xc6 => { has_modrm = true; imm_size = 1 },
+                _ => has_modrm = true,
+            }
+        },
+        0xc4 | 0xc5 | 0x62 if mode == Mode::X64 || reader.peek()? >= 0xc0 => {
+            // VEX (0xc4, 0xc5) and EVEX (0x62), which are `les`, `lds` and `bound` in 32bit if mod != 11
+            let map = match opcode {
+                0xc5 => { reader.byte()?; 1 },
+                0xc4 => { let map = reader.byte()? & 0x1f; reader.byte()?; map },
+                _ => { let map = reader.byte()? & 0x07; reader.skip(2)?; map },
+            };
+            let opcode = reader.byte()?;
+            match (map, opcode) {
+                // vzeroupper / vzeroall
+                (1, 0x77) => (),
+                (1, 0x70..=0x73 | 0xc2 | 0xc4..=0xc6) | (3, _) => { has_modrm = true; imm_size = 1 },
+                (1..=3, _) => has_modrm = true,
+                _ => return Err(DecodeError::UnsupportedOpcode(opcode)),
+            }
+        },
+        0x00..=0x3f => match opcode & 0x07 {
+            0..=3 => has_modrm = true,
+            4 => imm_size = 1,
+            5 => imm_size = imm_z,
+            // push / pop segment registers and BCD instructions
+            _ => x64_invalid(opcode)?,
+        },
+        0x40..=0x5f | 0x6c..=0x6f | 0x90..=0x99 | 0x9b..=0x9f | 0xa4..=0xa7 | 0xaa..=0xaf
+        | 0xc9 | 0xd7 | 0xec..=0xef | 0xf1 | 0xf5 | 0xf8..=0xfd => (),
+        0x60 | 0x61 | 0xce | 0xd6 => x64_invalid(opcode)?,
+        0x62 | 0xc4 | 0xc5 => has_modrm = true,
+        0x63 | 0x84..=0x8f | 0xd0..=0xd3 | 0xd8..=0xdf | 0xfe => has_modrm = true,
+        0x68 => imm_size = imm_z,
+        0x69 | 0x81 | 0xc7 => { has_modrm = true; imm_size = imm_z },
+        0x6a | 0xa8 | 0xb0..=0xb7 | 0xcd | 0xe4..=0xe7 => imm_size = 1,
+        0x6b | 0x80 | 0x83 | 0xc0 | 0xc1 | 0xc6 => { has_modrm = true; imm_size = 1 },
+        0x82 => { x64_invalid(opcode)?; has_modrm = true; imm_size = 1 },
+        0x70..=0x7f => { branch = Some(Branch::Jcc(opcode & 0x0f)); imm_size = 1 },
+        0x9a => { x64_invalid(opcode)?; imm_size = imm_z + 2 },
+        0xa0..=0xa3 => imm_size = match (mode, address_size_override) {
+            (Mode::X64, false) => 8,
+            (Mode::X64, true) | (Mode::X86, false) => 4,
+            (Mode::X86, true) => 2,
+        },
+        0xa9 => imm_size = imm_z,
+        0xb8..=0xbf => imm_size = if rex_w { 8 } else { imm_z },
+        0xc2 | 0xca => { imm_size = 2; ends_flow = true },
+        0xc3 | 0xcb | 0xcc | 0xcf | 0xf4 => ends_flow = true,
+        0xc8 => imm_size = 3,
+        0xd4 | 0xd5 => { x64_invalid(opcode)?; imm_size = 1 },
+        0xe0..=0xe3 => { branch = Some(Branch::Loop(opcode)); imm_size = 1 },
+        0xe8 | 0xe9 => {
+            // rel16 in 32bit, ignored in 64bit by Intel but not by AMD
+            if operand_size_override {
+                return Err(DecodeError::UnsupportedOpcode(opcode));
+            }
+            branch = Some(if opcode == 0xe8 { Branch::Call } else { Branch::Jmp });
+            imm_size = 4;
+            ends_flow = opcode == 0xe9;
+        },
+        0xea => { x64_invalid(opcode)?; imm_size = imm_z + 2; ends_flow = true },
+        0xeb => { branch = Some(Branch::Jmp); imm_size = 1; ends_flow = true },
+        0xf6 => { has_modrm = true; imm_size_of_test = Some(1) },
+        0xf7 => { has_modrm = true; imm_size_of_test = Some(imm_z) },
+        0xff => has_modrm = true,
+        // prefixes are consumed above, unless they follow a REX prefix, which CPUs then ignore
+        0x64..=0x67 | 0xf0 | 0xf2 | 0xf3 => return Err(DecodeError::UnsupportedOpcode(opcode)),
+    }
+
+    let mut operand = Operand::None;
+    if has_modrm {
+        let modrm = reader.byte()?;
+        let (md, reg, rm) = (modrm >> 6, (modrm >> 3) & 0x07, modrm & 0x07);
+        if let Some(size) = imm_size_of_test {
+            if reg <= 1 {
+                imm_size = size;
+            }
+        }
+        // indirect jmp near and far
+        if opcode == 0xff && (reg == 4 || reg == 5) {
+            ends_flow = true;
+        }
+        if md != 0b11 {
+            if mode == Mode::X86 && address_size_override {
+                // 16bit addressing
+                match md {
+                    0b00 if rm == 0b110 => reader.skip(2)?,
+                    0b01 => reader.skip(1)?,
+                    0b10 => reader.skip(2)?,
+                    _ => (),
+                }
+            } else {
+                if rm == 0b100 {
+                    let sib = reader.byte()?;
+                    if md == 0b00 && sib & 0x07 == 0b101 {
+                        reader.skip(4)?;
+                    }
+                } else if md == 0b00 && rm == 0b101 {
+                    // absolute in 32bit, rip-relative in 64bit
+                    if mode == Mode::X64 {
+                        operand = Operand::RipRelative { disp_offset: reader.pos };
+                    }
+                    reader.skip(4)?;
+                }
+                match md {
+                    0b01 => reader.skip(1)?,
+                    0b10 => reader.skip(4)?,
+                    _ => (),
+                }
+            }
+        }
+    }
+    reader.skip(imm_size)?;
+    if let Some(branch) = branch {
+        operand = Operand::Branch { branch, disp_size: imm_size };
+    }
+    if reader.pos > 15 {
+        return Err(DecodeError::TooLong);
+    }
+    Ok(Instruction { len: reader.pos, prefix_len, operand, ends_flow })
+}
@@ -0,0 +1,235 @@
+//! Inline hooks with a callable original.
+//!
+//! The first instructions of the target function are overwritten with a `jmp rel32` to the detour.
+//! They are decoded with a length disassembler and relocated into a trampoline, followed by a jump
+//! back to the rest of the target.
+//! The trampoline can be called like the original function while the hook is enabled.
+//!
+//! On x86_64 the trampoline is allocated within ±2GB of the target, such that relocated branches
+//! and rip-relative operands can still reach their destination.
+//! The detour is reached through an absolute jump placed in the trampoline.
+
+use std::slice;
+use std::sync::Mutex;
+use once_cell::sync::Lazy;
+use thiserror::Error;
+use crate::native::{alloc_page_near, free_page, make_rwx, make_rx};
+
+mod disasm;
+#[cfg(all(test, unix))]
+mod tests;
+
+pub use disasm::{decode, Branch, DecodeError, Instruction, Mode, Operand};
+
+/// size of the `jmp rel32` written to the target
+const JMP_REL32_LEN: usize = 5;
+/// size of `jmp [rip+0]` followed by the absolute address
+const JMP_ABS64_LEN: usize = 14;
+/// longest possible prologue: 4 bytes of instructions followed by an instruction of 15 bytes
+const MAX_PROLOGUE_LEN: usize = JMP_REL32_LEN - 1 + 15;
+
+/// Patches of different hooks may be on the same page, whose protection must only be restored
+/// once no other patch is written.
+static PATCH_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Debug, Error)]
+pub enum HookError {
+    #[error("can't decode the instruction at offset {offset}: {error}")]
+    Decode { offset: usize, error: DecodeError },
+    #[error("the function ends after {0} bytes, which is too short to be hooked")]
+    FunctionTooShort(usize),
+    #[error("the instruction at offset {0} branches into the overwritten instructions")]
+    BranchIntoPrologue(usize),
+    #[error("the relocated instruction at offset {0} can't reach its destination")]
+    OutOfRange(usize),
+    #[error("can't allocate a trampoline near the function")]
+    NoMemory,
+}
+
+/// Decode the instructions overwritten by the `jmp rel32` to the detour.
+pub fn decode_prologue(code: &[u8], mode: Mode) -> Result<Vec<Instruction>, HookError> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < JMP_REL32_LEN {
+        let instruction = decode(&code[offset..], mode)
+            .map_err(|error| HookError::Decode { offset, error })?;
+        offset += instruction.len;
+        instructions.push(instruction);
+        // anything following could be another function or padding
+        if instruction.ends_flow && offset < JMP_REL32_LEN {
+            return Err(HookError::FunctionTooShort(offset));
+        }
+    }
+    Ok(instructions)
+}
+
+/// Displacement of a rel32 ending at `end` to reach `target`.
+fn rel32(end: usize, target: usize, mode: Mode) -> Option<i32> {
+    match mode {
+        // everything is reachable as the address space wraps around
+        Mode::X86 => Some((target as u32).wrapping_sub(end as u32) as i32),
+        Mode::X64 => i32::try_from(target as i64 - end as i64).ok(),
+    }
+}
+
+/// Copy the instructions originally located at `from` to be executed at `to`, followed by a
+/// jump back to the instruction after them.
+///
+/// Relative branches are converted to their rel32 form, `loop` and `jecxz` get an additional jump.
+pub fn relocate(code: &[u8], instructions: &[Instruction], from: usize, to: usize, mode: Mode) -> Result<Vec<u8>, HookError> {
+    let prologue_len: usize = instructions.iter().map(|instruction| instruction.len).sum();
+    let mut out = Vec::new();
+    let mut offset = 0;
+    for instruction in instructions {
+        let bytes = &code[offset..offset + instruction.len];
+        let next = from + offset + instruction.len;
+        match instruction.operand {
+            Operand::None => out.extend_from_slice(bytes),
+            Operand::RipRelative { disp_offset } => {
+                let disp = i32::from_le_bytes(bytes[disp_offset..disp_offset + 4].try_into().unwrap());
+                let destination = next.wrapping_add(disp as isize as usize);
+                let new_next = to + out.len() + instruction.len;
+                let disp = rel32(new_next, destination, mode).ok_or(HookError::OutOfRange(offset))?;
+                out.extend_from_slice(bytes);
+                let start = out.len() - instruction.len + disp_offset;
+                out[start..start + 4].copy_from_slice(&disp.to_le_bytes());
+            },
+            Operand::Branch { branch, disp_size } => {
+                let disp = match disp_size {
+                    1 => bytes[instruction.len - 1] as i8 as i32,
+                    _ => i32::from_le_bytes(bytes[instruction.len - 4..].try_into().unwrap()),
+                };
+                let destination = next.wrapping_add(disp as isize as usize);
+                if (from..from + prologue_len).contains(&destination) {
+                    return Err(HookError::BranchIntoPrologue(offset));
+                }
+                out.extend_from_slice(&bytes[..instruction.prefix_len]);
+                match branch {
+                    Branch::Jmp => out.push(0xe9),
+                    Branch::Call => out.push(0xe8),
+                    Branch::Jcc(condition) => out.extend_from_slice(&[0x0f, 0x80 + condition]),
+                    // loop to the jmp rel32, otherwise skip it
+                    Branch::Loop(opcode) => out.extend_from_slice(&[opcode, 0x02, 0xeb, 0x05, 0xe9]),
+                }
+                let disp = rel32(to + out.len() + 4, destination, mode).ok_or(HookError::OutOfRange(offset))?;
+                out.extend_from_slice(&disp.to_le_bytes());
+            },
+        }
+        offset += instruction.len;
+    }
+    out.push(0xe9);
+    let disp = rel32(to + out.len() + 4, from + prologue_len, mode).ok_or(HookError::OutOfRange(prologue_len))?;
+    out.extend_from_slice(&disp.to_le_bytes());
+    Ok(out)
+}
+
+/// Write to code, which may currently be executed by other threads.
+unsafe fn write_code(addr: usize, bytes: &[u8]) {
+    let _lock = PATCH_LOCK.lock().unwrap();
+    let last = addr + bytes.len() - 1;
+    make_rwx(addr);
+    make_rwx(last);
+    slice::from_raw_parts_mut(addr as *mut u8, bytes.len()).copy_from_slice(bytes);
+    make_rx(addr);
+    make_rx(last);
+}
+
+/// Hook redirecting all calls of the target to the detour, which must have the same signature.
+///
+/// The hook is disabled and its trampoline freed when it's dropped, which must not happen while
+/// the original is executed.
+pub struct InlineHook {
+    target: usize,
+    trampoline: usize,
+    original_bytes: Vec<u8>,
+    patch: Vec<u8>,
+    is_enabled: bool,
+}
+
+impl InlineHook {
+    /// Prepare the hook without enabling it.
+    ///
+    /// # Safety
+    ///
+    /// `target` must point to the start of a function, whose first instructions aren't jumped to
+    /// from elsewhere in the function.
+    pub unsafe fn new(target: usize, detour: usize) -> Result<InlineHook, HookError> {
+        let mode = Mode::HOST;
+        let code = slice::from_raw_parts(target as *const u8, MAX_PROLOGUE_LEN);
+        let instructions = decode_prologue(code, mode)?;
+        let prologue_len: usize = instructions.iter().map(|instruction| instruction.len).sum();
+
+        let trampoline = alloc_page_near(target).ok_or(HookError::NoMemory)?;
+        let result = (|| -> Result<Vec<u8>, HookError> {
+            let mut code = relocate(code, &instructions, target, trampoline, mode)?;
+            let jump_target = match mode {
+                Mode::X86 => detour,
+                Mode::X64 => {
+                    // the detour may be more than 2GB away from the target, but the trampoline isn't
+                    let relay = trampoline + code.len();
+                    code.extend_from_slice(&[0xff, 0x25, 0, 0, 0, 0]);
+                    code.extend_from_slice(&(detour as u64).to_le_bytes());
+                    debug_assert_eq!(code.len(), relay - trampoline + JMP_ABS64_LEN);
+                    relay
+                },
+            };
+            let mut patch = vec![0xe9];
+            let disp = rel32(target + JMP_REL32_LEN, jump_target, mode).ok_or(HookError::OutOfRange(0))?;
+            patch.extend_from_slice(&disp.to_le_bytes());
+            // never executed, trap if we messed up
+            patch.resize(prologue_len, 0xcc);
+            slice::from_raw_parts_mut(trampoline as *mut u8, code.len()).copy_from_slice(&code);
+            make_rx(trampoline);
+            Ok(patch)
+        })();
+        let patch = match result {
+            Ok(patch) => patch,
+            Err(e) => {
+                free_page(trampoline);
+                return Err(e);
+            },
+        };
+        Ok(InlineHook {
+            target,
+            trampoline,
+            original_bytes: code[..prologue_len].to_vec(),
+            patch,
+            is_enabled: false,
+        })
+    }
+
+    /// Address of the trampoline, which behaves like the unhooked target.
+    pub fn original(&self) -> usize {
+        self.trampoline
+    }
+
+    /// Redirect calls of the target to the detour.
+    ///
+    /// # Safety
+    ///
+    /// No other thread may execute the overwritten instructions while the hook is enabled.
+    pub unsafe fn enable(&mut self) {
+        if !self.is_enabled {
+            write_code(self.target, &self.patch);
+            self.is_enabled = true;
+        }
+    }
+    /// Restore the overwritten instructions of the target.
+    ///
+    /// # Safety
+    ///
+    /// No other thread may execute the overwritten instructions while the hook is disabled.
+    pub unsafe fn disable(&mut self) {
+        if self.is_enabled {
+            write_code(self.target, &self.original_bytes);
+            self.is_enabled = false;
+        }
+    }
+}
+
+impl Drop for InlineHook {
+    fn drop(&mut self) {
+        unsafe { self.disable() };
+        free_page(self.trampoline);
+    }
+}
@@ -0,0 +1,294 @@
+//! Tests of the length disassembler, the relocation and of hooks on synthetic functions.
+
+use std::cell::Cell;
+use std::mem;
+use super::{decode, decode_prologue, relocate, Branch, DecodeError, HookError, InlineHook, Mode, Operand};
+
+type UnaryFn = unsafe extern "C" fn(i32) -> i32;
+
+std::arch::global_asm!(
+    // plain prologue
+    ".globl rtil_hook_test_plain",
+    "rtil_hook_test_plain:",
+    "push rbp",
+    "mov rbp, rsp",
+    "lea eax, [rdi + 5]",
+    "pop rbp",
+    "ret",
+
+    // rip-relative load
+    ".globl rtil_hook_test_rip_relative",
+    "rtil_hook_test_rip_relative:",
+    "mov eax, dword ptr [rip + .Lrtil_hook_test_data]",
+    "add eax, edi",
+    "ret",
+
+    // short conditional jump behind the overwritten bytes
+    ".globl rtil_hook_test_short_branch",
+    "rtil_hook_test_short_branch:",
+    "test edi, edi",
+    "je .Lshort_branch_zero",
+    "mov eax, 1",
+    "ret",
+    ".Lshort_branch_zero:",
+    "mov eax, 2",
+    "ret",
+
+    // call as first instruction
+    ".globl rtil_hook_test_call",
+    "rtil_hook_test_call:",
+    "call .Lforty",
+    "add eax, edi",
+    "ret",
+    ".Lforty:",
+    "mov eax, 40",
+    "ret",
+
+    // jrcxz only exists with a rel8
+    ".globl rtil_hook_test_jrcxz",
+    "rtil_hook_test_jrcxz:",
+    "mov ecx, edi",
+    "jrcxz .Ljrcxz_zero",
+    "mov eax, 1",
+    "ret",
+    ".Ljrcxz_zero:",
+    "mov eax, 2",
+    "ret",
+
+    // too short to be hooked, followed by the next function
+    ".globl rtil_hook_test_too_short",
+    "rtil_hook_test_too_short:",
+    "xor eax, eax",
+    "ret",
+
+    // loop within the overwritten bytes
+    ".globl rtil_hook_test_loop",
+    "rtil_hook_test_loop:",
+    "sub edi, 1",
+    "jnz rtil_hook_test_loop",
+    "mov eax, edi",
+    "ret",
+
+    ".pushsection .data",
+    ".Lrtil_hook_test_data:",
+    ".long 100",
+    ".popsection",
+);
+
+extern "C" {
+    fn rtil_hook_test_plain(x: i32) -> i32;
+    fn rtil_hook_test_rip_relative(x: i32) -> i32;
+    fn rtil_hook_test_short_branch(x: i32) -> i32;
+    fn rtil_hook_test_call(x: i32) -> i32;
+    fn rtil_hook_test_jrcxz(x: i32) -> i32;
+    fn rtil_hook_test_too_short(x: i32) -> i32;
+    fn rtil_hook_test_loop(x: i32) -> i32;
+}
+
+thread_local! {
+    /// trampoline of the function hooked by the current test
+    static ORIGINAL: Cell<usize> = const { Cell::new(0) };
+}
+
+extern "C" fn detour(x: i32) -> i32 {
+    let original: UnaryFn = unsafe { mem::transmute(ORIGINAL.with(Cell::get)) };
+    unsafe { original(x) * 10 }
+}
+
+/// Hook the function with a detour multiplying the result of the original by 10.
+fn check_hook(function: UnaryFn, calls: &[(i32, i32)]) {
+    let check = |hooked: bool| for &(arg, expected) in calls {
+        let expected = if hooked { expected * 10 } else { expected };
+        assert_eq!(unsafe { function(arg) }, expected, "hooked: {hooked}, arg: {arg}");
+    };
+    let mut hook = unsafe { InlineHook::new(function as usize, detour as *const () as usize) }.unwrap();
+    ORIGINAL.with(|original| original.set(hook.original()));
+    let original: UnaryFn = unsafe { mem::transmute(hook.original()) };
+    check(false);
+
+    unsafe { hook.enable() };
+    check(true);
+    for &(arg, expected) in calls {
+        assert_eq!(unsafe { original(arg) }, expected);
+    }
+    unsafe { hook.disable() };
+    check(false);
+    // enabling twice must keep the original bytes
+    unsafe { hook.enable(); hook.enable() };
+    check(true);
+    drop(hook);
+    check(false);
+}
+
+#[test]
+fn hook_plain() {
+    check_hook(rtil_hook_test_plain, &[(1, 6), (-5, 0)]);
+}
+
+#[test]
+fn hook_rip_relative() {
+    check_hook(rtil_hook_test_rip_relative, &[(1, 101)]);
+}
+
+#[test]
+fn hook_short_branch() {
+    check_hook(rtil_hook_test_short_branch, &[(0, 2), (7, 1)]);
+}
+
+#[test]
+fn hook_call() {
+    check_hook(rtil_hook_test_call, &[(2, 42)]);
+}
+
+#[test]
+fn hook_jrcxz() {
+    check_hook(rtil_hook_test_jrcxz, &[(0, 2), (5, 1)]);
+}
+
+#[test]
+fn unhookable_functions() {
+    let hook = |function: UnaryFn| unsafe { InlineHook::new(function as usize, detour as *const () as usize) }.err();
+    assert!(matches!(hook(rtil_hook_test_too_short), Some(HookError::FunctionTooShort(3))));
+    assert!(matches!(hook(rtil_hook_test_loop), Some(HookError::BranchIntoPrologue(3))));
+}
+
+fn check_decode(mode: Mode, cases: &[(&[u8], usize, Operand)]) {
+    for &(code, len, operand) in cases {
+        let instruction = decode(code, mode).unwrap_or_else(|e| panic!("{code:02x?}: {e}"));
+        assert_eq!((instruction.len, instruction.operand), (len, operand), "{code:02x?}");
+    }
+}
+
+#[test]
+fn decode_x64() {
+    let rip = |disp_offset| Operand::RipRelative { disp_offset };
+    let branch = |branch, disp_size| Operand::Branch { branch, disp_size };
+    check_decode(Mode::X64, &[
+        // push rbp
+        (&[0x55], 1, Operand::None),
+        // mov rbp, rsp
+        (&[0x48, 0x89, 0xe5], 3, Operand::None),
+        // sub rsp, 0x20
+        (&[0x48, 0x83, 0xec, 0x20], 4, Operand::None),
+        // sub rsp, 0x100
+        (&[0x48, 0x81, 0xec, 0x00, 0x01, 0x00, 0x00], 7, Operand::None),
+        // push r15
+        (&[0x41, 0x57], 2, Operand::None),
+        // endbr64
+        (&[0xf3, 0x0f, 0x1e, 0xfa], 4, Operand::None),
+        // nop word [rax + rax]
+        (&[0x66, 0x0f, 0x1f, 0x44, 0x00, 0x00], 6, Operand::None),
+        // mov rax, imm64
+        (&[0x48, 0xb8, 1, 2, 3, 4, 5, 6, 7, 8], 10, Operand::None),
+        // mov eax, [moffs64]
+        (&[0xa1, 1, 2, 3, 4, 5, 6, 7, 8], 9, Operand::None),
+        // mov rax, fs:[0x28]
+        (&[0x64, 0x48, 0x8b, 0x04, 0x25, 0x28, 0x00, 0x00, 0x00], 9, Operand::None),
+        // mov rax, [rsp + 8]
+        (&[0x48, 0x8b, 0x44, 0x24, 0x08], 5, Operand::None),
+        // test cl, 1
+        (&[0xf6, 0xc1, 0x01], 3, Operand::None),
+        // test ecx, imm32
+        (&[0xf7, 0xc1, 1, 2, 3, 4], 6, Operand::None),
+        // neg eax
+        (&[0xf7, 0xd8], 2, Operand::None),
+        // mov word [rbp - 16], 0x1234
+        (&[0x66, 0xc7, 0x45, 0xf0, 0x34, 0x12], 6, Operand::None),
+        // vzeroupper
+        (&[0xc5, 0xf8, 0x77], 3, Operand::None),
+        // vpshufd xmm0, xmm0, 0x1b
+        (&[0xc5, 0xf9, 0x70, 0xc0, 0x1b], 5, Operand::None),
+        // lea rax, [rip + disp]
+        (&[0x48, 0x8d, 0x05, 1, 2, 3, 4], 7, rip(3)),
+        // mov dword [rip + disp], imm32
+        (&[0xc7, 0x05, 1, 2, 3, 4, 5, 6, 7, 8], 10, rip(2)),
+        // vbroadcastss xmm0, [rip + disp]
+        (&[0xc4, 0xe2, 0x79, 0x18, 0x05, 1, 2, 3, 4], 9, rip(5)),
+        // jmp [rip + disp]
+        (&[0xff, 0x25, 1, 2, 3, 4], 6, rip(2)),
+        (&[0xe8, 1, 2, 3, 4], 5, branch(Branch::Call, 4)),
+        (&[0xe9, 1, 2, 3, 4], 5, branch(Branch::Jmp, 4)),
+        (&[0xeb, 0xfe], 2, branch(Branch::Jmp, 1)),
+        // je rel8 and rel32
+        (&[0x74, 0x05], 2, branch(Branch::Jcc(4), 1)),
+        (&[0x0f, 0x84, 1, 2, 3, 4], 6, branch(Branch::Jcc(4), 4)),
+        // jrcxz
+        (&[0xe3, 0x10], 2, branch(Branch::Loop(0xe3), 1)),
+    ]);
+
+    assert!(decode(&[0xc3], Mode::X64).unwrap().ends_flow);
+    assert!(decode(&[0xff, 0xe0], Mode::X64).unwrap().ends_flow);
+    assert!(!decode(&[0xff, 0xd0], Mode::X64).unwrap().ends_flow);
+    assert_eq!(decode(&[0xf3, 0x48, 0xab], Mode::X64).unwrap().prefix_len, 2);
+    assert_eq!(decode(&[0x06], Mode::X64), Err(DecodeError::UnsupportedOpcode(0x06)));
+    assert_eq!(decode(&[0x48], Mode::X64), Err(DecodeError::Truncated));
+    // legacy prefixes after a REX prefix
+    assert_eq!(decode(&[0x48, 0x66, 0x89, 0xe5], Mode::X64), Err(DecodeError::UnsupportedOpcode(0x66)));
+    assert_eq!(decode(&[0x41, 0xf3, 0x90], Mode::X64), Err(DecodeError::UnsupportedOpcode(0xf3)));
+    assert_eq!(decode(&[0x48, 0x8d, 0x05, 1, 2], Mode::X64), Err(DecodeError::Truncated));
+    assert_eq!(decode(&[[0x66; 14].as_slice(), &[0x90]].concat(), Mode::X64).unwrap().len, 15);
+    assert_eq!(decode(&[[0x66; 15].as_slice(), &[0x90]].concat(), Mode::X64), Err(DecodeError::TooLong));
+}
+
+#[test]
+fn decode_x86() {
+    check_decode(Mode::X86, &[
+        // push ebp
+        (&[0x55], 1, Operand::None),
+        // mov ebp, esp
+        (&[0x8b, 0xec], 2, Operand::None),
+        // sub esp, 0x10
+        (&[0x83, 0xec, 0x10], 3, Operand::None),
+        // push -1
+        (&[0x6a, 0xff], 2, Operand::None),
+        // push imm32
+        (&[0x68, 1, 2, 3, 4], 5, Operand::None),
+        // inc eax, which is a REX prefix in 64bit
+        (&[0x40], 1, Operand::None),
+        // mov eax, fs:[0]
+        (&[0x64, 0xa1, 0x00, 0x00, 0x00, 0x00], 6, Operand::None),
+        // mov ecx, [abs32], which is rip-relative in 64bit
+        (&[0x8b, 0x0d, 1, 2, 3, 4], 6, Operand::None),
+        // 16bit addressing: mov eax, [bp + 2] and mov eax, [0x1234]
+        (&[0x67, 0x8b, 0x46, 0x02], 4, Operand::None),
+        (&[0x67, 0x8b, 0x06, 0x34, 0x12], 5, Operand::None),
+        // les eax, [esi] and vzeroupper share their opcode
+        (&[0xc4, 0x06], 2, Operand::None),
+        (&[0xc5, 0xf8, 0x77], 3, Operand::None),
+        (&[0xe8, 1, 2, 3, 4], 5, Operand::Branch { branch: Branch::Call, disp_size: 4 }),
+    ]);
+
+    let ret = decode(&[0xc2, 0x08, 0x00], Mode::X86).unwrap();
+    assert_eq!((ret.len, ret.ends_flow), (3, true));
+    assert_eq!(decode(&[0x66, 0xe8, 1, 2], Mode::X86), Err(DecodeError::UnsupportedOpcode(0xe8)));
+}
+
+#[test]
+fn relocate_branches() {
+    let relocate = |code: &[u8], from, to, mode| {
+        let instructions = decode_prologue(code, mode)?;
+        relocate(code, &instructions, from, to, mode)
+    };
+    let rel32 = |disp: i32| disp.to_le_bytes();
+
+    // je +5; mov rbp, rsp
+    let code = [0x74, 0x05, 0x48, 0x89, 0xe5];
+    let expected = [&[0x0f, 0x84], &rel32(0x1007 - 0x2006)[..], &[0x48, 0x89, 0xe5, 0xe9], &rel32(0x1005 - 0x200e)].concat();
+    assert_eq!(relocate(&code, 0x1000, 0x2000, Mode::X64).unwrap(), expected);
+
+    // loop +0x10; mov rbp, rsp
+    let code = [0xe2, 0x10, 0x48, 0x89, 0xe5];
+    let expected = [&[0xe2, 0x02, 0xeb, 0x05, 0xe9], &rel32(0x1012 - 0x2009)[..], &[0x48, 0x89, 0xe5, 0xe9], &rel32(0x1005 - 0x2011)].concat();
+    assert_eq!(relocate(&code, 0x1000, 0x2000, Mode::X64).unwrap(), expected);
+
+    // call +0 in 32bit, where the displacement wraps around
+    let code = [0xe8, 0, 0, 0, 0];
+    let expected = [&[0xe8], &rel32(0x11000)[..], &[0xe9], &rel32(0x10ffb)].concat();
+    assert_eq!(relocate(&code, 0x1000, 0xffff_0000, Mode::X86).unwrap(), expected);
+
+    // mov rax, [rip + 0] can't reach its data from more than 2GB away
+    let code = [0x48, 0x8b, 0x05, 0, 0, 0, 0];
+    assert!(matches!(relocate(&code, 0x1000_0000, 0x1_0000_0000, Mode::X64), Err(HookError::OutOfRange(0))));
+    let expected = [&[0x48, 0x8b, 0x05], &rel32(-0x1000)[..], &[0xe9], &rel32(0x1007 - 0x200c)].concat();
+    assert_eq!(relocate(&code, 0x1000, 0x2000, Mode::X64).unwrap(), expected);
+}
@@ -2,7 +2,7 @@ use std::env;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use libc::{self, c_void, PROT_READ, PROT_WRITE, PROT_EXEC};
+use libc::{self, c_void, PROT_READ, PROT_WRITE, PROT_EXEC, MAP_PRIVATE, MAP_ANONYMOUS, MAP_FAILED};
 
 // Shoutout to https://github.com/geofft/redhook/blob/master/src/ld_preload.rs#L18
 // Rust doesn't directly expose __attribute__((constructor)), but this
@@ -132,3 +132,35 @@ pub(in crate::native) fn make_rx(addr: usize) {
     let page = page as *mut c_void;
     unsafe { libc::mprotect(page, 0x1000, PROT_READ | PROT_EXEC); }
 }
+
+/// Writable and executable, for patching code while other code on the same page may run.
+pub(in crate::native) fn make_rwx(addr: usize) {
+    let page = addr & !0xfff;
+    let page = page as *mut c_void;
+    unsafe { libc::mprotect(page, 0x1000, PROT_READ | PROT_WRITE | PROT_EXEC); }
+}
+
+/// Allocate a read-write page within ±2GB of `near`, which can be reached with a rel32 from there.
+pub(in crate::native) fn alloc_page_near(near: usize) -> Option<usize> {
+    const RANGE: usize = 0x7000_0000;
+    const STEP: usize = 0x10_0000;
+    let near = near & !0xfff;
+    // the hint is only used if it's free, try addresses with increasing distance to `near`
+    for i in 0..RANGE / STEP {
+        for hint in [near.checked_sub(i * STEP), near.checked_add(i * STEP)].into_iter().flatten() {
+            let addr = unsafe { libc::mmap(hint as *mut c_void, 0x1000, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0) };
+            if addr == MAP_FAILED {
+                continue;
+            }
+            if (addr as usize).abs_diff(near) < RANGE {
+                return Some(addr as usize);
+            }
+            unsafe { libc::munmap(addr, 0x1000); }
+        }
+    }
+    None
+}
+
+pub(in crate::native) fn free_page(addr: usize) {
+    unsafe { libc::munmap(addr as *mut c_void, 0x1000); }
+}
@@ -2,10 +2,14 @@ use std::cell::Cell;
 use std::fmt::{Formatter, Pointer};
 use std::ops::Deref;
 use std::sync::Mutex;
-use crate::native::{ArrayWrapper, ObjectIndex, StructValueWrapper, UeObjectWrapperType, UeScope, UObject};
-use crate::native::reflection::{ActorWrapper, AActor, UeObjectWrapper};
+use crate::native::{ObjectIndex, UeObjectWrapperType, UeScope, UObject};
+use crate::native::reflection::{ActorWrapper, ActorWrapperType, AActor, UeObjectWrapper};
 
 pub static LEVELS: Mutex<Vec<Level>> = Mutex::new(Vec::new());
+/// Elements which were added by the currently applied map and don't belong to any level.
+pub static SPAWNED_ELEMENTS: Mutex<Vec<SpawnedElementActor>> = Mutex::new(Vec::new());
+/// Rise delays of the currently applied map.
+pub static RISE_DELAYS: Mutex<RiseDelays> = Mutex::new(RiseDelays { delays: Vec::new(), waiting: Vec::new() });
 
 pub struct Level {
     pub level: ObjectIndex<LevelWrapperType>,
@@ -17,6 +21,71 @@ pub struct Level {
     pub springpads: Vec<ObjectIndex<SpringpadWrapperType>>,
 }
 
+pub struct SpawnedElementActor {
+    pub cluster_index: usize,
+    pub element_type: ElementType,
+    pub actor: ObjectIndex<ActorWrapperType>,
+}
+
+pub struct RiseDelays {
+    /// delay in seconds per cluster, empty if no map with delays is applied
+    delays: Vec<f32>,
+    /// clusters which should rise but are held back until their delay passed
+    waiting: Vec<WaitingCluster>,
+}
+struct WaitingCluster {
+    cluster_index: usize,
+    remaining: f32,
+    speed: f32,
+}
+
+impl RiseDelays {
+    pub fn delay(&self, cluster_index: usize) -> f32 {
+        self.delays.get(cluster_index).copied().unwrap_or(0.)
+    }
+    /// Rise speed of a cluster, which is set to 0 in the level while it waits.
+    pub fn speed(&self, cluster_index: usize, level: &LevelWrapper) -> f32 {
+        self.waiting.iter().find(|waiting| waiting.cluster_index == cluster_index)
+            .map(|waiting| waiting.speed)
+            .unwrap_or_else(|| level.speed())
+    }
+    /// Restore the speed of all waiting clusters and replace the delays.
+    pub fn set_delays(&mut self, scope: &UeScope, levels: &[Level], delays: Vec<f32>) {
+        self.release_all(scope, levels);
+        self.delays = delays;
+    }
+    fn release_all(&mut self, scope: &UeScope, levels: &[Level]) {
+        for waiting in self.waiting.drain(..) {
+            scope.get(levels[waiting.cluster_index].level).set_speed(waiting.speed);
+        }
+    }
+    /// Hold back clusters with a delay when the level counter reaches their rise order and let
+    /// them rise once their delay passed.
+    pub fn update(&mut self, scope: &UeScope, levels: &[Level], old_level: i32, new_level: i32, delta: f32) {
+        if new_level < old_level {
+            // new game or reset, clusters are lowered again
+            self.release_all(scope, levels);
+        } else if new_level > old_level {
+            for (cluster_index, level) in levels.iter().enumerate() {
+                let delay = self.delay(cluster_index);
+                let level = scope.get(level.level);
+                if delay > 0. && level.level_index() as i32 == new_level {
+                    self.waiting.push(WaitingCluster { cluster_index, remaining: delay, speed: level.speed() });
+                    level.set_speed(0.);
+                }
+            }
+        }
+        self.waiting.retain_mut(|waiting| {
+            waiting.remaining -= delta;
+            if waiting.remaining > 0. {
+                return true;
+            }
+            scope.get(levels[waiting.cluster_index].level).set_speed(waiting.speed);
+            false
+        });
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct LevelWrapper<'a> {
     base: ActorWrapper<'a>,
@@ -53,47 +122,46 @@ impl<'a> LevelWrapper<'a> {
         LevelWrapper { base: level }
     }
     pub fn level_index(&self) -> usize {
-        self.base.get_field("LevelIndex").unwrap::<i32>().try_into().unwrap()
+        self.raw_level_index().try_into().unwrap()
+    }
+    pub fn set_level_index(&self, index: usize) {
+        self.set_raw_level_index(index.try_into().unwrap())
     }
     pub fn source_location(&self) -> (f32, f32, f32) {
-        let loc = self.base.get_field("SourcePosition").unwrap::<StructValueWrapper>();
-        (loc.get_field("X").unwrap(), loc.get_field("Y").unwrap(), loc.get_field("Z").unwrap())
+        let loc = self.source_position();
+        (loc.get_typed_field("X"), loc.get_typed_field("Y"), loc.get_typed_field("Z"))
     }
     pub fn set_source_location(&self, x: f32, y: f32, z: f32) {
-        let loc = self.base.get_field("SourcePosition").unwrap::<StructValueWrapper>();
-        loc.get_field("X").unwrap::<&Cell<f32>>().set(x);
-        loc.get_field("Y").unwrap::<&Cell<f32>>().set(y);
-        loc.get_field("Z").unwrap::<&Cell<f32>>().set(z);
+        let loc = self.source_position();
+        loc.get_typed_field::<&Cell<f32>>("X").set(x);
+        loc.get_typed_field::<&Cell<f32>>("Y").set(y);
+        loc.get_typed_field::<&Cell<f32>>("Z").set(z);
     }
     pub fn platforms(&self) -> impl Iterator<Item = PlatformWrapper<'a>> + '_ {
-        self.base.get_field("FertileLands").unwrap::<ArrayWrapper<'_, _>>()
-            .into_iter()
+        self.fertile_lands().into_iter()
     }
     pub fn _platform(&self, index: usize) -> Option<PlatformWrapper<'a>> {
-        let array = self.base.get_field("FertileLands").unwrap::<ArrayWrapper<'_, _>>();
-        array.get(index)
+        self.fertile_lands().get(index)
     }
     pub fn cubes(&self) -> impl Iterator<Item = CubeWrapper<'a>> + '_ {
-        self.base.get_field("Collectibles").unwrap::<ArrayWrapper<'_, _>>()
-            .into_iter()
+        self.collectibles().into_iter()
     }
     pub fn _cube(&self, index: usize) -> Option<CubeWrapper<'a>> {
-        let array = self.base.get_field("Collectibles").unwrap::<ArrayWrapper<'_, _>>();
-        array.get(index)
+        self.collectibles().get(index)
     }
     pub fn buttons(&self) -> impl Iterator<Item = ButtonWrapper<'a>> + '_ {
-        self.base.get_field("Buttons").unwrap::<ArrayWrapper<'_, _>>()
-            .into_iter()
+        self.button_array().into_iter()
     }
     pub fn _button(&self, index: usize) -> Option<ButtonWrapper<'a>> {
-        let array = self.base.get_field("Buttons").unwrap::<ArrayWrapper<'_, _>>();
-        array.get(index)
+        self.button_array().get(index)
     }
-    pub fn speed(&self) -> f32 {
-        self.base.get_field("Speed").unwrap()
-    }
-    pub fn set_speed(&self, speed: f32) {
-        self.base.get_field("Speed").unwrap::<&Cell<f32>>().set(speed)
+    ue_fields! {
+        raw_level_index / set_raw_level_index: i32 = "LevelIndex";
+        source_position: StructValueWrapper<'a> = "SourcePosition";
+        fertile_lands: ArrayWrapper<'a, PlatformWrapper<'a>> = "FertileLands";
+        collectibles: ArrayWrapper<'a, CubeWrapper<'a>> = "Collectibles";
+        button_array: ArrayWrapper<'a, ButtonWrapper<'a>> = "Buttons";
+        pub speed / set_speed: f32 = "Speed";
     }
 }
 
@@ -356,23 +424,6 @@ pub fn init() {
             let object = item.object();
             let name = object.name();
             let class_name = object.class().name();
-            // fn print_children(depth: usize, class: StructWrapper) {
-            //     use crate::native::{PropertyWrapper, UProperty};
-            //     use crate::native::reflection::{StructWrapper, ClassWrapper};
-            //     for property in class.iter_properties() {
-            //         let class_name = property.class().name();
-            //         log!("{}{property}", "    ".repeat(depth));
-            //         if class_name == "ObjectProperty" {
-            //             let class = unsafe { ClassWrapper::new((*(property.as_uobjectproperty())).property_class) };
-            //             log!("{}going into {}", "    ".repeat(depth), class.name());
-            //             // print_children(depth+1, class);
-            //         }
-            //     }
-            //     log!("{}done printing children", "    ".repeat(depth));
-            // }
-            // log!("{:?} {:?} ({object:p})", class_name, name);
-            // print_children(1, object.class());
-
             if class_name == "BP_LevelRoot_C" && name != "Default__BP_LevelRoot_C" {
                 let level: LevelWrapper = object.upcast();
                 levels.push(Level {
@@ -33,9 +33,11 @@ mod gameinstance;
 mod platform_misc;
 mod texture;
 mod gameusersettings;
-mod reflection;
+#[macro_use] mod reflection;
 mod map_editor;
 mod kismet_system_library;
+mod process_event;
+mod hook;
 
 use crate::semaphore::Semaphore;
 #[cfg(unix)] use self::linux::*;
@@ -70,6 +72,7 @@ pub use self::gameinstance::UMyGameInstance;
 pub use self::reflection::*;
 pub use self::map_editor::*;
 pub use self::kismet_system_library::KismetSystemLibrary;
+pub use self::process_event::{observe_function, unobserve_function, unobserve_all_functions};
 
 /// Rebo code must only be executed once all `this*` have been found.
 /// There are currently 3 such `this`-pointers - rebo starts once the semaphore reaches 1.
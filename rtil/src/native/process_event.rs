@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use once_cell::sync::Lazy;
+use crate::native::{FunctionWrapper, ObjectWrapper, UFunction, UObject, UOBJECT_PROCESSEVENT};
+use crate::native::hook::{HookError, InlineHook};
+
+/// observed functions by the address of their `UFunction` with the class names they were observed for
+static OBSERVED_FUNCTIONS: Lazy<Mutex<HashMap<usize, Vec<String>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+/// Created with the first observed function and only disabled afterwards, such that the
+/// trampoline stays valid for calls of the original which are still running.
+static HOOK: Lazy<Mutex<Option<InlineHook>>> = Lazy::new(|| Mutex::new(None));
+/// trampoline of the hook, read without locking `HOOK`, which may be held while ProcessEvent is called
+static ORIGINAL_PROCESS_EVENT: AtomicUsize = AtomicUsize::new(0);
+static IS_HOOKED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn process_event(object: *mut UObject, function: *mut UFunction, params: *mut c_void) {
+    process_event_detour(object, function, params)
+}
+#[cfg(windows)]
+extern "thiscall" fn process_event(object: *mut UObject, function: *mut UFunction, params: *mut c_void) {
+    process_event_detour(object, function, params)
+}
+
+fn process_event_detour(object: *mut UObject, function: *mut UFunction, params: *mut c_void) {
+    // ProcessEvent is called very often, don't do anything for functions nobody is interested in
+    let class_names = OBSERVED_FUNCTIONS.lock().unwrap().get(&(function as usize)).cloned();
+    if let Some(class_names) = class_names {
+        let class_names = observing_classes(&unsafe { ObjectWrapper::new(object) }, class_names);
+        if !class_names.is_empty() {
+            crate::threads::ue::function_called(class_names, object, function, params);
+        }
+    }
+    let original: extern_fn!(fn(object: *mut UObject, function: *mut UFunction, params: *mut c_void))
+        = unsafe { ::std::mem::transmute(ORIGINAL_PROCESS_EVENT.load(Ordering::SeqCst)) };
+    original(object, function, params);
+}
+
+/// Class names the function was observed for which the object is an instance of.
+///
+/// Functions are also found in super classes, so the `UFunction` observed for a class may be
+/// called on objects of other subclasses of the class defining it.
+pub(in crate::native) fn observing_classes(object: &ObjectWrapper, class_names: Vec<String>) -> Vec<String> {
+    let class = object.class();
+    class_names.into_iter().filter(|class_name| class.extends_from(class_name)).collect()
+}
+
+/// Get notified about all calls of the function through `ProcessEvent` via `function_called`.
+///
+/// Takes effect with the next frame.
+pub fn observe_function(function: &FunctionWrapper, class_name: String) -> Result<(), HookError> {
+    // create the hook right away, such that failing to hook is reported to the caller
+    create_process_event_hook()?;
+    let mut observed = OBSERVED_FUNCTIONS.lock().unwrap();
+    let class_names = observed.entry(function.as_ptr() as usize).or_default();
+    if !class_names.contains(&class_name) {
+        class_names.push(class_name);
+    }
+    Ok(())
+}
+/// Returns `false` if the function wasn't observed for the class.
+pub fn unobserve_function(function: &FunctionWrapper, class_name: &str) -> bool {
+    let mut observed = OBSERVED_FUNCTIONS.lock().unwrap();
+    let class_names = match observed.get_mut(&(function.as_ptr() as usize)) {
+        Some(class_names) => class_names,
+        None => return false,
+    };
+    let len = class_names.len();
+    class_names.retain(|name| name != class_name);
+    let removed = class_names.len() != len;
+    if class_names.is_empty() {
+        observed.remove(&(function.as_ptr() as usize));
+    }
+    removed
+}
+pub fn unobserve_all_functions() {
+    OBSERVED_FUNCTIONS.lock().unwrap().clear();
+}
+
+fn create_process_event_hook() -> Result<(), HookError> {
+    let mut hook = HOOK.lock().unwrap();
+    if hook.is_none() {
+        let target = UOBJECT_PROCESSEVENT.load(Ordering::SeqCst);
+        let new = unsafe { InlineHook::new(target, process_event as *const () as usize)? };
+        ORIGINAL_PROCESS_EVENT.store(new.original(), Ordering::SeqCst);
+        *hook = Some(new);
+    }
+    Ok(())
+}
+
+/// Enable the ProcessEvent-hook only while functions are observed.
+///
+/// Must not be called from within ProcessEvent, which is why changes of the observed functions
+/// are only applied once per frame.
+pub(in crate::native) fn update_process_event_hook() {
+    let should_hook = !OBSERVED_FUNCTIONS.lock().unwrap().is_empty();
+    if should_hook == IS_HOOKED.load(Ordering::SeqCst) {
+        return;
+    }
+    if let Some(hook) = &mut *HOOK.lock().unwrap() {
+        unsafe {
+            if should_hook {
+                hook.enable();
+            } else {
+                hook.disable();
+            }
+        }
+    }
+    IS_HOOKED.store(should_hook, Ordering::SeqCst);
+}
@@ -0,0 +1,174 @@
+//! Wrappers for UE-owned `TSet`s and `TMap`s.
+//!
+//! Elements can be iterated and map values modified in place, e.g. through `&Cell<f32>`.
+//! Adding or removing elements and modifying set elements or map keys isn't supported, as that
+//! requires rehashing with UE's hash functions.
+
+use std::ffi::c_void;
+use std::fmt::{Formatter, Pointer};
+use std::marker::PhantomData;
+use crate::native::ue::TArray;
+use crate::native::ArrayElement;
+use crate::native::reflection::{MapPropertyWrapper, PropertyWrapper, SetPropertyWrapper};
+
+// technically TBitArray<FDefaultBitArrayAllocator>
+#[repr(C)]
+pub struct FScriptBitArray {
+    pub inline_data: [u32; 4],
+    pub secondary_data: *mut u32,
+    pub num_bits: i32,
+    pub max_bits: i32,
+}
+// technically TSparseArray<_, FDefaultSparseArrayAllocator>
+#[repr(C)]
+pub struct FScriptSparseArray {
+    pub data: TArray<u8>,
+    pub allocation_flags: FScriptBitArray,
+    pub first_free_index: i32,
+    pub num_free_indices: i32,
+}
+// technically TSet<_, _, FDefaultSetAllocator>, also used for TMap with TPair elements
+#[repr(C)]
+pub struct FScriptSet {
+    pub elements: FScriptSparseArray,
+    // TInlineAllocator<1> of FSetElementId
+    pub hash_inline_data: i32,
+    pub hash_secondary_data: *mut i32,
+    pub hash_size: i32,
+}
+
+fn align(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+/// Size of an element within the sparse array of a set, like `FScriptSetLayout`.
+pub(super) fn set_element_size(size: usize, alignment: usize) -> usize {
+    // the element is followed by its HashNextId and HashIndex, both i32
+    let hash_next_id_offset = align(size, 4);
+    let alignment = alignment.max(4);
+    let set_element_size = align(hash_next_id_offset + 8, alignment);
+    // elements share their memory with the free list links (two i32) of the sparse array
+    align(set_element_size.max(8), alignment)
+}
+
+/// Pointers to all allocated elements of the sparse array of a set.
+unsafe fn iter_set_elements(set: *mut FScriptSet, element_size: usize) -> impl Iterator<Item = *mut u8> {
+    let elements = &(*set).elements;
+    let flags = &elements.allocation_flags;
+    let flags = if flags.secondary_data.is_null() { flags.inline_data.as_ptr() } else { flags.secondary_data as *const u32 };
+    let data = elements.data.ptr;
+    (0..elements.data.len()).filter_map(move |index| {
+        let allocated = *flags.add(index / 32) & (1 << (index % 32)) != 0;
+        allocated.then(|| data.add(index * element_size))
+    })
+}
+
+/// Wrapper for a UE-owned set
+#[derive(Debug)]
+pub struct SetWrapper<'a, T: ArrayElement<'a>> {
+    set: *mut FScriptSet,
+    element_prop: PropertyWrapper<'a>,
+    element_size: usize,
+    _marker: PhantomData<&'a mut [T]>,
+}
+impl<'a, T: ArrayElement<'a>> Pointer for SetWrapper<'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.set, f)
+    }
+}
+// get rid of the implied T: Clone in derived Clone impls
+impl<'a, T: ArrayElement<'a>> Clone for SetWrapper<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set,
+            element_prop: self.element_prop.clone(),
+            element_size: self.element_size,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<'a, T: ArrayElement<'a>> ArrayElement<'a> for SetWrapper<'a, T> {
+    unsafe fn create(ptr: *mut c_void, prop: &PropertyWrapper<'a>) -> SetWrapper<'a, T> {
+        let element_prop = prop.upcast::<SetPropertyWrapper<'a>>().element_prop();
+        SetWrapper::new(ptr as *mut FScriptSet, element_prop)
+    }
+}
+impl<'a, T: ArrayElement<'a>> SetWrapper<'a, T> {
+    pub unsafe fn new(set: *mut FScriptSet, element_prop: PropertyWrapper<'a>) -> SetWrapper<'a, T> {
+        assert!(!set.is_null());
+        let element_size = set_element_size(element_prop.size(), element_prop.min_alignment());
+        SetWrapper { set, element_prop, element_size, _marker: PhantomData }
+    }
+    pub fn len(&self) -> usize {
+        unsafe { (*self.set).elements.data.len() - (*self.set).elements.num_free_indices as usize }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        unsafe {
+            iter_set_elements(self.set, self.element_size)
+                .map(|ptr| T::create(ptr as *mut c_void, &self.element_prop))
+        }
+    }
+}
+
+/// Wrapper for a UE-owned map
+#[derive(Debug)]
+pub struct MapWrapper<'a, K: ArrayElement<'a>, V: ArrayElement<'a>> {
+    map: *mut FScriptSet,
+    key_prop: PropertyWrapper<'a>,
+    value_prop: PropertyWrapper<'a>,
+    value_offset: usize,
+    pair_size: usize,
+    _marker: PhantomData<&'a mut [(K, V)]>,
+}
+impl<'a, K: ArrayElement<'a>, V: ArrayElement<'a>> Pointer for MapWrapper<'a, K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.map, f)
+    }
+}
+// get rid of the implied K: Clone, V: Clone in derived Clone impls
+impl<'a, K: ArrayElement<'a>, V: ArrayElement<'a>> Clone for MapWrapper<'a, K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map,
+            key_prop: self.key_prop.clone(),
+            value_prop: self.value_prop.clone(),
+            value_offset: self.value_offset,
+            pair_size: self.pair_size,
+            _marker: PhantomData,
+        }
+    }
+}
+impl<'a, K: ArrayElement<'a>, V: ArrayElement<'a>> ArrayElement<'a> for MapWrapper<'a, K, V> {
+    unsafe fn create(ptr: *mut c_void, prop: &PropertyWrapper<'a>) -> MapWrapper<'a, K, V> {
+        let map_property = prop.upcast::<MapPropertyWrapper<'a>>();
+        MapWrapper::new(ptr as *mut FScriptSet, map_property.key_prop(), map_property.value_prop())
+    }
+}
+impl<'a, K: ArrayElement<'a>, V: ArrayElement<'a>> MapWrapper<'a, K, V> {
+    pub unsafe fn new(map: *mut FScriptSet, key_prop: PropertyWrapper<'a>, value_prop: PropertyWrapper<'a>) -> MapWrapper<'a, K, V> {
+        assert!(!map.is_null());
+        // elements are TPair<K, V>, like FScriptMapLayout
+        let (key_alignment, value_alignment) = (key_prop.min_alignment(), value_prop.min_alignment());
+        let value_offset = align(key_prop.size(), value_alignment);
+        let pair_alignment = key_alignment.max(value_alignment);
+        let pair_size = set_element_size(align(value_offset + value_prop.size(), pair_alignment), pair_alignment);
+        MapWrapper { map, key_prop, value_prop, value_offset, pair_size, _marker: PhantomData }
+    }
+    pub fn len(&self) -> usize {
+        unsafe { (*self.map).elements.data.len() - (*self.map).elements.num_free_indices as usize }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (K, V)> + '_ {
+        unsafe {
+            iter_set_elements(self.map, self.pair_size).map(|ptr| (
+                K::create(ptr as *mut c_void, &self.key_prop),
+                V::create(ptr.add(self.value_offset) as *mut c_void, &self.value_prop),
+            ))
+        }
+    }
+    /// Value of the first key matching the predicate.
+    ///
+    /// Keys are compared one by one, as UE's hash functions aren't available.
+    pub fn find(&self, mut predicate: impl FnMut(&K) -> bool) -> Option<V> {
+        self.iter().find(|(key, _)| predicate(key)).map(|(_, value)| value)
+    }
+}
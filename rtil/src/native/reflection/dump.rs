@@ -0,0 +1,204 @@
+//! Dump of all classes, structs, functions and enums known to UE's reflection system.
+//!
+//! Types are sorted by name and contain only their own properties and functions, so that dumps
+//! of different game versions can be diffed.
+
+use std::fmt::Write;
+use serde::Serialize;
+use crate::native::reflection::{ArrayPropertyWrapper, BytePropertyWrapper, EnumPropertyWrapper, EnumWrapper, EPropertyFlags, FieldWrapper, FunctionWrapper, MapPropertyWrapper, ObjectPropertyWrapper, PropertyWrapper, SetPropertyWrapper, StructPropertyWrapper, StructWrapper};
+use crate::native::UeScope;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReflectionDump {
+    pub structs: Vec<StructDump>,
+    pub enums: Vec<EnumDump>,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct StructDump {
+    /// kind of the UStruct, e.g. `Class`, `BlueprintGeneratedClass` or `ScriptStruct`
+    pub kind: String,
+    pub name: String,
+    /// super structs starting with the direct one
+    pub super_chain: Vec<String>,
+    pub size: usize,
+    pub min_alignment: usize,
+    pub properties: Vec<PropertyDump>,
+    pub functions: Vec<FunctionDump>,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct PropertyDump {
+    pub name: String,
+    pub kind: String,
+    /// C++-like type
+    pub type_name: String,
+    pub offset: isize,
+    pub size: usize,
+    pub array_dim: usize,
+    pub flags: u64,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionDump {
+    pub name: String,
+    pub flags: u32,
+    pub params: Vec<PropertyDump>,
+}
+#[derive(Debug, Clone, Serialize)]
+pub struct EnumDump {
+    pub name: String,
+    pub values: Vec<(String, u8)>,
+}
+
+impl PropertyDump {
+    fn new(prop: &PropertyWrapper) -> PropertyDump {
+        PropertyDump {
+            name: prop.name(),
+            kind: prop.property_kind(),
+            type_name: type_name(prop),
+            offset: prop.offset(),
+            size: prop.size(),
+            array_dim: prop.array_dim(),
+            flags: prop.property_flags(),
+        }
+    }
+}
+
+fn own_fields<'a>(struct_: &StructWrapper<'a>) -> impl Iterator<Item = FieldWrapper<'a>> {
+    struct_.children().into_iter().flat_map(|children| children.iter_this_and_next_fields())
+}
+
+/// C++-like type of a property
+fn type_name(prop: &PropertyWrapper) -> String {
+    let kind = prop.property_kind();
+    match kind.as_str() {
+        "BoolProperty" => "bool".to_string(),
+        "Int8Property" => "int8".to_string(),
+        "Int16Property" => "int16".to_string(),
+        "IntProperty" => "int32".to_string(),
+        "Int64Property" => "int64".to_string(),
+        "UInt16Property" => "uint16".to_string(),
+        "UInt32Property" => "uint32".to_string(),
+        "UInt64Property" => "uint64".to_string(),
+        "FloatProperty" => "float".to_string(),
+        "DoubleProperty" => "double".to_string(),
+        "StrProperty" => "FString".to_string(),
+        "NameProperty" => "FName".to_string(),
+        "TextProperty" => "FText".to_string(),
+        "ByteProperty" => match prop.upcast::<BytePropertyWrapper>().enum_() {
+            Some(enum_) => format!("TEnumAsByte<{}>", enum_.name()),
+            None => "uint8".to_string(),
+        },
+        "EnumProperty" => prop.upcast::<EnumPropertyWrapper>().enum_().name(),
+        "StructProperty" => prop.upcast::<StructPropertyWrapper>().struct_().name(),
+        "ArrayProperty" => format!("TArray<{}>", type_name(&prop.upcast::<ArrayPropertyWrapper>().inner())),
+        "SetProperty" => format!("TSet<{}>", type_name(&prop.upcast::<SetPropertyWrapper>().element_prop())),
+        "MapProperty" => {
+            let map = prop.upcast::<MapPropertyWrapper>();
+            format!("TMap<{}, {}>", type_name(&map.key_prop()), type_name(&map.value_prop()))
+        },
+        "DelegateProperty" => "FScriptDelegate".to_string(),
+        "MulticastDelegateProperty" => "FMulticastScriptDelegate".to_string(),
+        "WeakObjectProperty" => "FWeakObjectPtr".to_string(),
+        "LazyObjectProperty" => "FLazyObjectPtr".to_string(),
+        "AssetObjectProperty" | "SoftObjectProperty" => "FSoftObjectPtr".to_string(),
+        "InterfaceProperty" => "FScriptInterface".to_string(),
+        _ => match prop.try_upcast::<ObjectPropertyWrapper>() {
+            // ObjectProperty and ClassProperty
+            Some(object) => format!("{}*", object.property_class().name()),
+            None => kind,
+        },
+    }
+}
+
+fn dump_function(function: &FunctionWrapper) -> FunctionDump {
+    FunctionDump {
+        name: function.name(),
+        flags: function.function_flags(),
+        params: function.iter_params().map(|param| PropertyDump::new(&param)).collect(),
+    }
+}
+
+fn dump_struct(kind: String, struct_: &StructWrapper) -> StructDump {
+    let super_chain = std::iter::successors(struct_.super_struct(), |s| s.super_struct())
+        .map(|s| s.name())
+        .collect();
+    let fields: Vec<_> = own_fields(struct_).collect();
+    let mut functions: Vec<_> = fields.iter()
+        .filter_map(|field| field.try_upcast::<FunctionWrapper>())
+        .map(|function| dump_function(&function))
+        .collect();
+    functions.sort_by(|a, b| a.name.cmp(&b.name));
+    StructDump {
+        kind,
+        name: struct_.name(),
+        super_chain,
+        size: struct_.properties_size(),
+        min_alignment: struct_.min_alignment(),
+        properties: fields.iter()
+            .filter_map(|field| field.try_upcast::<PropertyWrapper>())
+            .map(|prop| PropertyDump::new(&prop))
+            .collect(),
+        functions,
+    }
+}
+
+/// Walk `GUObjectArray` and dump all classes, structs and enums.
+///
+/// Functions are dumped as part of their class.
+pub fn dump_reflection(scope: &UeScope) -> ReflectionDump {
+    let mut structs = Vec::new();
+    let mut enums = Vec::new();
+    for object in scope.iter_global_object_array().filter_map(|item| item.try_object()) {
+        let class = object.class();
+        if class.extends_from("Function") {
+            continue;
+        } else if class.extends_from("Struct") {
+            structs.push(dump_struct(class.name(), &object.upcast::<StructWrapper>()));
+        } else if class.extends_from("Enum") {
+            let enum_ = object.upcast::<EnumWrapper>();
+            enums.push(EnumDump { name: enum_.name(), values: enum_.names() });
+        }
+    }
+    structs.sort_by(|a, b| a.name.cmp(&b.name));
+    enums.sort_by(|a, b| a.name.cmp(&b.name));
+    ReflectionDump { structs, enums }
+}
+
+impl ReflectionDump {
+    /// C++-like header with offsets, sizes and flags as comments
+    pub fn to_header(&self) -> String {
+        let mut out = String::new();
+        for enum_ in &self.enums {
+            writeln!(out, "enum class {} : uint8 {{", enum_.name).unwrap();
+            for (name, value) in &enum_.values {
+                writeln!(out, "    {name} = {value},").unwrap();
+            }
+            writeln!(out, "}};\n").unwrap();
+        }
+        for s in &self.structs {
+            let keyword = if s.kind == "ScriptStruct" { "struct" } else { "class" };
+            write!(out, "// {} size {:#x} align {}\n{keyword} {}", s.kind, s.size, s.min_alignment, s.name).unwrap();
+            if let Some(super_) = s.super_chain.first() {
+                write!(out, " : public {super_}").unwrap();
+            }
+            writeln!(out, " {{").unwrap();
+            for prop in &s.properties {
+                let dim = if prop.array_dim > 1 { format!("[{}]", prop.array_dim) } else { String::new() };
+                writeln!(out, "    {} {}{dim}; // {:#x} ({:#x}) flags {:#x}", prop.type_name, prop.name, prop.offset, prop.size, prop.flags).unwrap();
+            }
+            for function in &s.functions {
+                let is_return = |param: &&PropertyDump| param.flags & EPropertyFlags::ReturnParm as u64 != 0;
+                let return_type = function.params.iter().find(is_return).map(|param| param.type_name.as_str()).unwrap_or("void");
+                let params = function.params.iter()
+                    .filter(|param| !is_return(param))
+                    .map(|param| {
+                        let is_out = param.flags & EPropertyFlags::OutParm as u64 != 0;
+                        let is_const = param.flags & EPropertyFlags::ConstParm as u64 != 0;
+                        format!("{}{}{} {}", if is_const { "const " } else { "" }, param.type_name, if is_out { "&" } else { "" }, param.name)
+                    }).collect::<Vec<_>>().join(", ");
+                writeln!(out, "    {return_type} {}({params}); // flags {:#x}", function.name, function.flags).unwrap();
+            }
+            writeln!(out, "}};\n").unwrap();
+        }
+        out
+    }
+}
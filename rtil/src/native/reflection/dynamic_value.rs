@@ -2,7 +2,7 @@ use std::ffi::c_void;
 use crate::native::{ArrayElement, ObjectPropertyWrapper, ObjectWrapper, SizedArrayElement, UObject};
 use crate::native::reflection::{PropertyWrapper};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DynamicValue<'a> {
     /// single indirection when pointing to structs and primitives (*mut f32), double-indirection when pointing to e.g. UObject (*mut *mut UObject)
     ptr: *mut c_void,
@@ -18,6 +18,10 @@ impl<'a> DynamicValue<'a> {
         self.prop.clone()
     }
 
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.ptr
+    }
+
     pub fn unwrap<T: ArrayElement<'a>>(self) -> T {
         unsafe { T::create(self.ptr, &self.prop) }
     }
@@ -44,3 +48,10 @@ impl<'a> DynamicValue<'a> {
         unsafe { *ptr = object.as_ptr() }
     }
 }
+
+/// allows untyped access to elements of arrays, sets and maps
+impl<'a> ArrayElement<'a> for DynamicValue<'a> {
+    unsafe fn create(ptr: *mut c_void, prop: &PropertyWrapper<'a>) -> DynamicValue<'a> {
+        DynamicValue::new(ptr, prop.clone())
+    }
+}
@@ -6,33 +6,80 @@ use std::sync::atomic::Ordering;
 use std::sync::Mutex;
 use once_cell::sync::Lazy;
 use crate::native::{FUOBJECTARRAY_ALLOCATESERIALNUMBER, GUOBJECTARRAY, UeObjectWrapper, UeObjectWrapperType};
-use crate::native::reflection::{ObjectWrapper, UObject};
+use crate::native::reflection::{FWeakObjectPtr, ObjectWrapper, UObject};
 
+/// `WeakObjectHandle` which resolves to a specific wrapper type.
 #[derive(Debug)]
 pub struct ObjectIndex<T: UeObjectWrapperType> {
-    index: UntypedObjectIndex,
+    handle: WeakObjectHandle,
     _marker: PhantomData<T>,
 }
 // get rid of the implied T: Clone bound of derive
 impl<T: UeObjectWrapperType> Clone for ObjectIndex<T> {
     fn clone(&self) -> Self {
         ObjectIndex {
-            index: self.index,
+            handle: self.handle,
             _marker: PhantomData,
         }
     }
 }
 // get rid of the implied T: Clone bound of derive
 impl<T: UeObjectWrapperType> Copy for ObjectIndex<T> {}
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct UntypedObjectIndex {
     internal_index: i32,
     serial_number: i32,
 }
 static BUFFER: Lazy<Mutex<HashMap<u32, UntypedObjectIndex>>> = Lazy::new(|| Mutex::new(HashMap::new()));
 
+impl<T: UeObjectWrapperType> ObjectIndex<T> {
+    pub fn handle(&self) -> WeakObjectHandle {
+        self.handle
+    }
+}
+
+/// Weak handle to any UObject, which can be held across frames and sent between threads.
+///
+/// Resolving it fails once the object was garbage collected or is about to be, e.g. after a
+/// level reload, instead of returning a dangling pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WeakObjectHandle {
+    index: UntypedObjectIndex,
+}
+impl WeakObjectHandle {
+    /// Internal index and serial number, to store the handle where only plain values are allowed.
+    pub fn into_raw(self) -> (i32, i32) {
+        (self.index.internal_index, self.index.serial_number)
+    }
+    /// Handle from the parts returned by `into_raw`, resolving invalid parts fails.
+    pub fn from_raw(internal_index: i32, serial_number: i32) -> WeakObjectHandle {
+        WeakObjectHandle { index: UntypedObjectIndex { internal_index, serial_number } }
+    }
+}
+
+/// Keeps an object alive by adding it to the root set until dropped.
+#[derive(Debug)]
+pub struct RootGuard {
+    handle: WeakObjectHandle,
+}
+impl RootGuard {
+    pub fn handle(&self) -> WeakObjectHandle {
+        self.handle
+    }
+}
+impl Drop for RootGuard {
+    fn drop(&mut self) {
+        let index = self.handle.index;
+        UeScope::with(|scope| match scope.resolve_untyped_object_item(index) {
+            // also unroot objects pending kill, otherwise the GC would never clean them up
+            Ok(item) => item.mark_as_root_object(false),
+            Err(_) => log!("rooted object {:?} was destroyed while rooted", index),
+        })
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
-pub enum GetBufferedItemError {
+pub enum ResolveObjectError {
     NotBuffered,
     Invalidated,
 }
@@ -68,34 +115,62 @@ impl UeScope {
         }
     }
     pub fn object_index<'a, T: UeObjectWrapper<'a>>(&'a self, object: &T) -> ObjectIndex<T::UeObjectWrapperType> {
-        let item = self.object_array().get_item_of_object(object);
         ObjectIndex {
-            index: self.untyped_object_index(&item),
+            handle: self.weak_handle(object),
             _marker: PhantomData,
         }
     }
-    fn resolve_untyped_object_index<'a>(&'a self, index: UntypedObjectIndex) -> Result<ObjectWrapper<'a>, GetBufferedItemError> {
+    /// item of the index if its slot wasn't reused, even if the object is pending kill
+    fn resolve_untyped_object_item<'a>(&'a self, index: UntypedObjectIndex) -> Result<ObjectItemWrapper<'a>, ResolveObjectError> {
         let item = self.object_array().try_get(index.internal_index)
-            .ok_or(GetBufferedItemError::Invalidated)?;
+            .ok_or(ResolveObjectError::Invalidated)?;
         if item.serial_number() != index.serial_number {
-            return Err(GetBufferedItemError::Invalidated);
+            return Err(ResolveObjectError::Invalidated);
+        }
+        Ok(item)
+    }
+    fn resolve_untyped_object_index<'a>(&'a self, index: UntypedObjectIndex) -> Result<ObjectWrapper<'a>, ResolveObjectError> {
+        let item = self.resolve_untyped_object_item(index)?;
+        if item.is_pending_destruction() {
+            return Err(ResolveObjectError::Invalidated);
         }
-        Ok(item.object())
+        item.try_object().ok_or(ResolveObjectError::Invalidated)
+    }
+    pub fn weak_handle<'a, T: UeObjectWrapper<'a>>(&'a self, object: &T) -> WeakObjectHandle {
+        let item = self.object_array().get_item_of_object(object);
+        WeakObjectHandle { index: self.untyped_object_index(&item) }
+    }
+    /// `Err(Invalidated)` if the object was garbage collected or is pending kill
+    pub fn resolve<'a>(&'a self, handle: WeakObjectHandle) -> Result<ObjectWrapper<'a>, ResolveObjectError> {
+        self.resolve_untyped_object_index(handle.index)
+    }
+    /// Add the object to the root set, such that it isn't garbage collected until the guard is dropped.
+    pub fn root<'a, T: UeObjectWrapper<'a>>(&'a self, object: &T) -> RootGuard {
+        let item = self.object_array().get_item_of_object(object);
+        item.mark_as_root_object(true);
+        RootGuard { handle: WeakObjectHandle { index: self.untyped_object_index(&item) } }
+    }
+    /// `None` if the object was destroyed or the UE weak pointer was never set
+    pub fn resolve_weak_object_ptr<'a>(&'a self, ptr: FWeakObjectPtr) -> Option<ObjectWrapper<'a>> {
+        if ptr.object_serial_number == 0 {
+            return None;
+        }
+        self.resolve(WeakObjectHandle::from_raw(ptr.object_index, ptr.object_serial_number)).ok()
     }
     pub fn get<'a, T: UeObjectWrapperType>(&'a self, index: impl Borrow<ObjectIndex<T>>) -> T::UeObjectWrapper<'a> {
-        let object = self.resolve_untyped_object_index(index.borrow().index).unwrap();
+        let object = self.resolve(index.borrow().handle).unwrap();
         object.upcast()
     }
-    pub fn try_get<'a, T: UeObjectWrapper<'a>>(&'a self, index: &ObjectIndex<T::UeObjectWrapperType>) -> Result<T, GetBufferedItemError> {
-        let object = self.resolve_untyped_object_index(index.index)?;
+    pub fn try_get<'a, T: UeObjectWrapper<'a>>(&'a self, index: &ObjectIndex<T::UeObjectWrapperType>) -> Result<T, ResolveObjectError> {
+        let object = self.resolve(index.handle)?;
         Ok(object.upcast())
     }
     pub fn buffer_item<'a>(&'a self, ident: impl Into<u32>, item: &ObjectItemWrapper<'a>) {
         BUFFER.lock().unwrap().insert(ident.into(), self.untyped_object_index(item));
     }
-    pub fn get_buffered<'a>(&'a self, ident: impl Into<u32>) -> Result<ObjectWrapper<'a>, GetBufferedItemError> {
+    pub fn get_buffered<'a>(&'a self, ident: impl Into<u32>) -> Result<ObjectWrapper<'a>, ResolveObjectError> {
         let index = BUFFER.lock().unwrap().get(&ident.into()).copied()
-            .ok_or(GetBufferedItemError::NotBuffered)?;
+            .ok_or(ResolveObjectError::NotBuffered)?;
         self.resolve_untyped_object_index(index)
     }
 }
@@ -177,8 +252,9 @@ impl<'a> ObjectArrayWrapper<'a> {
     pub fn get(&self, internal_index: i32) -> ObjectItemWrapper<'a> {
         self.try_get(internal_index).unwrap_or_else(|| panic!("assert {} < {}", internal_index, self.num_elements()))
     }
+    /// `None` if the index is out of bounds, including negative indices
     pub fn try_get(&self, internal_index: i32) -> Option<ObjectItemWrapper<'a>> {
-        let index: usize = internal_index.try_into().unwrap();
+        let index: usize = internal_index.try_into().ok()?;
         unsafe {
             if index >= self.num_elements() {
                 None
@@ -208,11 +284,21 @@ impl<'a> ObjectItemWrapper<'a> {
     pub fn object(&self) -> ObjectWrapper<'a> {
         unsafe { ObjectWrapper::new((*self.item).object) }
     }
+    /// `None` for slots of destroyed objects
+    pub fn try_object(&self) -> Option<ObjectWrapper<'a>> {
+        unsafe { ObjectWrapper::new_nullable((*self.item).object) }
+    }
 
     pub fn serial_number(&self) -> i32 {
         unsafe { (*self.item).serial_number }
     }
 
+    /// object got destroyed or was found unreachable by the GC, but isn't collected yet
+    pub fn is_pending_destruction(&self) -> bool {
+        let flags = EInternalObjectFlags::PendingKill as i32 | EInternalObjectFlags::Unreachable as i32;
+        unsafe { (*self.item).flags & flags != 0 }
+    }
+
     pub fn mark_as_root_object(&self, val: bool) {
         unsafe {
             if val {
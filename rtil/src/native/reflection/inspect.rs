@@ -0,0 +1,390 @@
+//! Untyped access to properties by path, used by the object inspector.
+//!
+//! A path consists of property names separated by dots, e.g.
+//! `Controller.PlayerCameraManager.DefaultFOV`.
+//! Each name can be followed by an index into an array like `Levels[3]`.
+//! Every part but the last one must be an object, struct or array element.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::ptr;
+use thiserror::Error;
+use crate::native::reflection::{ArrayWrapper, BoolValueWrapper, DynamicValue, EPropertyFlags, EnumValueWrapper, FunctionWrapper, MapWrapper, ObjectPropertyWrapper, ObjectWrapper, SetWrapper, StructValueWrapper, UObject};
+use crate::native::ue::{FName, FString, TArray};
+use crate::native::SizedArrayElement;
+
+#[derive(Debug, Error)]
+pub enum InspectError {
+    #[error("{owner} has no property {name}")]
+    NoSuchProperty { owner: String, name: String },
+    #[error("invalid path segment `{0}`")]
+    InvalidSegment(String),
+    #[error("{0} is None")]
+    NullObject(String),
+    #[error("{path} is a {kind}, which can't contain properties")]
+    NotAContainer { path: String, kind: String },
+    #[error("{path} is a {kind}, which can't be indexed")]
+    NotAnArray { path: String, kind: String },
+    #[error("index {index} of {path} is out of bounds for length {len}")]
+    IndexOutOfBounds { path: String, index: usize, len: usize },
+    #[error("can't assign a {value} to the {kind} {path}")]
+    TypeMismatch { path: String, kind: String, value: &'static str },
+    #[error("{value} is out of range for the {kind} {path}")]
+    OutOfRange { path: String, kind: String, value: String },
+    #[error("{path} has no enum value named {name}")]
+    NoSuchEnumValue { path: String, name: String },
+    #[error("{object} of class {class} isn't compatible with the {path} of class {property_class}")]
+    IncompatibleObject { path: String, object: String, class: String, property_class: String },
+    #[error("{path} is a {kind}, which can't be written")]
+    Unsupported { path: String, kind: String },
+    #[error("{class} has no function {name}")]
+    NoSuchFunction { class: String, name: String },
+    #[error("{function} has no parameter {name}, parameters are: {params}")]
+    NoSuchParameter { function: String, name: String, params: String },
+    #[error("missing argument {name} for {function}")]
+    MissingArgument { function: String, name: String },
+    #[error("the parameters of {function} end at {calculated} bytes instead of their size of {size} bytes")]
+    ParameterSize { function: String, size: usize, calculated: usize },
+}
+
+/// Primitive value read from or written to a property.
+#[derive(Debug, Clone)]
+pub enum InspectValue<'a> {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// strings, names and enum values
+    String(String),
+    Object(Option<ObjectWrapper<'a>>),
+    /// fields of a struct by name, only written; fields which aren't given keep their value
+    Struct(Vec<(String, InspectValue<'a>)>),
+    /// anything else, formatted with [`format_value`]
+    Other(String),
+}
+
+/// Resolve a path starting at the given object.
+pub fn resolve_path<'a>(object: &ObjectWrapper<'a>, path: &str) -> Result<DynamicValue<'a>, InspectError> {
+    let mut current: Option<DynamicValue<'a>> = None;
+    let mut resolved = String::new();
+    for segment in path.split('.') {
+        let (name, indices) = parse_segment(segment)?;
+        let value = match &current {
+            None => object.try_get_field(name)
+                .ok_or_else(|| InspectError::NoSuchProperty { owner: object.name(), name: name.to_string() })?,
+            Some(value) => match value.prop().property_kind().as_str() {
+                "StructProperty" => value.clone().unwrap::<StructValueWrapper>().try_get_field(name)
+                    .ok_or_else(|| InspectError::NoSuchProperty { owner: resolved.clone(), name: name.to_string() })?,
+                _ => match read_object(value) {
+                    Some(Some(object)) => object.try_get_field(name)
+                        .ok_or_else(|| InspectError::NoSuchProperty { owner: resolved.clone(), name: name.to_string() })?,
+                    Some(None) => return Err(InspectError::NullObject(resolved)),
+                    None => return Err(InspectError::NotAContainer { path: resolved, kind: value.prop().property_kind() }),
+                },
+            },
+        };
+        if !resolved.is_empty() {
+            resolved.push('.');
+        }
+        resolved.push_str(name);
+        let mut value = value;
+        for index in indices {
+            let kind = value.prop().property_kind();
+            if kind != "ArrayProperty" {
+                return Err(InspectError::NotAnArray { path: resolved, kind });
+            }
+            let array = value.unwrap::<ArrayWrapper<DynamicValue>>();
+            value = array.get(index)
+                .ok_or(InspectError::IndexOutOfBounds { path: resolved.clone(), index, len: array.len() })?;
+            resolved.push_str(&format!("[{index}]"));
+        }
+        current = Some(value);
+    }
+    current.ok_or_else(|| InspectError::InvalidSegment(path.to_string()))
+}
+
+/// Split `Name[1][2]` into its name and indices.
+fn parse_segment(segment: &str) -> Result<(&str, Vec<usize>), InspectError> {
+    let invalid = || InspectError::InvalidSegment(segment.to_string());
+    let (name, mut rest) = segment.split_once('[').map(|(name, rest)| (name, Some(rest))).unwrap_or((segment, None));
+    if name.is_empty() {
+        return Err(invalid());
+    }
+    let mut indices = Vec::new();
+    while let Some(r) = rest {
+        let (index, r) = r.split_once(']').ok_or_else(invalid)?;
+        indices.push(index.trim().parse().map_err(|_| invalid())?);
+        rest = match r {
+            "" => None,
+            r => Some(r.strip_prefix('[').ok_or_else(invalid)?),
+        };
+    }
+    Ok((name, indices))
+}
+
+/// `None` if the value isn't an object property, `Some(None)` if the object is null
+fn read_object<'a>(value: &DynamicValue<'a>) -> Option<Option<ObjectWrapper<'a>>> {
+    value.prop().try_upcast::<ObjectPropertyWrapper>()?;
+    unsafe { Some(ObjectWrapper::new_nullable(*(value.as_ptr() as *mut *mut UObject))) }
+}
+
+/// `None` if the value isn't an enum or its underlying type isn't supported, in which case
+/// ByteProperties are handled as integers and EnumProperties as other values
+fn read_enum<'a>(value: &DynamicValue<'a>) -> Option<EnumValueWrapper<'a>> {
+    unsafe { EnumValueWrapper::from_property(value.as_ptr(), &value.prop()) }
+}
+
+/// Read the value of a property.
+pub fn read_value<'a>(value: &DynamicValue<'a>) -> InspectValue<'a> {
+    read_primitive(value).unwrap_or_else(|| InspectValue::Other(format_value(value)))
+}
+
+/// `None` if the value isn't a primitive
+fn read_primitive<'a>(value: &DynamicValue<'a>) -> Option<InspectValue<'a>> {
+    if let Some(object) = read_object(value) {
+        return Some(InspectValue::Object(object));
+    }
+    if let Some(enum_value) = read_enum(value) {
+        return Some(InspectValue::String(enum_value.name().unwrap_or_else(|| enum_value.get().to_string())));
+    }
+    let v = value.clone();
+    Some(match value.prop().property_kind().as_str() {
+        "BoolProperty" => InspectValue::Bool(v.unwrap::<BoolValueWrapper>().get()),
+        "Int8Property" => InspectValue::Int(v.unwrap::<i8>().into()),
+        "Int16Property" => InspectValue::Int(v.unwrap::<i16>().into()),
+        "IntProperty" => InspectValue::Int(v.unwrap::<i32>().into()),
+        "Int64Property" => InspectValue::Int(v.unwrap::<i64>()),
+        "ByteProperty" => InspectValue::Int(v.unwrap::<u8>().into()),
+        "UInt16Property" => InspectValue::Int(v.unwrap::<u16>().into()),
+        "UInt32Property" => InspectValue::Int(v.unwrap::<u32>().into()),
+        // reinterpreted, as rebo doesn't have unsigned 64 bit integers
+        "UInt64Property" => InspectValue::Int(v.unwrap::<u64>() as i64),
+        "FloatProperty" => InspectValue::Float(v.unwrap::<f32>().into()),
+        "DoubleProperty" => InspectValue::Float(v.unwrap::<f64>()),
+        "StrProperty" => InspectValue::String(unsafe { (*(value.as_ptr() as *const FString)).to_string_lossy() }),
+        "NameProperty" => InspectValue::String(unsafe { (*(value.as_ptr() as *const FName)).to_string_lossy() }),
+        _ => return None,
+    })
+}
+
+/// Write a value to a property, converting it to the property's type.
+///
+/// `path` is only used for error messages.
+pub fn write_value(value: &DynamicValue, path: &str, new: InspectValue) -> Result<(), InspectError> {
+    let kind = value.prop().property_kind();
+    let mismatch = |new: &InspectValue| InspectError::TypeMismatch {
+        path: path.to_string(),
+        kind: kind.clone(),
+        value: match new {
+            InspectValue::Bool(_) => "bool",
+            InspectValue::Int(_) => "int",
+            InspectValue::Float(_) => "float",
+            InspectValue::String(_) => "string",
+            InspectValue::Object(_) => "object",
+            InspectValue::Struct(_) => "struct",
+            InspectValue::Other(_) => "formatted value",
+        },
+    };
+    let out_of_range = |new: String| InspectError::OutOfRange { path: path.to_string(), kind: kind.clone(), value: new };
+    fn set_int<'a, T>(value: &DynamicValue<'a>, new: i64) -> Result<(), String>
+    where
+        T: TryFrom<i64> + Copy + 'a,
+        &'a Cell<T>: SizedArrayElement<'a>,
+    {
+        let new = T::try_from(new).map_err(|_| new.to_string())?;
+        value.clone().unwrap::<&Cell<T>>().set(new);
+        Ok(())
+    }
+
+    if read_object(value).is_some() {
+        let object = match new {
+            InspectValue::Object(object) => object,
+            new => return Err(mismatch(&new)),
+        };
+        let property_class = value.prop().upcast::<ObjectPropertyWrapper>().property_class();
+        if let Some(object) = &object {
+            if !object.class().extends_from(&property_class.name()) {
+                return Err(InspectError::IncompatibleObject {
+                    path: path.to_string(),
+                    object: object.name(),
+                    class: object.class().name(),
+                    property_class: property_class.name(),
+                });
+            }
+        }
+        let ptr = object.map(|object| object.as_ptr()).unwrap_or(std::ptr::null_mut());
+        unsafe { *(value.as_ptr() as *mut *mut UObject) = ptr };
+        return Ok(());
+    }
+    if let Some(enum_value) = read_enum(value) {
+        return match new {
+            InspectValue::String(name) => if enum_value.set_name(&name) {
+                Ok(())
+            } else {
+                Err(InspectError::NoSuchEnumValue { path: path.to_string(), name })
+            },
+            InspectValue::Int(i) => {
+                let new = u64::try_from(i).ok().filter(|&new| new <= enum_value.max_value())
+                    .ok_or_else(|| out_of_range(i.to_string()))?;
+                enum_value.set(new);
+                Ok(())
+            },
+            new => Err(mismatch(&new)),
+        };
+    }
+    match (kind.as_str(), new) {
+        ("BoolProperty", InspectValue::Bool(b)) => value.clone().unwrap::<BoolValueWrapper>().set(b),
+        ("Int8Property", InspectValue::Int(i)) => set_int::<i8>(value, i).map_err(out_of_range)?,
+        ("Int16Property", InspectValue::Int(i)) => set_int::<i16>(value, i).map_err(out_of_range)?,
+        ("IntProperty", InspectValue::Int(i)) => set_int::<i32>(value, i).map_err(out_of_range)?,
+        ("Int64Property", InspectValue::Int(i)) => set_int::<i64>(value, i).map_err(out_of_range)?,
+        ("ByteProperty", InspectValue::Int(i)) => set_int::<u8>(value, i).map_err(out_of_range)?,
+        ("UInt16Property", InspectValue::Int(i)) => set_int::<u16>(value, i).map_err(out_of_range)?,
+        ("UInt32Property", InspectValue::Int(i)) => set_int::<u32>(value, i).map_err(out_of_range)?,
+        ("UInt64Property", InspectValue::Int(i)) => value.clone().unwrap::<&Cell<u64>>().set(i as u64),
+        ("FloatProperty", InspectValue::Float(f)) => value.clone().unwrap::<&Cell<f32>>().set(f as f32),
+        ("FloatProperty", InspectValue::Int(i)) => value.clone().unwrap::<&Cell<f32>>().set(i as f32),
+        ("DoubleProperty", InspectValue::Float(f)) => value.clone().unwrap::<&Cell<f64>>().set(f),
+        ("DoubleProperty", InspectValue::Int(i)) => value.clone().unwrap::<&Cell<f64>>().set(i as f64),
+        // the old FString is dropped, freeing its memory through FMemory
+        ("StrProperty", InspectValue::String(s)) => unsafe { *(value.as_ptr() as *mut FString) = FString::from(s) },
+        ("NameProperty", InspectValue::String(s)) => unsafe { *(value.as_ptr() as *mut FName) = FName::from(s) },
+        ("StructProperty", InspectValue::Struct(fields)) => {
+            let struct_value = value.clone().unwrap::<StructValueWrapper>();
+            for (name, field) in fields {
+                let field_value = struct_value.try_get_field(&name)
+                    .ok_or_else(|| InspectError::NoSuchProperty { owner: path.to_string(), name: name.clone() })?;
+                write_value(&field_value, &format!("{path}.{name}"), field)?;
+            }
+        },
+        ("BoolProperty" | "Int8Property" | "Int16Property" | "IntProperty" | "Int64Property" | "ByteProperty"
+            | "UInt16Property" | "UInt32Property" | "UInt64Property" | "FloatProperty" | "DoubleProperty"
+            | "StrProperty" | "NameProperty" | "StructProperty", new) => return Err(mismatch(&new)),
+        _ => return Err(InspectError::Unsupported { path: path.to_string(), kind: kind.clone() }),
+    }
+    Ok(())
+}
+
+/// Human-readable representation of any property value.
+///
+/// Structs, arrays, sets and maps are formatted recursively.
+pub fn format_value(value: &DynamicValue) -> String {
+    // long arrays would flood the output
+    const MAX_ELEMENTS: usize = 16;
+    fn format_elements(len: usize, elements: impl Iterator<Item = String>) -> String {
+        let mut formatted: Vec<String> = elements.take(MAX_ELEMENTS).collect();
+        if len > MAX_ELEMENTS {
+            formatted.push(format!("... {} more", len - MAX_ELEMENTS));
+        }
+        formatted.join(", ")
+    }
+
+    let kind = value.prop().property_kind();
+    match kind.as_str() {
+        "StructProperty" => {
+            let struct_value = value.clone().unwrap::<StructValueWrapper>();
+            let fields = struct_value.iter_field_values()
+                .map(|field| format!("{}: {}", field.prop().name(), format_value(&field)))
+                .collect::<Vec<_>>();
+            format!("{} {{ {} }}", struct_value.struct_().name(), fields.join(", "))
+        },
+        "ArrayProperty" => {
+            let array = value.clone().unwrap::<ArrayWrapper<DynamicValue>>();
+            format!("[{}]", format_elements(array.len(), array.into_iter().map(|e| format_value(&e))))
+        },
+        "SetProperty" => {
+            let set = value.clone().unwrap::<SetWrapper<DynamicValue>>();
+            format!("{{{}}}", format_elements(set.len(), set.iter().map(|e| format_value(&e))))
+        },
+        "MapProperty" => {
+            let map = value.clone().unwrap::<MapWrapper<DynamicValue, DynamicValue>>();
+            format!("{{{}}}", format_elements(map.len(), map.iter().map(|(k, v)| format!("{}: {}", format_value(&k), format_value(&v)))))
+        },
+        _ => match read_primitive(value) {
+            Some(InspectValue::Bool(b)) => b.to_string(),
+            Some(InspectValue::Int(i)) => i.to_string(),
+            Some(InspectValue::Float(f)) => f.to_string(),
+            Some(InspectValue::String(s)) => format!("{s:?}"),
+            Some(InspectValue::Object(Some(object))) => format!("{} ({})", object.name(), object.class().name()),
+            Some(InspectValue::Object(None)) => "None".to_string(),
+            Some(InspectValue::Other(s)) => s,
+            Some(InspectValue::Struct(_)) => unreachable!("structs aren't read as primitives"),
+            // everything we can't read, e.g. delegates and weak object pointers
+            None => format!("<{kind} at {:p}>", value.as_ptr()),
+        },
+    }
+}
+
+/// Call a UFunction on an object like blueprints do, with arguments by parameter name.
+///
+/// All parameters except out-parameters must be passed, structs as [`InspectValue::Struct`].
+/// Returns the `ReturnValue` and all non-const out-parameters by name.
+pub fn call_function<'a>(object: &ObjectWrapper<'a>, name: &str, mut args: HashMap<String, InspectValue<'a>>) -> Result<Vec<(String, InspectValue<'a>)>, InspectError> {
+    let function = object.class().find_function(name)
+        .ok_or_else(|| InspectError::NoSuchFunction { class: object.class().name(), name: name.to_string() })?;
+    let params: Vec<_> = function.iter_params().collect();
+    if let Some(name) = args.keys().find(|arg| !params.iter().any(|param| param.name() == **arg)) {
+        return Err(InspectError::NoSuchParameter {
+            function: function.name(),
+            name: name.clone(),
+            params: params.iter().map(|param| format!("{} {}", param.property_kind(), param.name())).collect::<Vec<_>>().join(", "),
+        });
+    }
+    if params.is_empty() {
+        // ProcessEvent doesn't touch the arguments of functions without parameters
+        let mut no_args = 0u64;
+        unsafe { function.call_raw(object.as_ptr(), &mut no_args) };
+        return Ok(Vec::new());
+    }
+
+    let arg_struct = function.try_create_argument_struct().map_err(|calculated| InspectError::ParameterSize {
+        function: function.name(),
+        size: function.parms_size().into(),
+        calculated,
+    })?;
+    let result: Result<Vec<_>, InspectError> = (|| {
+        for param in &params {
+            let value = arg_struct.try_get_field(&param.name()).unwrap();
+            match args.remove(&param.name()) {
+                Some(arg) => write_value(&value, &param.name(), arg)?,
+                None if param.has_property_flag(EPropertyFlags::OutParm) || param.has_property_flag(EPropertyFlags::ReturnParm) => (),
+                None => return Err(InspectError::MissingArgument { function: function.name(), name: param.name() }),
+            }
+        }
+        unsafe { function.call(object.as_ptr(), &arg_struct) };
+        Ok(arg_struct.iter_field_values()
+            .filter(|value| {
+                let prop = value.prop();
+                prop.has_property_flag(EPropertyFlags::ReturnParm)
+                    || prop.has_property_flag(EPropertyFlags::OutParm) && !prop.has_property_flag(EPropertyFlags::ConstParm)
+            }).map(|value| (value.prop().name(), read_value(&value)))
+            .collect())
+    })();
+    // the argument struct is freed without running destructors, free what UE or we allocated
+    for value in arg_struct.iter_field_values() {
+        match value.prop().property_kind().as_str() {
+            "StrProperty" => unsafe { ptr::drop_in_place(value.as_ptr() as *mut FString) },
+            "ArrayProperty" => unsafe { ptr::drop_in_place(value.as_ptr() as *mut TArray<u8>) },
+            _ => (),
+        }
+    }
+    result
+}
+
+/// Read the arguments of a call of `function` from the parameter struct passed to `ProcessEvent`.
+///
+/// Must be called before the function runs, so the `ReturnValue` and pure out-parameters are
+/// skipped as they aren't initialized yet.
+pub unsafe fn read_arguments<'a>(function: &FunctionWrapper<'a>, params: *mut c_void) -> Vec<(String, InspectValue<'a>)> {
+    if params.is_null() || function.num_parms() == 0 {
+        return Vec::new();
+    }
+    let params = StructValueWrapper::with_limit_num_fields(params, (**function).clone(), function.num_parms() as usize);
+    params.iter_field_values()
+        .filter(|value| {
+            let prop = value.prop();
+            !prop.has_property_flag(EPropertyFlags::ReturnParm)
+                && (!prop.has_property_flag(EPropertyFlags::OutParm) || prop.has_property_flag(EPropertyFlags::ReferenceParm))
+        }).map(|value| (value.prop().name(), read_value(&value)))
+        .collect()
+}
@@ -2,14 +2,63 @@ use std::ffi::c_void;
 use std::fmt::Pointer;
 use std::cell::Cell;
 use std::ops::Deref;
-use crate::native::ue::{FName, TArray, UeU64};
+use crate::native::ue::{FName, FString, TArray, UeU64};
+
+/// Typed getters and optional setters for primitive and object properties, used within the `impl`
+/// of a wrapper which derefs to `ObjectWrapper`.
+///
+/// Properties are looked up once per class and their type is checked once.
+/// Struct and array properties are also looked up once, but only get a getter.
+/// ```ignore
+/// impl<'a> LevelWrapper<'a> {
+///     ue_fields! {
+///         pub speed / set_speed: f32 = "Speed";
+///         source_position: StructValueWrapper<'a> = "SourcePosition";
+///         fertile_lands: ArrayWrapper<'a, PlatformWrapper<'a>> = "FertileLands";
+///     }
+/// }
+/// ```
+macro_rules! ue_fields {
+    () => {};
+    ($vis:vis $getter:ident: StructValueWrapper<$l:lifetime> = $name:literal; $($rest:tt)*) => {
+        $vis fn $getter(&self) -> $crate::native::StructValueWrapper<$l> {
+            self.get_field($name).unwrap()
+        }
+        ue_fields! { $($rest)* }
+    };
+    ($vis:vis $getter:ident: ArrayWrapper<$l:lifetime, $t:ty> = $name:literal; $($rest:tt)*) => {
+        $vis fn $getter(&self) -> $crate::native::ArrayWrapper<$l, $t> {
+            self.get_field($name).unwrap()
+        }
+        ue_fields! { $($rest)* }
+    };
+    ($vis:vis $getter:ident $(/ $setter:ident)?: $t:ty = $name:literal; $($rest:tt)*) => {
+        $vis fn $getter(&self) -> $t {
+            self.get_typed_field::<$t>($name)
+        }
+        $(
+            $vis fn $setter(&self, value: $t) {
+                self.get_typed_field::<&::std::cell::Cell<$t>>($name).set(value)
+            }
+        )?
+        ue_fields! { $($rest)* }
+    };
+}
 
 mod dynamic_value;
 pub use dynamic_value::*;
 mod wrappers;
 pub use wrappers::*;
+mod containers;
+pub use containers::*;
+mod inspect;
+pub use inspect::*;
+mod dump;
+pub use dump::*;
 mod guobjectarray;
 pub use guobjectarray::*;
+#[cfg(all(test, unix))]
+mod tests;
 
 pub trait UeObjectWrapperType {
     type UeObjectWrapper<'a>: UeObjectWrapper<'a, UeObjectWrapperType = Self>;
@@ -190,7 +239,18 @@ pub struct UProperty {
     pub post_construct_link_next: *mut UProperty,
 }
 
-// Byte, Int*, UInt* are all empty
+/// subset of EPropertyFlags (CPF_*)
+#[allow(unused)]
+#[repr(u64)]
+#[derive(Debug, Clone, Copy)]
+pub enum EPropertyFlags {
+    ConstParm = 0x2,
+    Parm = 0x80,
+    OutParm = 0x100,
+    ReturnParm = 0x400,
+    ReferenceParm = 0x8000000,
+}
+
 #[repr(C)]
 pub struct UBoolProperty {
     base_uproperty: UProperty,
@@ -222,16 +282,18 @@ pub struct UArrayProperty {
     pub inner: *mut UProperty,
 }
 #[repr(C)]
-pub struct _UMapProperty {
+pub struct UMapProperty {
     pub base_uproperty: UProperty,
     pub key_prop: *mut UProperty,
     pub value_prop: *mut UProperty,
+    // the FScriptMapLayout is computed from the key and value properties instead
     //pub map_layout: FScriptMapLayout,
 }
 #[repr(C)]
-pub struct _USetProperty {
+pub struct USetProperty {
     pub base_uproperty: UProperty,
     pub element_prop: *mut UProperty,
+    // the FScriptSetLayout is computed from the element property instead
     //pub set_layout: FScriptSetLayout,
 }
 #[repr(C)]
@@ -241,21 +303,63 @@ pub struct UStructProperty {
     pub struct_: *mut UStruct,
 }
 #[repr(C)]
-pub struct _UDelegateProperty {
+pub struct UDelegateProperty {
     pub base_uproperty: UProperty,
-    //pub signature_function: *mut UFunction,
+    pub signature_function: *mut UFunction,
 }
 #[repr(C)]
-pub struct _UMulticastDelegateProperty {
+pub struct UMulticastDelegateProperty {
     pub base_uproperty: UProperty,
-    //pub signature_function: *mut UFunction,
+    pub signature_function: *mut UFunction,
 }
 // {UMulticastInlineDelegateProperty, UMulticastSparseDelegateProperty} : UMulticastDelegateProperty
 #[repr(C)]
-pub struct _UEnumProperty {
+pub struct UEnumProperty {
     pub base_uproperty: UProperty,
     // UNumericProperty - Byte, Int*, UInt*
     pub underlying_prop: *mut UProperty,
-    //pub enum_: *mut UEnum,
+    pub enum_: *mut UEnum,
+}
+// Int*, UInt* are empty, but ByteProperty can be an enum
+#[repr(C)]
+pub struct UByteProperty {
+    pub base_uproperty: UProperty,
+    // null if the byte isn't an enum
+    pub enum_: *mut UEnum,
+}
+
+#[repr(C)]
+pub struct UEnum {
+    pub base_ufield: UField,
+    pub cpp_type: FString,
+    pub names: TArray<FEnumName>,
+    // ECppForm
+    pub cpp_form: i32,
+}
+// technically TPair<FName, uint8>
+#[repr(C)]
+pub struct FEnumName {
+    pub name: FName,
+    pub value: u8,
+}
+
+// technically TWeakObjectPtr, resolved through GUObjectArray
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FWeakObjectPtr {
+    pub object_index: i32,
+    pub object_serial_number: i32,
+}
+// technically TScriptDelegate<FWeakObjectPtr>
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct FScriptDelegate {
+    pub object: FWeakObjectPtr,
+    pub function_name: FName,
+}
+// technically TMulticastScriptDelegate<FWeakObjectPtr>
+#[repr(C)]
+pub struct FMulticastScriptDelegate {
+    pub invocation_list: TArray<FScriptDelegate>,
 }
 
@@ -0,0 +1,688 @@
+//! Offline tests of the reflection layer on a synthetic object graph.
+//!
+//! The graph consists of leaked `#[repr(C)]` objects registered in a fake `GUObjectArray`.
+//! The few UE functions used by the wrappers (`FMemory`, `FName::AppendString` and
+//! `FUObjectArray::AllocateSerialNumber`) are replaced by fakes.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::{mem, ptr};
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicI32, Ordering};
+use libc::c_void;
+use memoffset::offset_of;
+use once_cell::sync::Lazy;
+use crate::native::{FMEMORY_FREE, FMEMORY_MALLOC, FNAME_APPENDSTRING, FUOBJECTARRAY_ALLOCATESERIALNUMBER, GUOBJECTARRAY};
+use crate::native::reflection::{ActorWrapper, ArrayPropertyWrapper, ArrayWrapper, BoolValueWrapper, ClassWrapper, EInternalObjectFlags, EnumValueWrapper, EPropertyFlags, FEnumName, FScriptBitArray, FScriptSet, FScriptSparseArray, FUObjectArray, FunctionWrapper, FUObjectItem, InspectError, InspectValue, MapWrapper, ObjectWrapper, ResolveObjectError, SetWrapper, StructPropertyWrapper, StructValueWrapper, TUObjectArray, UArrayProperty, UBoolProperty, UByteProperty, UClass, UEnum, UEnumProperty, UeScope, UField, UMapProperty, UObject, UObjectProperty, UProperty, USetProperty, UStruct, UStructProperty, UFunction, call_function, format_value, read_arguments, read_value, resolve_path, write_value};
+use crate::native::reflection::containers::set_element_size;
+use crate::native::process_event::observing_classes;
+use crate::native::ue::{FName, FString, FVector, TArray, UeU64};
+
+const MAX_OBJECTS: usize = 1024;
+
+static NAMES: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(vec!["None".to_string()]));
+static NEXT_SERIAL_NUMBER: AtomicI32 = AtomicI32::new(1);
+/// tests lock the graph, as they share the object array and create objects
+static GRAPH: Lazy<Mutex<Graph>> = Lazy::new(|| Mutex::new(unsafe { Graph::new() }));
+
+fn graph() -> MutexGuard<'static, Graph> {
+    // don't fail all following tests if one fails
+    GRAPH.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// FName whose number is the index into `NAMES`
+fn fname(name: &str) -> FName {
+    let mut names = NAMES.lock().unwrap();
+    let index = match names.iter().position(|n| n == name) {
+        Some(index) => index,
+        None => {
+            names.push(name.to_string());
+            names.len() - 1
+        }
+    };
+    FName { number: UeU64::new(index as u64) }
+}
+
+extern "C" fn fake_malloc(count: usize, _alignment: u32) -> *mut c_void {
+    unsafe { libc::malloc(count) }
+}
+extern "C" fn fake_free(original: *mut c_void) {
+    unsafe { libc::free(original) }
+}
+extern "C" fn fake_append_string(this: *mut FName, out: *mut FString) {
+    unsafe {
+        let index = (*this).number.get() as usize;
+        *out = FString::from(&NAMES.lock().unwrap()[index]);
+    }
+}
+extern "C" fn fake_allocate_serial_number(this: *mut FUObjectArray, object_index: i32) -> i32 {
+    unsafe {
+        let item = (*this).obj_objects.objects.offset(object_index as isize);
+        if (*item).serial_number == 0 {
+            (*item).serial_number = NEXT_SERIAL_NUMBER.fetch_add(1, Ordering::SeqCst);
+        }
+        (*item).serial_number
+    }
+}
+
+#[repr(C)]
+struct TestActor {
+    base: UObject,
+    /// `bHidden` (0x1) of Actor and `bActive` (0x4)
+    bitfield: u8,
+    full_bool: u8,
+    speed: f32,
+    level_index: i32,
+    location: FVector,
+    values: TArray<i32>,
+    locations: TArray<FVector>,
+    other: *mut UObject,
+    /// `TSet<int32>` with a free slot between its elements
+    ids: FScriptSet,
+    /// `TMap<uint8, FVector>`
+    offsets: FScriptSet,
+    /// `EMode` as ByteProperty
+    mode: u8,
+    /// `EMode` as EnumProperty with an underlying `uint32`
+    wide_mode: u32,
+}
+
+/// Element of the sparse array of a `TSet`, followed by its hash links.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+struct SetElement<T> {
+    value: T,
+    hash_next_id: i32,
+    hash_index: i32,
+}
+
+/// `TPair<uint8, FVector>`, whose value is aligned after the key
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[allow(dead_code)]
+struct OffsetPair {
+    key: u8,
+    offset: FVector,
+}
+
+/// Set without hash, containing all elements which aren't `None`.
+fn script_set<T: Copy>(elements: &[Option<T>]) -> FScriptSet {
+    assert!(elements.len() <= 128, "only the inline allocation flags are supported");
+    let mut array = TArray::with_capacity(elements.len());
+    let mut inline_data = [0u32; 4];
+    for (index, element) in elements.iter().enumerate() {
+        if element.is_some() {
+            inline_data[index / 32] |= 1 << (index % 32);
+        }
+        // free slots contain the free list links in UE, which aren't read
+        let value = element.unwrap_or_else(|| unsafe { mem::zeroed() });
+        array.push(SetElement { value, hash_next_id: -1, hash_index: 0 });
+    }
+    let data = TArray { ptr: array.ptr as *mut u8, len: array.len, capacity: array.capacity };
+    mem::forget(array);
+    let free_indices: Vec<_> = (0..elements.len()).filter(|&index| elements[index].is_none()).collect();
+    FScriptSet {
+        elements: FScriptSparseArray {
+            data,
+            allocation_flags: FScriptBitArray {
+                inline_data,
+                secondary_data: ptr::null_mut(),
+                num_bits: elements.len() as i32,
+                max_bits: 128,
+            },
+            first_free_index: free_indices.first().map_or(-1, |&index| index as i32),
+            num_free_indices: free_indices.len() as i32,
+        },
+        hash_inline_data: -1,
+        hash_secondary_data: ptr::null_mut(),
+        hash_size: 0,
+    }
+}
+
+fn tarray<T: Copy>(values: &[T]) -> TArray<T> {
+    let mut array = TArray::with_capacity(values.len());
+    for &value in values {
+        array.push(value);
+    }
+    array
+}
+
+struct Graph {
+    array: *mut FUObjectArray,
+    classes: HashMap<String, *mut UClass>,
+}
+// only accessed through GRAPH's mutex
+unsafe impl Send for Graph {}
+
+impl Graph {
+    unsafe fn new() -> Graph {
+        FMEMORY_MALLOC.store(fake_malloc as usize, Ordering::SeqCst);
+        FMEMORY_FREE.store(fake_free as usize, Ordering::SeqCst);
+        FNAME_APPENDSTRING.store(fake_append_string as usize, Ordering::SeqCst);
+        FUOBJECTARRAY_ALLOCATESERIALNUMBER.store(fake_allocate_serial_number as usize, Ordering::SeqCst);
+
+        let objects: &mut [FUObjectItem] = Box::leak((0..MAX_OBJECTS)
+            .map(|_| FUObjectItem { object: ptr::null_mut(), flags: 0, cluster_index: -1, serial_number: 0 })
+            .collect());
+        let array = Box::into_raw(Box::new(FUObjectArray {
+            obj_first_gc_index: 0,
+            obj_last_non_gc_index: -1,
+            max_objects_not_considered_by_gc: 0,
+            open_for_disregard_for_gc: false,
+            obj_objects: TUObjectArray {
+                objects: objects.as_mut_ptr(),
+                max_elements: MAX_OBJECTS as i32,
+                num_elements: 0,
+            },
+        }));
+        GUOBJECTARRAY.store(array as usize, Ordering::SeqCst);
+
+        let mut graph = Graph { array, classes: HashMap::new() };
+        let hierarchy = [
+            ("Object", None),
+            ("Field", Some("Object")),
+            ("Struct", Some("Field")),
+            ("Class", Some("Struct")),
+            ("ScriptStruct", Some("Struct")),
+            ("Function", Some("Struct")),
+            ("Enum", Some("Field")),
+            ("Property", Some("Field")),
+            ("NumericProperty", Some("Property")),
+            ("IntProperty", Some("NumericProperty")),
+            ("UInt32Property", Some("NumericProperty")),
+            ("ByteProperty", Some("NumericProperty")),
+            ("FloatProperty", Some("NumericProperty")),
+            ("BoolProperty", Some("Property")),
+            ("ArrayProperty", Some("Property")),
+            ("StructProperty", Some("Property")),
+            ("EnumProperty", Some("Property")),
+            ("SetProperty", Some("Property")),
+            ("MapProperty", Some("Property")),
+            ("ObjectPropertyBase", Some("Property")),
+            ("ObjectProperty", Some("ObjectPropertyBase")),
+            ("Actor", Some("Object")),
+            ("BP_TestActor_C", Some("Actor")),
+        ];
+        for (name, super_) in hierarchy {
+            graph.new_class(name, super_);
+        }
+        // the class of all classes only exists after it's created
+        let class_class = graph.class("Class");
+        for &class in graph.classes.values() {
+            (*(class as *mut UObject)).class = class_class;
+        }
+
+        let vector = graph.new_object::<UStruct>(graph.class("ScriptStruct"), "Vector");
+        (*vector).properties_size = mem::size_of::<FVector>() as i32;
+        (*vector).min_alignment = mem::align_of::<FVector>() as i32;
+        graph.add_property::<UProperty>(vector, "FloatProperty", "X", offset_of!(FVector, x), 4);
+        graph.add_property::<UProperty>(vector, "FloatProperty", "Y", offset_of!(FVector, y), 4);
+        graph.add_property::<UProperty>(vector, "FloatProperty", "Z", offset_of!(FVector, z), 4);
+
+        let actor = graph.class("Actor") as *mut UStruct;
+        graph.add_bool_property(actor, "bHidden", offset_of!(TestActor, bitfield), 0x1);
+
+        let test_actor = graph.class("BP_TestActor_C") as *mut UStruct;
+        (*test_actor).properties_size = mem::size_of::<TestActor>() as i32;
+        (*test_actor).min_alignment = mem::align_of::<TestActor>() as i32;
+        graph.add_bool_property(test_actor, "bActive", offset_of!(TestActor, bitfield), 0x4);
+        graph.add_bool_property(test_actor, "bFullBool", offset_of!(TestActor, full_bool), 0xff);
+        graph.add_property::<UProperty>(test_actor, "FloatProperty", "Speed", offset_of!(TestActor, speed), 4);
+        graph.add_property::<UProperty>(test_actor, "IntProperty", "LevelIndex", offset_of!(TestActor, level_index), 4);
+        let location = graph.add_property::<UStructProperty>(test_actor, "StructProperty", "Location", offset_of!(TestActor, location), mem::size_of::<FVector>());
+        (*location).struct_ = vector;
+        let values = graph.add_property::<UArrayProperty>(test_actor, "ArrayProperty", "Values", offset_of!(TestActor, values), mem::size_of::<TArray<i32>>());
+        (*values).inner = graph.new_property::<UProperty>("IntProperty", "Values", 0, 4);
+        let locations = graph.add_property::<UArrayProperty>(test_actor, "ArrayProperty", "Locations", offset_of!(TestActor, locations), mem::size_of::<TArray<FVector>>());
+        let inner = graph.new_property::<UStructProperty>("StructProperty", "Locations", 0, mem::size_of::<FVector>());
+        (*inner).struct_ = vector;
+        (*locations).inner = inner as *mut UProperty;
+        let other = graph.add_property::<UObjectProperty>(test_actor, "ObjectProperty", "Other", offset_of!(TestActor, other), mem::size_of::<*mut UObject>());
+        (*other).property_class = graph.class("Actor");
+        let ids = graph.add_property::<USetProperty>(test_actor, "SetProperty", "Ids", offset_of!(TestActor, ids), mem::size_of::<FScriptSet>());
+        (*ids).element_prop = graph.new_property::<UProperty>("IntProperty", "Ids", 0, 4);
+        let offsets = graph.add_property::<UMapProperty>(test_actor, "MapProperty", "Offsets", offset_of!(TestActor, offsets), mem::size_of::<FScriptSet>());
+        (*offsets).key_prop = graph.new_property::<UByteProperty>("ByteProperty", "Offsets_Key", 0, 1) as *mut UProperty;
+        let value = graph.new_property::<UStructProperty>("StructProperty", "Offsets", 0, mem::size_of::<FVector>());
+        (*value).struct_ = vector;
+        (*offsets).value_prop = value as *mut UProperty;
+
+        let mode_enum = graph.new_object::<UEnum>(graph.class("Enum"), "EMode");
+        let names = [("EMode::Walk", 0), ("EMode::Fly", 1), ("EMode::Swim", 200)];
+        let mut enum_names = TArray::with_capacity(names.len());
+        for (name, value) in names {
+            enum_names.push(FEnumName { name: fname(name), value });
+        }
+        (*mode_enum).names = enum_names;
+        let mode = graph.add_property::<UByteProperty>(test_actor, "ByteProperty", "Mode", offset_of!(TestActor, mode), 1);
+        (*mode).enum_ = mode_enum;
+        let wide_mode = graph.add_property::<UEnumProperty>(test_actor, "EnumProperty", "WideMode", offset_of!(TestActor, wide_mode), 4);
+        (*wide_mode).underlying_prop = graph.new_property::<UProperty>("UInt32Property", "UnderlyingType", 0, 4);
+        (*wide_mode).enum_ = mode_enum;
+
+        graph
+    }
+
+    fn class(&self, name: &str) -> *mut UClass {
+        *self.classes.get(name).unwrap_or_else(|| panic!("no class {name} in the test graph"))
+    }
+
+    fn item(&self, object: *mut UObject) -> *mut FUObjectItem {
+        unsafe { (*self.array).obj_objects.objects.offset((*object).internal_index as isize) }
+    }
+
+    /// Allocate a zeroed object and register it in the object array.
+    unsafe fn new_object<T>(&mut self, class: *mut UClass, name: &str) -> *mut T {
+        let object = Box::into_raw(Box::new(mem::zeroed::<T>()));
+        let uobject = object as *mut UObject;
+        let objects = &mut (*self.array).obj_objects;
+        let index = objects.num_elements;
+        assert!(index < objects.max_elements, "too many objects in the test graph");
+        (*objects.objects.offset(index as isize)).object = uobject;
+        objects.num_elements += 1;
+        (*uobject).internal_index = index;
+        (*uobject).class = class;
+        (*uobject).name = fname(name);
+        object
+    }
+
+    unsafe fn new_class(&mut self, name: &str, super_: Option<&str>) -> *mut UClass {
+        let super_struct = super_.map_or(ptr::null_mut(), |super_| self.class(super_) as *mut UStruct);
+        let class = self.new_object::<UClass>(ptr::null_mut(), name);
+        (*class).base_ustruct.super_struct = super_struct;
+        self.classes.insert(name.to_string(), class);
+        class
+    }
+
+    /// Property which isn't part of a struct, e.g. the inner property of an array.
+    unsafe fn new_property<P>(&mut self, kind: &str, name: &str, offset: usize, size: usize) -> *mut P {
+        let class = self.class(kind);
+        let prop = self.new_object::<P>(class, name);
+        let uprop = prop as *mut UProperty;
+        (*uprop).array_dim = 1;
+        (*uprop).element_size = size as i32;
+        (*uprop).offset_internal = offset as i32;
+        prop
+    }
+
+    /// Property appended to the children of the struct.
+    unsafe fn add_property<P>(&mut self, owner: *mut UStruct, kind: &str, name: &str, offset: usize, size: usize) -> *mut P {
+        let prop = self.new_property::<P>(kind, name, offset, size);
+        let mut link = ptr::addr_of_mut!((*owner).children);
+        while !(*link).is_null() {
+            link = ptr::addr_of_mut!((**link).next);
+        }
+        *link = prop as *mut UField;
+        prop
+    }
+
+    /// Bool property, which is a bitfield unless the mask is `0xff`.
+    unsafe fn add_bool_property(&mut self, owner: *mut UStruct, name: &str, offset: usize, mask: u8) {
+        let prop = self.add_property::<UBoolProperty>(owner, "BoolProperty", name, offset, 1);
+        (*prop).field_size = 1;
+        (*prop).byte_offset = 0;
+        (*prop).byte_mask = mask;
+        (*prop).field_mask = mask;
+    }
+
+    fn new_test_actor(&mut self, name: &str) -> *mut TestActor {
+        unsafe {
+            let actor = self.new_object::<TestActor>(self.class("BP_TestActor_C"), name);
+            (*actor).bitfield = 0x1;
+            (*actor).speed = 4.5;
+            (*actor).level_index = 3;
+            (*actor).location = FVector { x: 1., y: 2., z: 3. };
+            (*actor).values = tarray(&[1, 2, 3]);
+            (*actor).locations = tarray(&[FVector { x: 10., y: 20., z: 30. }, FVector { x: 40., y: 50., z: 60. }]);
+            (*actor).ids = script_set(&[Some(3), None, Some(7)]);
+            (*actor).offsets = script_set(&[
+                Some(OffsetPair { key: 1, offset: FVector { x: 1., y: 2., z: 3. } }),
+                Some(OffsetPair { key: 2, offset: FVector { x: 4., y: 5., z: 6. } }),
+            ]);
+            (*actor).mode = 1;
+            actor
+        }
+    }
+}
+
+fn object<'a>(actor: *mut TestActor) -> ObjectWrapper<'a> {
+    unsafe { ObjectWrapper::new(actor as *mut UObject) }
+}
+
+#[test]
+fn property_lookup() {
+    let actor = graph().new_test_actor("PropertyLookup");
+    let object = object(actor);
+    assert_eq!(object.name(), "PropertyLookup");
+    assert_eq!(object.class().name(), "BP_TestActor_C");
+    assert_eq!(object.get_field("Speed").unwrap::<f32>(), 4.5);
+    assert_eq!(object.get_field("LevelIndex").unwrap::<i32>(), 3);
+    // inherited from Actor
+    assert_eq!(object.get_field("bHidden").prop().property_kind(), "BoolProperty");
+    assert!(object.try_get_field("DoesNotExist").is_none());
+
+    let speed = object.class().find_property("Speed").unwrap();
+    assert_eq!(speed.offset(), offset_of!(TestActor, speed) as isize);
+    assert_eq!(speed.size(), 4);
+    assert_eq!(speed.property_kind(), "FloatProperty");
+    let names: Vec<_> = object.class().iter_properties().map(|prop| prop.name()).collect();
+    assert_eq!(names, ["bActive", "bFullBool", "Speed", "LevelIndex", "Location", "Values", "Locations", "Other", "Ids", "Offsets", "Mode", "WideMode", "bHidden"]);
+}
+
+#[test]
+fn typed_fields() {
+    let mut graph = graph();
+    let actor = graph.new_test_actor("TypedFields");
+    let object = object(actor);
+    assert_eq!(object.get_typed_field::<f32>("Speed"), 4.5);
+    object.get_typed_field::<&Cell<f32>>("Speed").set(7.25);
+    assert_eq!(unsafe { (*actor).speed }, 7.25);
+    assert_eq!(object.get_field("Speed").unwrap::<f32>(), 7.25);
+
+    // the cached lookup is reused for other objects of the same class
+    let other = graph.new_test_actor("TypedFields2");
+    assert_eq!(self::object(other).get_typed_field::<i32>("LevelIndex"), 3);
+}
+
+#[test]
+fn bool_bitfields() {
+    let actor = graph().new_test_actor("BoolBitfields");
+    let object = object(actor);
+    let hidden = object.get_field("bHidden").unwrap::<BoolValueWrapper>();
+    let active = object.get_field("bActive").unwrap::<BoolValueWrapper>();
+    let full = object.get_field("bFullBool").unwrap::<BoolValueWrapper>();
+    assert!(hidden.get());
+    assert!(!active.get());
+    assert!(!full.get());
+
+    active.set(true);
+    assert_eq!(unsafe { (*actor).bitfield }, 0x5);
+    hidden.set(false);
+    assert_eq!(unsafe { (*actor).bitfield }, 0x4);
+    assert!(active.get());
+    assert!(!hidden.get());
+
+    full.set(true);
+    assert_eq!(unsafe { (*actor).full_bool }, 1);
+    assert!(full.get());
+    // bitfields in other bytes are untouched
+    assert_eq!(unsafe { (*actor).bitfield }, 0x4);
+}
+
+#[test]
+fn struct_values() {
+    let actor = graph().new_test_actor("StructValues");
+    let object = object(actor);
+    let location = object.get_field("Location").unwrap::<StructValueWrapper>();
+    assert_eq!(location.struct_().name(), "Vector");
+    assert_eq!(location.get_field("Y").unwrap::<f32>(), 2.);
+    assert_eq!(location.get_typed_field::<f32>("X"), 1.);
+    location.get_field("Z").unwrap::<&Cell<f32>>().set(-3.5);
+    assert_eq!(unsafe { (*actor).location.z }, -3.5);
+    assert!(location.try_get_field("W").is_none());
+
+    fn fields<'a>(fields: &[(&str, InspectValue<'a>)]) -> InspectValue<'a> {
+        InspectValue::Struct(fields.iter().map(|(name, value)| (name.to_string(), value.clone())).collect())
+    }
+    let location = object.get_field("Location");
+    write_value(&location, "Location", fields(&[("X", InspectValue::Float(7.)), ("Y", InspectValue::Int(8))])).unwrap();
+    let written = unsafe { (*actor).location };
+    // fields which aren't given keep their value
+    assert_eq!((written.x, written.y, written.z), (7., 8., -3.5));
+    assert!(matches!(write_value(&location, "Location", fields(&[("W", InspectValue::Float(1.))])), Err(InspectError::NoSuchProperty { .. })));
+    assert!(matches!(write_value(&location, "Location", fields(&[("X", InspectValue::Bool(true))])), Err(InspectError::TypeMismatch { path, .. }) if path == "Location.X"));
+    assert!(write_value(&location, "Location", InspectValue::Float(1.)).is_err());
+}
+
+#[test]
+fn array_iteration() {
+    let actor = graph().new_test_actor("ArrayIteration");
+    let object = object(actor);
+    let values = object.get_field("Values").unwrap::<ArrayWrapper<i32>>();
+    assert_eq!(values.len(), 3);
+    assert_eq!(values.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(values.get(1), Some(2));
+    assert_eq!(values.get(3), None);
+
+    let locations = object.get_field("Locations").unwrap::<ArrayWrapper<StructValueWrapper>>();
+    let ys: Vec<f32> = locations.into_iter().map(|location| location.get_field("Y").unwrap()).collect();
+    assert_eq!(ys, [20., 50.]);
+    locations.get(1).unwrap().get_field("X").unwrap::<&Cell<f32>>().set(-1.);
+    assert_eq!(unsafe { (*actor).locations[1].x }, -1.);
+}
+
+#[test]
+fn object_properties() {
+    let mut graph = graph();
+    let target = graph.new_test_actor("Target");
+    let actor = graph.new_test_actor("ObjectProperties");
+    let object = object(actor);
+    assert!(matches!(read_value(&object.get_field("Other")), InspectValue::Object(None)));
+
+    object.get_field("Other").set_object(&self::object(target));
+    assert_eq!(unsafe { (*actor).other }, target as *mut UObject);
+    let other = object.get_field("Other").unwrap::<ObjectWrapper>();
+    assert_eq!(other.name(), "Target");
+    assert!(other.try_upcast::<ActorWrapper>().is_some());
+}
+
+#[test]
+fn inspect_paths() {
+    let mut graph = graph();
+    let target = graph.new_test_actor("InspectTarget");
+    let actor = graph.new_test_actor("InspectPaths");
+    unsafe { (*actor).other = target as *mut UObject };
+    let object = object(actor);
+
+    let read = |path| read_value(&resolve_path(&object, path).unwrap());
+    assert!(matches!(read("Location.X"), InspectValue::Float(x) if x == 1.));
+    assert!(matches!(read("Values[2]"), InspectValue::Int(3)));
+    assert!(matches!(read("Locations[1].Z"), InspectValue::Float(z) if z == 60.));
+    assert!(matches!(read("Other.LevelIndex"), InspectValue::Int(3)));
+    assert!(matches!(read("bHidden"), InspectValue::Bool(true)));
+    assert!(resolve_path(&object, "Values[3]").is_err());
+    assert!(resolve_path(&object, "Speed.X").is_err());
+    assert!(resolve_path(&object, "Location.W").is_err());
+
+    let level_index = resolve_path(&object, "Other.LevelIndex").unwrap();
+    write_value(&level_index, "Other.LevelIndex", InspectValue::Int(9)).unwrap();
+    assert_eq!(unsafe { (*target).level_index }, 9);
+    assert!(write_value(&level_index, "Other.LevelIndex", InspectValue::Int(i64::MAX)).is_err());
+}
+
+#[test]
+fn set_layout() {
+    assert_eq!(set_element_size(1, 1), mem::size_of::<SetElement<u8>>());
+    assert_eq!(set_element_size(4, 4), mem::size_of::<SetElement<i32>>());
+    assert_eq!(set_element_size(8, 8), mem::size_of::<SetElement<u64>>());
+    assert_eq!(set_element_size(mem::size_of::<FVector>(), mem::align_of::<FVector>()), mem::size_of::<SetElement<FVector>>());
+    assert_eq!(set_element_size(mem::size_of::<OffsetPair>(), mem::align_of::<OffsetPair>()), mem::size_of::<SetElement<OffsetPair>>());
+}
+
+#[test]
+fn sets() {
+    let actor = graph().new_test_actor("Sets");
+    let object = object(actor);
+    let ids = object.get_field("Ids").unwrap::<SetWrapper<i32>>();
+    // the free slot is skipped
+    assert_eq!(ids.len(), 2);
+    assert_eq!(ids.iter().collect::<Vec<_>>(), [3, 7]);
+    assert_eq!(format_value(&object.get_field("Ids")), "{3, 7}");
+    assert!(matches!(read_value(&object.get_field("Ids")), InspectValue::Other(s) if s == "{3, 7}"));
+}
+
+#[test]
+fn maps() {
+    let actor = graph().new_test_actor("Maps");
+    let object = object(actor);
+    let offsets = object.get_field("Offsets").unwrap::<MapWrapper<u8, StructValueWrapper>>();
+    assert_eq!(offsets.len(), 2);
+    let keys: Vec<_> = offsets.iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, [1, 2]);
+    let offset = offsets.find(|&key| key == 2).unwrap();
+    assert_eq!(offset.get_typed_field::<f32>("X"), 4.);
+    offset.get_field("Z").unwrap::<&Cell<f32>>().set(-6.);
+    let pairs = unsafe { (*actor).offsets.elements.data.ptr as *const SetElement<OffsetPair> };
+    assert_eq!(unsafe { (*pairs.add(1)).value.offset.z }, -6.);
+    assert!(offsets.find(|&key| key == 3).is_none());
+    assert_eq!(format_value(&object.get_field("Offsets")), "{1: Vector { X: 1, Y: 2, Z: 3 }, 2: Vector { X: 4, Y: 5, Z: -6 }}");
+}
+
+#[test]
+fn enums() {
+    let actor = graph().new_test_actor("Enums");
+    let object = object(actor);
+    let mode = object.get_field("Mode");
+    let wide_mode = object.get_field("WideMode");
+    assert!(matches!(read_value(&mode), InspectValue::String(s) if s == "Fly"));
+    assert!(matches!(read_value(&wide_mode), InspectValue::String(s) if s == "Walk"));
+    assert_eq!(wide_mode.clone().unwrap::<EnumValueWrapper>().enum_().value_of("Swim"), Some(200));
+
+    write_value(&mode, "Mode", InspectValue::String("EMode::Swim".to_string())).unwrap();
+    assert_eq!(unsafe { (*actor).mode }, 200);
+    write_value(&wide_mode, "WideMode", InspectValue::String("Fly".to_string())).unwrap();
+    assert_eq!(unsafe { (*actor).wide_mode }, 1);
+    write_value(&wide_mode, "WideMode", InspectValue::Int(200)).unwrap();
+    assert_eq!(unsafe { (*actor).wide_mode }, 200);
+    assert!(write_value(&wide_mode, "WideMode", InspectValue::String("Run".to_string())).is_err());
+    // ints are written if they fit into the underlying type
+    write_value(&wide_mode, "WideMode", InspectValue::Int(70_000)).unwrap();
+    assert_eq!(unsafe { (*actor).wide_mode }, 70_000);
+    assert!(write_value(&wide_mode, "WideMode", InspectValue::Int(1 << 32)).is_err());
+    assert!(write_value(&wide_mode, "WideMode", InspectValue::Int(-1)).is_err());
+    assert!(write_value(&mode, "Mode", InspectValue::Int(256)).is_err());
+    assert!(write_value(&mode, "Mode", InspectValue::Float(1.)).is_err());
+
+    // values without a name are read as their number
+    assert!(matches!(read_value(&wide_mode), InspectValue::String(s) if s == "70000"));
+}
+
+#[test]
+fn extends_from() {
+    let graph = graph();
+    let class = |name| unsafe { ClassWrapper::new(graph.class(name)) };
+    assert!(class("IntProperty").extends_from("IntProperty"));
+    assert!(class("IntProperty").extends_from("NumericProperty"));
+    assert!(class("IntProperty").extends_from("Property"));
+    assert!(class("IntProperty").extends_from("Object"));
+    assert!(!class("IntProperty").extends_from("StructProperty"));
+    assert!(class("BP_TestActor_C").extends_from("Actor"));
+    assert!(!class("Actor").extends_from("BP_TestActor_C"));
+    assert_eq!(class("Actor").class().name(), "Class");
+
+    let values = class("BP_TestActor_C").find_property("Values").unwrap();
+    assert!(values.try_upcast::<ArrayPropertyWrapper>().is_some());
+    assert!(values.try_upcast::<StructPropertyWrapper>().is_none());
+    assert_eq!(values.upcast::<ArrayPropertyWrapper>().inner().property_kind(), "IntProperty");
+}
+
+#[test]
+fn observed_classes() {
+    let mut graph = graph();
+    let test_actor = graph.new_test_actor("ObservedClasses");
+    let actor_class = graph.class("Actor");
+    let actor = unsafe { ObjectWrapper::new(graph.new_object::<UObject>(actor_class, "ObservedClassesActor")) };
+    // functions of Actor are observed for Actor and its subclasses, but only reported for instances
+    let class_names = || vec!["Actor".to_string(), "BP_TestActor_C".to_string(), "Pawn".to_string()];
+    assert_eq!(observing_classes(&object(test_actor), class_names()), ["Actor", "BP_TestActor_C"]);
+    assert_eq!(observing_classes(&actor, class_names()), ["Actor"]);
+}
+
+/// parameters of `SetSpeed(float NewSpeed, float& Speed, float& OldSpeed) -> float` followed by a local
+#[repr(C)]
+struct SetSpeedParams {
+    new_speed: f32,
+    speed: f32,
+    old_speed: f32,
+    return_value: f32,
+    temp: f32,
+}
+
+/// `SetSpeed` with its parameters
+fn set_speed_function(graph: &mut Graph) -> *mut UFunction {
+    unsafe {
+        let class = graph.class("Function");
+        let function = graph.new_object::<UFunction>(class, "SetSpeed");
+        let owner = function as *mut UStruct;
+        let parm = EPropertyFlags::Parm as u64;
+        let out = EPropertyFlags::OutParm as u64;
+        let params = [
+            ("NewSpeed", offset_of!(SetSpeedParams, new_speed), parm),
+            ("Speed", offset_of!(SetSpeedParams, speed), parm | out | EPropertyFlags::ReferenceParm as u64),
+            ("OldSpeed", offset_of!(SetSpeedParams, old_speed), parm | out),
+            ("ReturnValue", offset_of!(SetSpeedParams, return_value), parm | out | EPropertyFlags::ReturnParm as u64),
+            ("Temp", offset_of!(SetSpeedParams, temp), 0),
+        ];
+        for (name, offset, flags) in params {
+            let prop = graph.add_property::<UProperty>(owner, "FloatProperty", name, offset, 4);
+            (*prop).property_flags = UeU64::new(flags);
+        }
+        (*function).num_parms = 4;
+        (*function).parms_size = offset_of!(SetSpeedParams, temp) as u16;
+        function
+    }
+}
+
+#[test]
+fn function_arguments() {
+    let mut graph = graph();
+    let function = unsafe { FunctionWrapper::new(set_speed_function(&mut graph)) };
+
+    let mut params = SetSpeedParams { new_speed: 1., speed: 2., old_speed: 3., return_value: 4., temp: 5. };
+    let args = unsafe { read_arguments(&function, &mut params as *mut SetSpeedParams as *mut c_void) };
+    // out-parameters aren't initialized before the call, unless they are passed by reference
+    let names: Vec<_> = args.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["NewSpeed", "Speed"]);
+    assert!(matches!(args[0].1, InspectValue::Float(x) if x == 1.));
+    assert!(matches!(args[1].1, InspectValue::Float(x) if x == 2.));
+    assert!(unsafe { read_arguments(&function, ptr::null_mut()) }.is_empty());
+}
+
+#[test]
+fn argument_struct_size() {
+    let mut graph = graph();
+    let function = set_speed_function(&mut graph);
+    unsafe {
+        let class = graph.new_class("BP_ArgumentStructSize_C", Some("Object"));
+        (*class).base_ustruct.children = function as *mut UField;
+        let object = ObjectWrapper::new(graph.new_object::<UObject>(class, "ArgumentStructSize"));
+        assert!(FunctionWrapper::new(function).try_create_argument_struct().is_ok());
+
+        // ParmsSize doesn't end with the last parameter
+        (*function).parms_size += 4;
+        assert_eq!(FunctionWrapper::new(function).try_create_argument_struct().err(), Some(16));
+        let args = HashMap::from([("NewSpeed".to_string(), InspectValue::Float(1.))]);
+        assert!(matches!(call_function(&object, "SetSpeed", args), Err(InspectError::ParameterSize { size: 20, calculated: 16, .. })));
+    }
+}
+
+#[test]
+fn weak_handles() {
+    let mut graph = graph();
+    let actor = graph.new_test_actor("WeakHandles");
+    let ptr = actor as *mut UObject;
+    let handle = UeScope::with(|scope| scope.weak_handle(&unsafe { ObjectWrapper::new(ptr) }));
+    UeScope::with(|scope| assert_eq!(scope.resolve(handle).unwrap().name(), "WeakHandles"));
+
+    let item = graph.item(ptr);
+    unsafe { (*item).flags |= EInternalObjectFlags::PendingKill as i32 };
+    UeScope::with(|scope| assert!(matches!(scope.resolve(handle), Err(ResolveObjectError::Invalidated))));
+    unsafe { (*item).flags = 0 };
+    UeScope::with(|scope| assert!(scope.resolve(handle).is_ok()));
+    // the slot is reused by a new object after the GC collected the old one
+    unsafe { (*item).serial_number += 1 };
+    UeScope::with(|scope| assert!(matches!(scope.resolve(handle), Err(ResolveObjectError::Invalidated))));
+}
+
+#[test]
+fn root_guard() {
+    let mut graph = graph();
+    let actor = graph.new_test_actor("RootGuard");
+    let ptr = actor as *mut UObject;
+    let is_rooted = || unsafe { (*graph.item(ptr)).flags & EInternalObjectFlags::RootSet as i32 != 0 };
+    let guard = UeScope::with(|scope| scope.root(&unsafe { ObjectWrapper::new(ptr) }));
+    assert!(is_rooted());
+    drop(guard);
+    assert!(!is_rooted());
+}
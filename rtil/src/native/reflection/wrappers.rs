@@ -2,13 +2,16 @@ use std::alloc::{Layout, System, GlobalAlloc};
 use std::cell::Cell;
 use std::ffi::c_void;
 use std::fmt::{Display, Formatter, Pointer};
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::ops::Deref;
+use std::sync::Mutex;
 use std::sync::atomic::Ordering;
 use itertools::Itertools;
-use crate::native::reflection::{AActor, DynamicValue, UArrayProperty, UClass, UeObjectWrapper, UObject, UObjectProperty, UProperty, UStruct, UStructProperty};
-use crate::native::ue::TArray;
-use crate::native::{ArrayElement, UBoolProperty, UeObjectWrapperType, UField, UFunction, UOBJECT_PROCESSEVENT};
+use once_cell::sync::Lazy;
+use crate::native::reflection::{AActor, DynamicValue, EPropertyFlags, UArrayProperty, UClass, UeObjectWrapper, UObject, UObjectProperty, UProperty, UStruct, UStructProperty, UMapProperty, USetProperty, UByteProperty, UEnumProperty, UDelegateProperty, UMulticastDelegateProperty, UEnum, FScriptDelegate, FMulticastScriptDelegate, FWeakObjectPtr};
+use crate::native::ue::{FName, TArray, UeU64};
+use crate::native::{ArrayElement, SizedArrayElement, UBoolProperty, UeObjectWrapperType, UeScope, UField, UFunction, UOBJECT_PROCESSEVENT};
 
 #[derive(Debug, Clone)]
 pub struct BoolValueWrapper<'a> {
@@ -32,7 +35,7 @@ impl<'a> BoolValueWrapper<'a> {
         assert!(!ptr.is_null());
         BoolValueWrapper { ptr, bool_property, _marker: PhantomData }
     }
-    pub fn _get(&self) -> bool {
+    pub fn get(&self) -> bool {
         unsafe {
             let ptr = self.ptr.offset(self.bool_property.byte_offset() as isize);
             if self.bool_property.field_mask() == 0xff {
@@ -90,12 +93,35 @@ impl<'a> StructValueWrapper<'a> {
         assert!(!ptr.is_null());
         StructValueWrapper { ptr: ptr as *mut u8, struct_information, limit_num_fields, _marker: PhantomData }
     }
-    pub fn get_field(&self, name: &str) -> DynamicValue {
+    pub fn get_field(&self, name: &str) -> DynamicValue<'a> {
         unsafe {
             let field_info = self.struct_information.get_field_info(name, self.limit_num_fields);
             apply_field_info(self.ptr, field_info)
         }
     }
+    pub fn try_get_field(&self, name: &str) -> Option<DynamicValue<'a>> {
+        unsafe {
+            let field_info = self.struct_information.try_get_field_info(name, self.limit_num_fields)?;
+            Some(apply_field_info(self.ptr, field_info))
+        }
+    }
+    /// Like `get_field(name).unwrap::<T>()`, but the property type is only checked once per struct.
+    pub fn get_typed_field<T: SizedArrayElement<'a>>(&self, name: &str) -> T {
+        if self.limit_num_fields != usize::MAX {
+            return self.get_field(name).unwrap();
+        }
+        unsafe {
+            let offset = self.struct_information.typed_field_offset::<T>(name);
+            T::create(self.ptr.offset(offset) as *mut T::ElementType)
+        }
+    }
+    pub fn struct_(&self) -> StructWrapper<'a> {
+        self.struct_information.clone()
+    }
+    pub fn iter_field_values(&self) -> impl Iterator<Item = DynamicValue<'a>> + '_ {
+        self.struct_information.iter_properties().take(self.limit_num_fields)
+            .map(|prop| unsafe { DynamicValue::new(self.ptr.offset(prop.offset()) as *mut c_void, prop) })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -117,20 +143,27 @@ impl<'a> Pointer for OwningStructValueWrapper<'a> {
 }
 impl<'a> OwningStructValueWrapper<'a> {
     pub unsafe fn new(struct_information: StructWrapper<'a>, limit_num_fields: usize, size: usize) -> OwningStructValueWrapper<'a> {
+        OwningStructValueWrapper::try_new(struct_information, limit_num_fields, size)
+            .unwrap_or_else(|calculated_size| panic!("size {size} doesn't match the calculated size {calculated_size}"))
+    }
+    /// Returns the size calculated from the fields if it doesn't match the given size.
+    pub unsafe fn try_new(struct_information: StructWrapper<'a>, limit_num_fields: usize, size: usize) -> Result<OwningStructValueWrapper<'a>, usize> {
         // check that the size makes sense
         let mut calculated_size = 0;
         for field in struct_information.iter_fields().take(limit_num_fields) {
             let prop: PropertyWrapper = field.upcast();
             calculated_size = prop.offset() as usize + prop.size();
         }
-        assert_eq!(size, calculated_size);
+        if size != calculated_size {
+            return Err(calculated_size);
+        }
         let align = struct_information.min_alignment();
         let layout = Layout::from_size_align(size, align).unwrap();
         let ptr = System.alloc_zeroed(layout);
-        OwningStructValueWrapper {
+        Ok(OwningStructValueWrapper {
             inner: StructValueWrapper::with_limit_num_fields(ptr as *mut c_void, struct_information.clone(), limit_num_fields),
             layout,
-        }
+        })
     }
 }
 impl<'a> Drop for OwningStructValueWrapper<'a> {
@@ -139,11 +172,192 @@ impl<'a> Drop for OwningStructValueWrapper<'a> {
     }
 }
 
+/// Value of an enum, stored either in a `ByteProperty` with an enum or in an `EnumProperty`.
+#[derive(Debug, Clone)]
+pub struct EnumValueWrapper<'a> {
+    ptr: *mut u8,
+    /// size of the underlying integer in bytes, 1, 2, 4 or 8
+    size: usize,
+    enum_: EnumWrapper<'a>,
+    _marker: PhantomData<&'a mut u8>,
+}
+impl<'a> Pointer for EnumValueWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.ptr, f)
+    }
+}
+impl<'a> ArrayElement<'a> for EnumValueWrapper<'a> {
+    unsafe fn create(ptr: *mut c_void, prop: &PropertyWrapper<'a>) -> EnumValueWrapper<'a> {
+        EnumValueWrapper::from_property(ptr, prop)
+            .unwrap_or_else(|| panic!("{} {} isn't an enum with a supported underlying type", prop.property_kind(), prop.name()))
+    }
+}
+impl<'a> EnumValueWrapper<'a> {
+    pub unsafe fn new(ptr: *mut u8, size: usize, enum_: EnumWrapper<'a>) -> EnumValueWrapper<'a> {
+        assert!(!ptr.is_null());
+        assert!(matches!(size, 1 | 2 | 4 | 8), "enums with an underlying type of {size} bytes aren't supported");
+        EnumValueWrapper { ptr, size, enum_, _marker: PhantomData }
+    }
+    /// `None` if the property isn't an enum, e.g. a ByteProperty without enum, or if its
+    /// underlying type isn't an integer of 1, 2, 4 or 8 bytes
+    pub unsafe fn from_property(ptr: *mut c_void, prop: &PropertyWrapper<'a>) -> Option<EnumValueWrapper<'a>> {
+        let (enum_, size) = match prop.property_kind().as_str() {
+            "ByteProperty" => (prop.upcast::<BytePropertyWrapper<'a>>().enum_()?, 1),
+            "EnumProperty" => {
+                let enum_property = prop.upcast::<EnumPropertyWrapper<'a>>();
+                (enum_property.enum_(), enum_property.underlying_prop().size())
+            },
+            _ => return None,
+        };
+        if !matches!(size, 1 | 2 | 4 | 8) {
+            return None;
+        }
+        Some(EnumValueWrapper::new(ptr as *mut u8, size, enum_))
+    }
+    pub fn enum_(&self) -> EnumWrapper<'a> {
+        self.enum_.clone()
+    }
+    /// zero-extended value of the underlying integer
+    pub fn get(&self) -> u64 {
+        unsafe {
+            match self.size {
+                1 => (*self.ptr).into(),
+                2 => (*(self.ptr as *mut u16)).into(),
+                4 => (*(self.ptr as *mut u32)).into(),
+                _ => *(self.ptr as *mut u64),
+            }
+        }
+    }
+    /// largest value the underlying integer can hold
+    pub fn max_value(&self) -> u64 {
+        u64::MAX >> (64 - 8 * self.size)
+    }
+    /// set the underlying integer, panics if the value is larger than [`max_value`](Self::max_value)
+    pub fn set(&self, value: u64) {
+        assert!(value <= self.max_value(), "{value} doesn't fit into an enum of {} bytes", self.size);
+        unsafe {
+            match self.size {
+                1 => *self.ptr = value as u8,
+                2 => *(self.ptr as *mut u16) = value as u16,
+                4 => *(self.ptr as *mut u32) = value as u32,
+                _ => *(self.ptr as *mut u64) = value,
+            }
+        }
+    }
+    /// name of the current value without the `EnumName::` prefix
+    pub fn name(&self) -> Option<String> {
+        u8::try_from(self.get()).ok().and_then(|value| self.enum_.name_of(value))
+    }
+    /// set the value by its name, returning `false` if the enum has no such name
+    pub fn set_name(&self, name: &str) -> bool {
+        match self.enum_.value_of(name) {
+            Some(value) => {
+                self.set(value.into());
+                true
+            },
+            None => false,
+        }
+    }
+}
+
+/// Object and function a delegate is bound to.
+#[derive(Debug, Clone)]
+pub struct DelegateBinding {
+    object: FWeakObjectPtr,
+    function_name: String,
+}
+impl DelegateBinding {
+    fn from_script_delegate(delegate: &FScriptDelegate) -> Option<DelegateBinding> {
+        if delegate.function_name.number == FName::NAME_None.number {
+            return None;
+        }
+        Some(DelegateBinding { object: delegate.object, function_name: delegate.function_name.to_string_lossy() })
+    }
+    /// `None` if the bound object was destroyed
+    pub fn object<'a>(&self, scope: &'a UeScope) -> Option<ObjectWrapper<'a>> {
+        scope.resolve_weak_object_ptr(self.object)
+    }
+    pub fn function_name(&self) -> &str {
+        &self.function_name
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DelegateWrapper<'a> {
+    ptr: *mut FScriptDelegate,
+    _marker: PhantomData<&'a mut FScriptDelegate>,
+}
+impl<'a> Pointer for DelegateWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.ptr, f)
+    }
+}
+impl<'a> ArrayElement<'a> for DelegateWrapper<'a> {
+    unsafe fn create(ptr: *mut c_void, prop: &PropertyWrapper<'a>) -> DelegateWrapper<'a> {
+        assert_eq!(prop.property_kind(), "DelegateProperty");
+        DelegateWrapper::new(ptr as *mut FScriptDelegate)
+    }
+}
+impl<'a> DelegateWrapper<'a> {
+    pub unsafe fn new(ptr: *mut FScriptDelegate) -> DelegateWrapper<'a> {
+        assert!(!ptr.is_null());
+        DelegateWrapper { ptr, _marker: PhantomData }
+    }
+    /// `None` if the delegate isn't bound
+    pub fn binding(&self) -> Option<DelegateBinding> {
+        unsafe { DelegateBinding::from_script_delegate(&*self.ptr) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MulticastDelegateWrapper<'a> {
+    ptr: *mut FMulticastScriptDelegate,
+    _marker: PhantomData<&'a mut FMulticastScriptDelegate>,
+}
+impl<'a> Pointer for MulticastDelegateWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.ptr, f)
+    }
+}
+impl<'a> ArrayElement<'a> for MulticastDelegateWrapper<'a> {
+    unsafe fn create(ptr: *mut c_void, prop: &PropertyWrapper<'a>) -> MulticastDelegateWrapper<'a> {
+        assert!(prop.class().extends_from("MulticastDelegateProperty"), "{} isn't a multicast delegate", prop.name());
+        MulticastDelegateWrapper::new(ptr as *mut FMulticastScriptDelegate)
+    }
+}
+impl<'a> MulticastDelegateWrapper<'a> {
+    pub unsafe fn new(ptr: *mut FMulticastScriptDelegate) -> MulticastDelegateWrapper<'a> {
+        assert!(!ptr.is_null());
+        MulticastDelegateWrapper { ptr, _marker: PhantomData }
+    }
+    pub fn bindings(&self) -> Vec<DelegateBinding> {
+        unsafe { (&(*self.ptr).invocation_list).into_iter().filter_map(DelegateBinding::from_script_delegate).collect() }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FieldInfo<'a> {
     offset: isize,
     prop: PropertyWrapper<'a>,
 }
+/// Resolved properties keyed by the address of their UStruct and then by their name, such that
+/// lookups don't need to allocate.
+///
+/// Classes and structs are never unloaded in Refunct, so entries never get stale.
+static FIELD_CACHE: Lazy<Mutex<HashMap<usize, HashMap<String, Option<CachedField>>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+#[derive(Debug)]
+struct CachedField {
+    offset: isize,
+    /// `*mut UProperty`, which isn't `Send`
+    prop: usize,
+    /// `type_name`s of the `SizedArrayElement`s the property type was checked against
+    checked_types: Vec<&'static str>,
+}
+impl CachedField {
+    fn field_info<'a>(&self) -> FieldInfo<'a> {
+        FieldInfo { offset: self.offset, prop: unsafe { PropertyWrapper::new(self.prop as *mut UProperty) } }
+    }
+}
 #[derive(Debug, Clone)]
 pub struct ObjectWrapper<'a> {
     object: *mut UObject,
@@ -198,6 +412,23 @@ impl<'a> ObjectWrapper<'a> {
             apply_field_info(self.object as *mut u8, field_info)
         }
     }
+    pub fn try_get_field(&self, name: &str) -> Option<DynamicValue<'a>> {
+        unsafe {
+            let field_info = self.class().try_get_field_info(name, usize::MAX)?;
+            Some(apply_field_info(self.object as *mut u8, field_info))
+        }
+    }
+    /// Like `get_field(name).unwrap::<T>()`, but the property type is only checked once per class.
+    pub fn get_typed_field<T: SizedArrayElement<'a>>(&self, name: &str) -> T {
+        unsafe {
+            let offset = self.class().typed_field_offset::<T>(name);
+            T::create((self.object as *mut u8).offset(offset) as *mut T::ElementType)
+        }
+    }
+    pub fn iter_field_values(&self) -> impl Iterator<Item = DynamicValue<'a>> + '_ {
+        self.class().iter_properties()
+            .map(|prop| unsafe { DynamicValue::new((self.object as *mut u8).offset(prop.offset()) as *mut c_void, prop) })
+    }
 
     pub fn upcast<T: UeObjectWrapper<'a>>(&self) -> T {
         self.try_upcast().unwrap_or_else(|| panic!("can't upcast {} to {}", self.class().name(), T::CLASS_NAME))
@@ -323,6 +554,31 @@ impl<'a> PropertyWrapper<'a> {
     pub fn size(&self) -> usize {
         unsafe { (*self.as_ptr()).element_size.try_into().unwrap() }
     }
+    pub fn array_dim(&self) -> usize {
+        unsafe { (*self.as_ptr()).array_dim.try_into().unwrap() }
+    }
+    pub fn property_flags(&self) -> u64 {
+        unsafe { (*self.as_ptr()).property_flags.get() }
+    }
+    pub fn has_property_flag(&self, flag: EPropertyFlags) -> bool {
+        self.property_flags() & flag as u64 != 0
+    }
+    /// `UProperty::GetMinAlignment` is virtual, so it's derived from the property kind instead
+    pub fn min_alignment(&self) -> usize {
+        match self.property_kind().as_str() {
+            "StructProperty" => self.upcast::<StructPropertyWrapper>().struct_().min_alignment(),
+            "EnumProperty" => self.upcast::<EnumPropertyWrapper>().underlying_prop().min_alignment(),
+            "BoolProperty" | "ByteProperty" | "Int8Property" => 1,
+            "Int16Property" | "UInt16Property" => 2,
+            "IntProperty" | "UInt32Property" | "FloatProperty" => 4,
+            // FName is two i32, but aligned like a u64
+            "Int64Property" | "UInt64Property" | "DoubleProperty" | "NameProperty" => std::mem::align_of::<UeU64>(),
+            // DelegateProperty contains an FName
+            "DelegateProperty" => std::mem::align_of::<FScriptDelegate>(),
+            // pointers and everything containing pointers like arrays, strings, maps and sets
+            _ => std::mem::align_of::<usize>(),
+        }
+    }
 
     pub fn iter_this_and_next_properties(&self) -> impl Iterator<Item = PropertyWrapper<'a>> {
         self.iter_this_and_next_fields().map(|field| field.upcast())
@@ -508,6 +764,325 @@ impl<'a> BoolPropertyWrapper<'a> {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct MapPropertyWrapper<'a> {
+    base: PropertyWrapper<'a>,
+}
+pub enum MapPropertyWrapperType {}
+impl UeObjectWrapperType for MapPropertyWrapperType {
+    type UeObjectWrapper<'a> = MapPropertyWrapper<'a>;
+}
+unsafe impl<'a> UeObjectWrapper<'a> for MapPropertyWrapper<'a> {
+    type UeObjectWrapperType = MapPropertyWrapperType;
+    type Wrapping = UMapProperty;
+    const CLASS_NAME: &'static str = "MapProperty";
+
+    unsafe fn create(ptr: *mut Self::Wrapping) -> MapPropertyWrapper<'a> {
+        MapPropertyWrapper::new(ptr)
+    }
+}
+impl<'a> Deref for MapPropertyWrapper<'a> {
+    type Target = PropertyWrapper<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+impl<'a> Pointer for MapPropertyWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+impl<'a> MapPropertyWrapper<'a> {
+    pub unsafe fn new(prop: *mut UMapProperty) -> MapPropertyWrapper<'a> {
+        assert!(!prop.is_null());
+        MapPropertyWrapper { base: PropertyWrapper::new(prop as *mut UProperty) }
+    }
+    pub fn as_ptr(&self) -> *mut UMapProperty {
+        self.base.as_ptr() as *mut UMapProperty
+    }
+    pub fn key_prop(&self) -> PropertyWrapper<'a> {
+        unsafe { PropertyWrapper::new((*self.as_ptr()).key_prop) }
+    }
+    pub fn value_prop(&self) -> PropertyWrapper<'a> {
+        unsafe { PropertyWrapper::new((*self.as_ptr()).value_prop) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SetPropertyWrapper<'a> {
+    base: PropertyWrapper<'a>,
+}
+pub enum SetPropertyWrapperType {}
+impl UeObjectWrapperType for SetPropertyWrapperType {
+    type UeObjectWrapper<'a> = SetPropertyWrapper<'a>;
+}
+unsafe impl<'a> UeObjectWrapper<'a> for SetPropertyWrapper<'a> {
+    type UeObjectWrapperType = SetPropertyWrapperType;
+    type Wrapping = USetProperty;
+    const CLASS_NAME: &'static str = "SetProperty";
+
+    unsafe fn create(ptr: *mut Self::Wrapping) -> SetPropertyWrapper<'a> {
+        SetPropertyWrapper::new(ptr)
+    }
+}
+impl<'a> Deref for SetPropertyWrapper<'a> {
+    type Target = PropertyWrapper<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+impl<'a> Pointer for SetPropertyWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+impl<'a> SetPropertyWrapper<'a> {
+    pub unsafe fn new(prop: *mut USetProperty) -> SetPropertyWrapper<'a> {
+        assert!(!prop.is_null());
+        SetPropertyWrapper { base: PropertyWrapper::new(prop as *mut UProperty) }
+    }
+    pub fn as_ptr(&self) -> *mut USetProperty {
+        self.base.as_ptr() as *mut USetProperty
+    }
+    pub fn element_prop(&self) -> PropertyWrapper<'a> {
+        unsafe { PropertyWrapper::new((*self.as_ptr()).element_prop) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BytePropertyWrapper<'a> {
+    base: PropertyWrapper<'a>,
+}
+pub enum BytePropertyWrapperType {}
+impl UeObjectWrapperType for BytePropertyWrapperType {
+    type UeObjectWrapper<'a> = BytePropertyWrapper<'a>;
+}
+unsafe impl<'a> UeObjectWrapper<'a> for BytePropertyWrapper<'a> {
+    type UeObjectWrapperType = BytePropertyWrapperType;
+    type Wrapping = UByteProperty;
+    const CLASS_NAME: &'static str = "ByteProperty";
+
+    unsafe fn create(ptr: *mut Self::Wrapping) -> BytePropertyWrapper<'a> {
+        BytePropertyWrapper::new(ptr)
+    }
+}
+impl<'a> Deref for BytePropertyWrapper<'a> {
+    type Target = PropertyWrapper<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+impl<'a> Pointer for BytePropertyWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+impl<'a> BytePropertyWrapper<'a> {
+    pub unsafe fn new(prop: *mut UByteProperty) -> BytePropertyWrapper<'a> {
+        assert!(!prop.is_null());
+        BytePropertyWrapper { base: PropertyWrapper::new(prop as *mut UProperty) }
+    }
+    pub fn as_ptr(&self) -> *mut UByteProperty {
+        self.base.as_ptr() as *mut UByteProperty
+    }
+    pub fn enum_(&self) -> Option<EnumWrapper<'a>> {
+        unsafe { EnumWrapper::new_nullable((*self.as_ptr()).enum_) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumPropertyWrapper<'a> {
+    base: PropertyWrapper<'a>,
+}
+pub enum EnumPropertyWrapperType {}
+impl UeObjectWrapperType for EnumPropertyWrapperType {
+    type UeObjectWrapper<'a> = EnumPropertyWrapper<'a>;
+}
+unsafe impl<'a> UeObjectWrapper<'a> for EnumPropertyWrapper<'a> {
+    type UeObjectWrapperType = EnumPropertyWrapperType;
+    type Wrapping = UEnumProperty;
+    const CLASS_NAME: &'static str = "EnumProperty";
+
+    unsafe fn create(ptr: *mut Self::Wrapping) -> EnumPropertyWrapper<'a> {
+        EnumPropertyWrapper::new(ptr)
+    }
+}
+impl<'a> Deref for EnumPropertyWrapper<'a> {
+    type Target = PropertyWrapper<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+impl<'a> Pointer for EnumPropertyWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+impl<'a> EnumPropertyWrapper<'a> {
+    pub unsafe fn new(prop: *mut UEnumProperty) -> EnumPropertyWrapper<'a> {
+        assert!(!prop.is_null());
+        EnumPropertyWrapper { base: PropertyWrapper::new(prop as *mut UProperty) }
+    }
+    pub fn as_ptr(&self) -> *mut UEnumProperty {
+        self.base.as_ptr() as *mut UEnumProperty
+    }
+    pub fn underlying_prop(&self) -> PropertyWrapper<'a> {
+        unsafe { PropertyWrapper::new((*self.as_ptr()).underlying_prop) }
+    }
+    pub fn enum_(&self) -> EnumWrapper<'a> {
+        unsafe { EnumWrapper::new((*self.as_ptr()).enum_) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DelegatePropertyWrapper<'a> {
+    base: PropertyWrapper<'a>,
+}
+pub enum DelegatePropertyWrapperType {}
+impl UeObjectWrapperType for DelegatePropertyWrapperType {
+    type UeObjectWrapper<'a> = DelegatePropertyWrapper<'a>;
+}
+unsafe impl<'a> UeObjectWrapper<'a> for DelegatePropertyWrapper<'a> {
+    type UeObjectWrapperType = DelegatePropertyWrapperType;
+    type Wrapping = UDelegateProperty;
+    const CLASS_NAME: &'static str = "DelegateProperty";
+
+    unsafe fn create(ptr: *mut Self::Wrapping) -> DelegatePropertyWrapper<'a> {
+        DelegatePropertyWrapper::new(ptr)
+    }
+}
+impl<'a> Deref for DelegatePropertyWrapper<'a> {
+    type Target = PropertyWrapper<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+impl<'a> Pointer for DelegatePropertyWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+impl<'a> DelegatePropertyWrapper<'a> {
+    pub unsafe fn new(prop: *mut UDelegateProperty) -> DelegatePropertyWrapper<'a> {
+        assert!(!prop.is_null());
+        DelegatePropertyWrapper { base: PropertyWrapper::new(prop as *mut UProperty) }
+    }
+    pub fn as_ptr(&self) -> *mut UDelegateProperty {
+        self.base.as_ptr() as *mut UDelegateProperty
+    }
+    pub fn signature_function(&self) -> FunctionWrapper<'a> {
+        unsafe { FunctionWrapper::new((*self.as_ptr()).signature_function) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MulticastDelegatePropertyWrapper<'a> {
+    base: PropertyWrapper<'a>,
+}
+pub enum MulticastDelegatePropertyWrapperType {}
+impl UeObjectWrapperType for MulticastDelegatePropertyWrapperType {
+    type UeObjectWrapper<'a> = MulticastDelegatePropertyWrapper<'a>;
+}
+unsafe impl<'a> UeObjectWrapper<'a> for MulticastDelegatePropertyWrapper<'a> {
+    type UeObjectWrapperType = MulticastDelegatePropertyWrapperType;
+    type Wrapping = UMulticastDelegateProperty;
+    const CLASS_NAME: &'static str = "MulticastDelegateProperty";
+
+    unsafe fn create(ptr: *mut Self::Wrapping) -> MulticastDelegatePropertyWrapper<'a> {
+        MulticastDelegatePropertyWrapper::new(ptr)
+    }
+}
+impl<'a> Deref for MulticastDelegatePropertyWrapper<'a> {
+    type Target = PropertyWrapper<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+impl<'a> Pointer for MulticastDelegatePropertyWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+impl<'a> MulticastDelegatePropertyWrapper<'a> {
+    pub unsafe fn new(prop: *mut UMulticastDelegateProperty) -> MulticastDelegatePropertyWrapper<'a> {
+        assert!(!prop.is_null());
+        MulticastDelegatePropertyWrapper { base: PropertyWrapper::new(prop as *mut UProperty) }
+    }
+    pub fn as_ptr(&self) -> *mut UMulticastDelegateProperty {
+        self.base.as_ptr() as *mut UMulticastDelegateProperty
+    }
+    pub fn signature_function(&self) -> FunctionWrapper<'a> {
+        unsafe { FunctionWrapper::new((*self.as_ptr()).signature_function) }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EnumWrapper<'a> {
+    base: FieldWrapper<'a>,
+}
+pub enum EnumWrapperType {}
+impl UeObjectWrapperType for EnumWrapperType {
+    type UeObjectWrapper<'a> = EnumWrapper<'a>;
+}
+unsafe impl<'a> UeObjectWrapper<'a> for EnumWrapper<'a> {
+    type UeObjectWrapperType = EnumWrapperType;
+    type Wrapping = UEnum;
+    const CLASS_NAME: &'static str = "Enum";
+
+    unsafe fn create(ptr: *mut Self::Wrapping) -> EnumWrapper<'a> {
+        EnumWrapper::new(ptr)
+    }
+}
+impl<'a> Deref for EnumWrapper<'a> {
+    type Target = FieldWrapper<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+impl<'a> Pointer for EnumWrapper<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Pointer::fmt(&self.as_ptr(), f)
+    }
+}
+impl<'a> EnumWrapper<'a> {
+    pub unsafe fn new(enum_: *mut UEnum) -> EnumWrapper<'a> {
+        assert!(!enum_.is_null());
+        EnumWrapper { base: FieldWrapper::new(enum_ as *mut UField) }
+    }
+    pub fn as_ptr(&self) -> *mut UEnum {
+        self.base.as_ptr() as *mut UEnum
+    }
+    pub unsafe fn new_nullable(enum_: *mut UEnum) -> Option<EnumWrapper<'a>> {
+        (!enum_.is_null()).then(|| EnumWrapper::new(enum_))
+    }
+    /// names with their values, namespaced enums prefix the names with `EnumName::`
+    pub fn names(&self) -> Vec<(String, u8)> {
+        unsafe { (&(*self.as_ptr()).names).into_iter().map(|name| (name.name.to_string_lossy(), name.value)).collect() }
+    }
+    /// name of the value without the `EnumName::` prefix
+    pub fn name_of(&self, value: u8) -> Option<String> {
+        self.names().into_iter()
+            .find(|&(_, v)| v == value)
+            .map(|(name, _)| short_enum_name(&name).to_string())
+    }
+    /// value of the name, with or without the `EnumName::` prefix
+    pub fn value_of(&self, name: &str) -> Option<u8> {
+        self.names().into_iter()
+            .find(|(n, _)| n == name || short_enum_name(n) == name)
+            .map(|(_, value)| value)
+    }
+}
+fn short_enum_name(name: &str) -> &str {
+    name.rsplit_once("::").map_or(name, |(_, short)| short)
+}
+
 #[derive(Debug, Clone)]
 pub struct StructWrapper<'a> {
     base: FieldWrapper<'a>,
@@ -614,7 +1189,55 @@ impl<'a> StructWrapper<'a> {
         self.iter_functions().find(|f| f.name() == name)
     }
 
-    fn get_field_info(&self, mut name: &str, limit_num_fields: usize) -> FieldInfo<'a> {
+    fn get_field_info(&self, name: &str, limit_num_fields: usize) -> FieldInfo<'a> {
+        self.try_get_field_info(name, limit_num_fields).unwrap_or_else(|| panic!("cannot access property {name} of type {}, properties available: {}", self.class().name(),
+            self.iter_properties().take(limit_num_fields).map(|prop| format!("{} {}", prop.class().name(), prop.name())).join(", "),
+        ))
+    }
+    fn try_get_field_info(&self, name: &str, limit_num_fields: usize) -> Option<FieldInfo<'a>> {
+        // limited lookups are only used for function arguments, which aren't worth caching
+        if limit_num_fields != usize::MAX {
+            return self.find_field_info(name, limit_num_fields);
+        }
+        self.with_cached_field(name, |cached| cached.map(|cached| cached.field_info()))
+    }
+    /// Offset of a property, whose type is checked against `T` only on the first access.
+    fn typed_field_offset<T: SizedArrayElement<'a>>(&self, name: &str) -> isize {
+        let type_name = std::any::type_name::<T>();
+        let cached = self.with_cached_field(name, |cached| cached.map(|cached| (cached.offset, cached.prop, cached.checked_types.contains(&type_name))));
+        let (offset, prop, checked) = match cached {
+            Some(cached) => cached,
+            None => return self.get_field_info(name, usize::MAX).offset,
+        };
+        if !checked {
+            // outside of the lock, as it calls into UE and panics on mismatches
+            T::check_property_type(&unsafe { PropertyWrapper::new(prop as *mut UProperty) });
+            self.with_cached_field(name, |cached| if let Some(cached) = cached {
+                if !cached.checked_types.contains(&type_name) {
+                    cached.checked_types.push(type_name);
+                }
+            });
+        }
+        offset
+    }
+    /// `f` is called while `FIELD_CACHE` is locked and must not call into UE.
+    fn with_cached_field<R>(&self, name: &str, f: impl FnOnce(Option<&mut CachedField>) -> R) -> R {
+        let key = self.as_ptr() as usize;
+        if let Some(cached) = FIELD_CACHE.lock().unwrap().get_mut(&key).and_then(|fields| fields.get_mut(name)) {
+            return f(cached.as_mut());
+        }
+        // resolved outside of the lock, as it walks the properties of the struct
+        let resolved = self.find_field_info(name, usize::MAX).map(|info| CachedField {
+            offset: info.offset,
+            prop: info.prop.as_ptr() as usize,
+            checked_types: Vec::new(),
+        });
+        let mut cache = FIELD_CACHE.lock().unwrap();
+        let cached = cache.entry(key).or_default()
+            .entry(name.to_string()).or_insert(resolved);
+        f(cached.as_mut())
+    }
+    fn find_field_info(&self, mut name: &str, limit_num_fields: usize) -> Option<FieldInfo<'a>> {
         let hacked_absolute = (name == "AbsoluteLocation" || name == "AbsoluteRotation" || name == "AbsoluteScale3D") && self.extends_from("SceneComponent");
         if hacked_absolute {
             match name {
@@ -625,10 +1248,7 @@ impl<'a> StructWrapper<'a> {
             }
         }
         let prop = self.iter_properties().take(limit_num_fields)
-            .find(|prop| prop.name() == name)
-            .unwrap_or_else(|| panic!("cannot access property {name} of type {}, properties available: {}", self.class().name(),
-                self.iter_properties().take(limit_num_fields).map(|prop| format!("{} {}", prop.class().name(), prop.name())).join(", "),
-            )).upcast::<PropertyWrapper>();
+            .find(|prop| prop.name() == name)?;
         let offset = if hacked_absolute {
             match name {
                 "RelativeLocation" => {
@@ -648,7 +1268,7 @@ impl<'a> StructWrapper<'a> {
         } else {
             prop.offset()
         };
-        FieldInfo { offset, prop }
+        Some(FieldInfo { offset, prop })
     }
 
     pub fn extends_from(&self, name: &str) -> bool {
@@ -744,6 +1364,9 @@ impl<'a> FunctionWrapper<'a> {
         self.base.as_ptr() as *mut UFunction
     }
 
+    pub fn function_flags(&self) -> u32 {
+        unsafe { (*self.as_ptr()).function_flags }
+    }
     pub fn num_parms(&self) -> u8 {
         unsafe { (*self.as_ptr()).num_parms }
     }
@@ -760,6 +1383,12 @@ impl<'a> FunctionWrapper<'a> {
             OwningStructValueWrapper::new((**self).clone(), self.num_parms() as usize, self.parms_size() as usize)
         }
     }
+    /// Returns the size calculated from the parameters if it doesn't match `ParmsSize`.
+    pub fn try_create_argument_struct(&self) -> Result<OwningStructValueWrapper<'a>, usize> {
+        unsafe {
+            OwningStructValueWrapper::try_new((**self).clone(), self.num_parms() as usize, self.parms_size() as usize)
+        }
+    }
     pub unsafe fn call<This>(&self, this: *mut This, args: &OwningStructValueWrapper<'a>) {
         self.call_raw(this, args.as_ptr())
     }
@@ -909,6 +1538,17 @@ impl<'a> ActorWrapper<'a> {
         relative_scale.get_field("Y").unwrap::<&Cell<f32>>().set(yscale);
         relative_scale.get_field("Z").unwrap::<&Cell<f32>>().set(zscale);
     }
+    pub fn is_hidden_in_game(&self) -> bool {
+        self.get_field("bHidden").unwrap::<BoolValueWrapper>().get()
+    }
+    /// Hide the actor and disable its collision, or show it and enable its collision.
+    pub fn set_hidden_in_game(&self, hidden: bool) {
+        let set_actor_hidden_in_game = self.class().find_function("SetActorHiddenInGame").unwrap();
+        let args = set_actor_hidden_in_game.create_argument_struct();
+        args.get_field("bNewHidden").unwrap::<BoolValueWrapper>().set(hidden);
+        unsafe { set_actor_hidden_in_game.call(self.as_ptr(), &args) };
+        AActor::set_actor_enable_collision(self.as_ptr(), !hidden);
+    }
     /// origin x,y,z, half-size x,y,z
     pub fn get_actor_bounds(&self) -> (f32, f32, f32, f32, f32, f32) {
         let get_actor_bounds = self.class().find_function("GetActorBounds").unwrap();
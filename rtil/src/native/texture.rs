@@ -2,19 +2,20 @@ use std::{mem, ptr};
 use std::sync::atomic::Ordering;
 use image::RgbaImage;
 use crate::native::{FUNTYPEDBULKDATA_LOCK, FUNTYPEDBULKDATA_UNLOCK, UTEXTURE2D_CREATETRANSIENT, UTEXTURE2D_GETRUNNINGPLATFORMDATA, UTEXTURE2D_UPDATERESOURCE};
-use crate::native::reflection::{GlobalObjectArrayWrapper, ObjectWrapper, UObject};
+use crate::native::reflection::{ObjectWrapper, RootGuard, UeScope, UObject};
 use crate::native::ue::TArray;
 
-pub struct UTexture2D(*mut UTexture2DUE);
+pub struct UTexture2D {
+    texture: *mut UTexture2DUE,
+    /// keeps the texture from being cleaned by the GC
+    _root: RootGuard,
+}
 pub(in crate::native) enum UTexture2DUE {}
 
 // WARNING: somewhat unsound - see AMyCharacter
 unsafe impl Send for UTexture2D {}
 
 impl UTexture2D {
-    fn as_object(&self) -> ObjectWrapper {
-        unsafe { ObjectWrapper::new(self.0 as *mut UObject) }
-    }
     fn create_transient(width: i32, height: i32, format: EPixelFormat) -> *mut UTexture2DUE {
         let fun: extern "C" fn(
             in_size_x: i32, in_size_y: i32, in_format: EPixelFormat
@@ -26,14 +27,14 @@ impl UTexture2D {
         let fun: extern_fn!(fn(
             this: *mut UTexture2DUE
         ) -> *mut *mut FTexturePlatformData) = unsafe { mem::transmute(UTEXTURE2D_GETRUNNINGPLATFORMDATA.load(Ordering::SeqCst)) };
-        fun(self.0)
+        fun(self.texture)
     }
 
     fn update_resource(&mut self) {
         let fun: extern_fn!(fn(
             this: *mut UTexture2DUE
         )) = unsafe { mem::transmute(UTEXTURE2D_UPDATERESOURCE.load(Ordering::SeqCst)) };
-        fun(self.0)
+        fun(self.texture)
     }
 
     pub fn width(&self) -> i32 {
@@ -44,7 +45,7 @@ impl UTexture2D {
     }
 
     pub(in crate::native) fn as_ptr(&self) -> *mut UTexture2DUE {
-        self.0
+        self.texture
     }
 
     pub fn set_image(&mut self, image: &RgbaImage) {
@@ -66,23 +67,11 @@ impl UTexture2D {
         let height = image.height().try_into().unwrap();
         let texture = UTexture2D::create_transient(width, height, EPixelFormat::R8G8B8A8);
         log!("texture: {:p}", texture);
-        let mut texture = UTexture2D(texture);
+        let root = UeScope::with(|scope| scope.root(&unsafe { ObjectWrapper::new(texture as *mut UObject) }));
+        let mut texture = UTexture2D { texture, _root: root };
         texture.set_image(image);
-        // mark texture as root-object to not be cleaned by the GC
-        texture.mark_as_root_object(true);
         texture
     }
-
-    fn mark_as_root_object(&self, val: bool) {
-        unsafe { GlobalObjectArrayWrapper::get().object_array().get_item_of_object(&self.as_object()).mark_as_root_object(val) }
-    }
-}
-
-impl Drop for UTexture2D {
-    fn drop(&mut self) {
-        // mark texture as non-root-object to be cleaned by the GC
-        self.mark_as_root_object(false)
-    }
 }
 
 #[repr(C)]
@@ -3,6 +3,8 @@ use crate::native::UObject;
 
 #[rtil_derive::hook_after(UEngine::UpdateTimeAndHandleMaxTickRate)]
 fn tick() {
+    // the tick is never within ProcessEvent
+    crate::native::process_event::update_process_event_hook();
     crate::threads::ue::tick();
 }
 
@@ -9,7 +9,7 @@ use crate::native::ue::{FName, FVector, FRotator};
 use crate::native::{APAWN_SPAWNDEFAULTCONTROLLER, AACTOR_SETACTORENABLECOLLISION, GWORLD, UWORLD_SPAWNACTOR, UWORLD_DESTROYACTOR, AMyCharacter, UGAMEPLAYSTATICS_GETACCURATEREALTIME};
 use crate::native::character::AMyCharacterUE;
 use crate::native::gameinstance::UMyGameInstance;
-use crate::native::reflection::{AActor, UClass, UObject};
+use crate::native::reflection::{AActor, ActorWrapper, UClass, UObject};
 
 pub enum APawn {}
 pub enum UGameplayStatics {}
@@ -149,6 +149,37 @@ impl UWorld {
             my_character
         }
     }
+    /// Spawn a copy of the given actor, e.g. to add elements to a map.
+    pub fn spawn_from_template(template: &ActorWrapper, x: f32, y: f32, z: f32, pitch: f32, yaw: f32, roll: f32) -> *mut AActor {
+        unsafe {
+            let location = FVector { x, y, z };
+            let rotation = FRotator { pitch, yaw, roll };
+            let spawn_parameters = FActorSpawnParameters {
+                name: FName::NAME_None,
+                template: template.as_ptr(),
+                owner: ptr::null(),
+                instigator: ptr::null(),
+                override_level: ptr::null(),
+                spawn_collision_handling_override: ESpawnActorCollisionHandlingMethod::AlwaysSpawn,
+                bitfield: FActorSpawnParameters::B_NO_FAIL,
+                name_node: ESpawnActorNameMode::RequiredFatal,
+                object_flags: 0x00000000,
+            };
+            let ptr = Self::spawn_actor(
+                template.class().as_ptr(), &location, &rotation, &spawn_parameters,
+            );
+            assert!(!ptr.is_null(), "UWorld::SpawnActor returned null");
+            ptr
+        }
+    }
+    pub fn destroy_spawned(actor: *mut AActor) {
+        unsafe {
+            let destroyed = Self::destroy_actor(actor, true, true);
+            if !destroyed {
+                log!("spawned actor {:p} not destroyed", actor);
+            }
+        }
+    }
     pub fn destroy_amycharaccter(my_character: AMyCharacter) {
         unsafe {
             let destroyed = Self::destroy_actor(my_character.as_ptr() as *const AActor, true, true);
@@ -6,11 +6,11 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 
 use winapi::ctypes::c_void;
 use winapi::shared::minwindef::FALSE;
-use winapi::um::winnt::{PAGE_READWRITE, PAGE_EXECUTE_READ, HANDLE, THREAD_ALL_ACCESS};
+use winapi::um::winnt::{PAGE_READWRITE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, MEM_COMMIT, MEM_RESERVE, MEM_RELEASE, HANDLE, THREAD_ALL_ACCESS};
 use winapi::um::tlhelp32::{CreateToolhelp32Snapshot, TH32CS_SNAPTHREAD, THREADENTRY32, Thread32First, Thread32Next};
 use winapi::um::handleapi::{INVALID_HANDLE_VALUE, CloseHandle};
 use winapi::um::processthreadsapi::{GetCurrentThreadId, GetCurrentProcessId, OpenThread, SuspendThread, ResumeThread};
-use winapi::um::memoryapi::VirtualProtect;
+use winapi::um::memoryapi::{VirtualProtect, VirtualAlloc, VirtualFree};
 use winapi::um::libloaderapi::GetModuleHandleA;
 
 // https://www.unknowncheats.me/forum/general-programming-and-reversing/123333-demo-pure-rust-internal-coding.html
@@ -180,3 +180,21 @@ pub(in crate::native) fn make_rx(addr: usize) {
     let mut out = 0;
     unsafe { VirtualProtect(page, 0x1000, PAGE_EXECUTE_READ, &mut out); }
 }
+
+/// Writable and executable, for patching code while other code on the same page may run.
+pub(in crate::native) fn make_rwx(addr: usize) {
+    let page = addr & !0xfff;
+    let page = page as *mut std::ffi::c_void;
+    let mut out = 0;
+    unsafe { VirtualProtect(page, 0x1000, PAGE_EXECUTE_READWRITE, &mut out); }
+}
+
+/// Allocate a read-write page, which can be reached from everywhere with a rel32 on 32bit.
+pub(in crate::native) fn alloc_page_near(_near: usize) -> Option<usize> {
+    let addr = unsafe { VirtualAlloc(ptr::null_mut(), 0x1000, MEM_COMMIT | MEM_RESERVE, PAGE_READWRITE) };
+    (!addr.is_null()).then(|| addr as usize)
+}
+
+pub(in crate::native) fn free_page(addr: usize) {
+    unsafe { VirtualFree(addr as *mut c_void, 0, MEM_RELEASE); }
+}
@@ -1,5 +1,6 @@
+use std::ffi::c_void;
 use crossbeam_channel::{Receiver, Sender};
-use crate::native::{ElementIndex, try_find_element_index, UObject};
+use crate::native::{ElementIndex, try_find_element_index, UFunction, UObject};
 use crate::threads::{ReboToStream, StreamToRebo};
 
 mod rebo;
@@ -18,6 +19,7 @@ enum UeEvent {
     DrawHud,
     ApplyResolutionSettings,
     AddToScreen,
+    FunctionCalled(rebo::FunctionCall),
 }
 #[derive(Debug, Clone, Copy)]
 enum Suspend {
@@ -82,6 +84,11 @@ pub fn add_to_screen() {
     handle(UeEvent::AddToScreen);
 }
 
+pub fn function_called(class_names: Vec<String>, object: *mut UObject, function: *mut UFunction, params: *mut c_void) {
+    // the event may be queued, read the arguments while they are still alive
+    rebo::function_called(rebo::FunctionCall::new(class_names, object, function, params));
+}
+
 fn handle(event: UeEvent) {
     rebo::poll(event);
 }
@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Default time remote players are rendered in the past, should be a few server-updates long.
+pub const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+/// Maximum time a remote player is extrapolated when updates are late, after which it stands still.
+const MAX_EXTRAPOLATION: Duration = Duration::from_millis(250);
+/// Samples older than this (relative to the newest sample) are dropped.
+const BUFFER_DURATION: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    /// local time the update was captured by the player, see `RemotePlayer::local_time`
+    time: Instant,
+    location: (f32, f32, f32),
+    rotation: (f32, f32, f32),
+    /// velocity reported by the player, `None` for old clients
+    velocity: Option<(f32, f32, f32)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub location: (f32, f32, f32),
+    pub rotation: (f32, f32, f32),
+    pub velocity: (f32, f32, f32),
+}
+
+/// Timestamped buffer of the position updates of a remote player.
+///
+/// Remote players are rendered `delay` in the past, such that there usually are two updates
+/// to interpolate between.
+/// If updates are late, the player is extrapolated from its last velocity.
+/// Old clients don't report their velocity, in which case it's derived from the last two updates.
+#[derive(Debug, Default)]
+pub struct RemotePlayer {
+    samples: VecDeque<Sample>,
+    /// receive-time and client timestamp of the update with the lowest delay so far, to convert
+    /// client timestamps to local time
+    clock: Option<(Instant, u64)>,
+}
+
+impl RemotePlayer {
+    /// Add an update received at `received`, `timestamp` is the client's unix time in ms when it
+    /// captured the update, or `0` if unknown.
+    pub fn push(&mut self, received: Instant, timestamp: u64, location: (f32, f32, f32), rotation: (f32, f32, f32), velocity: Option<(f32, f32, f32)>) {
+        let time = self.local_time(received, timestamp);
+        // updates can't arrive out of order over a websocket, but be defensive about the clock
+        if self.samples.back().map_or(false, |last| last.time > time) {
+            self.samples.clear();
+        }
+        self.samples.push_back(Sample { time, location, rotation, velocity });
+        while let Some(first) = self.samples.front() {
+            if time.duration_since(first.time) <= BUFFER_DURATION {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    /// Local time the update was captured by the client.
+    ///
+    /// The client timestamps keep the time between updates without the jitter of the network.
+    /// They are converted to local time by assuming that the fastest update had no delay.
+    fn local_time(&mut self, received: Instant, timestamp: u64) -> Instant {
+        let (anchor, anchor_timestamp) = match self.clock {
+            _ if timestamp == 0 => {
+                self.clock = None;
+                return received;
+            }
+            Some((anchor, anchor_timestamp)) if timestamp >= anchor_timestamp => (anchor, anchor_timestamp),
+            // first update with a timestamp, or the client's clock jumped back
+            _ => {
+                self.clock = Some((received, timestamp));
+                return received;
+            }
+        };
+        let time = anchor + Duration::from_millis(timestamp - anchor_timestamp);
+        if time <= received {
+            return time;
+        }
+        // this update was faster than all before, move the earlier ones by the same amount
+        let earlier = time - received;
+        for sample in &mut self.samples {
+            sample.time = sample.time.checked_sub(earlier).unwrap_or(sample.time);
+        }
+        self.clock = Some((received, timestamp));
+        received
+    }
+
+    /// Transform of the player at `now - delay`.
+    pub fn sample(&self, now: Instant, delay: Duration) -> Option<Transform> {
+        let time = now.checked_sub(delay).unwrap_or(now);
+        let last = *self.samples.back()?;
+        // first sample after `time`
+        let next_index = self.samples.iter().position(|sample| sample.time > time);
+        match next_index {
+            // before the first sample
+            Some(0) => Some(Transform {
+                location: self.samples[0].location,
+                rotation: self.samples[0].rotation,
+                velocity: (0., 0., 0.),
+            }),
+            // interpolate between the two surrounding samples
+            Some(index) => {
+                let prev = self.samples[index - 1];
+                let next = self.samples[index];
+                let span = next.time.duration_since(prev.time).as_secs_f32();
+                let t = time.duration_since(prev.time).as_secs_f32() / span;
+                let velocity = match (prev.velocity, next.velocity) {
+                    (Some(prev_velocity), Some(next_velocity)) => lerp3(prev_velocity, next_velocity, t),
+                    _ => velocity(prev, next),
+                };
+                Some(Transform {
+                    location: lerp3(prev.location, next.location, t),
+                    rotation: lerp_rotation(prev.rotation, next.rotation, t),
+                    velocity,
+                })
+            }
+            // after the last sample: extrapolate
+            None => {
+                let velocity = match (last.velocity, self.samples.len()) {
+                    (Some(velocity), _) => velocity,
+                    (None, 0 | 1) => (0., 0., 0.),
+                    (None, len) => velocity(self.samples[len - 2], last),
+                };
+                let late = time.duration_since(last.time);
+                let location = add_scaled(last.location, velocity, late.min(MAX_EXTRAPOLATION).as_secs_f32());
+                // stand still once we stopped extrapolating
+                let velocity = if late > MAX_EXTRAPOLATION { (0., 0., 0.) } else { velocity };
+                Some(Transform { location, rotation: last.rotation, velocity })
+            }
+        }
+    }
+}
+
+fn velocity(prev: Sample, next: Sample) -> (f32, f32, f32) {
+    let dt = next.time.duration_since(prev.time).as_secs_f32();
+    if dt <= 0. {
+        return (0., 0., 0.);
+    }
+    let (x1, y1, z1) = prev.location;
+    let (x2, y2, z2) = next.location;
+    ((x2 - x1) / dt, (y2 - y1) / dt, (z2 - z1) / dt)
+}
+
+fn add_scaled((x, y, z): (f32, f32, f32), (dx, dy, dz): (f32, f32, f32), factor: f32) -> (f32, f32, f32) {
+    (x + dx * factor, y + dy * factor, z + dz * factor)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+fn lerp3((x1, y1, z1): (f32, f32, f32), (x2, y2, z2): (f32, f32, f32), t: f32) -> (f32, f32, f32) {
+    (lerp(x1, x2, t), lerp(y1, y2, t), lerp(z1, z2, t))
+}
+
+/// Interpolate an angle in degrees along the shorter direction.
+fn lerp_angle(a: f32, b: f32, t: f32) -> f32 {
+    let diff = (b - a + 180.).rem_euclid(360.) - 180.;
+    a + diff * t
+}
+
+fn lerp_rotation((p1, y1, r1): (f32, f32, f32), (p2, y2, r2): (f32, f32, f32), t: f32) -> (f32, f32, f32) {
+    (lerp_angle(p1, p2, t), lerp_angle(y1, y2, t), lerp_angle(r1, r2, t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn assert_close((x1, y1, z1): (f32, f32, f32), (x2, y2, z2): (f32, f32, f32)) {
+        assert!((x1 - x2).abs() < 1e-2 && (y1 - y2).abs() < 1e-2 && (z1 - z2).abs() < 1e-2, "{:?} != {:?}", (x1, y1, z1), (x2, y2, z2));
+    }
+
+    #[test]
+    fn no_samples() {
+        assert_eq!(RemotePlayer::default().sample(Instant::now(), DEFAULT_DELAY), None);
+    }
+
+    #[test]
+    fn before_the_first_sample() {
+        let start = Instant::now();
+        let mut player = RemotePlayer::default();
+        player.push(start, 0, (10., 0., 0.), (0., 90., 0.), Some((100., 0., 0.)));
+        let transform = player.sample(start + 50 * MS, 100 * MS).unwrap();
+        assert_eq!(transform, Transform { location: (10., 0., 0.), rotation: (0., 90., 0.), velocity: (0., 0., 0.) });
+    }
+
+    #[test]
+    fn interpolation() {
+        let start = Instant::now();
+        let mut player = RemotePlayer::default();
+        player.push(start, 0, (0., 0., 0.), (0., 170., 0.), Some((100., 0., 0.)));
+        player.push(start + 100 * MS, 0, (10., 20., 30.), (0., -170., 0.), Some((300., 0., 0.)));
+        let transform = player.sample(start + 125 * MS, 100 * MS).unwrap();
+        assert_close(transform.location, (2.5, 5., 7.5));
+        assert_close(transform.velocity, (150., 0., 0.));
+        // along the shorter direction across 180°
+        assert_close(transform.rotation, (0., 175., 0.));
+    }
+
+    #[test]
+    fn velocity_of_old_clients_is_derived() {
+        let start = Instant::now();
+        let mut player = RemotePlayer::default();
+        player.push(start, 0, (0., 0., 0.), (0., 0., 0.), None);
+        player.push(start + 100 * MS, 0, (10., 0., 0.), (0., 0., 0.), None);
+        let transform = player.sample(start + 150 * MS, 100 * MS).unwrap();
+        assert_close(transform.location, (5., 0., 0.));
+        assert_close(transform.velocity, (100., 0., 0.));
+        // and used to extrapolate
+        let transform = player.sample(start + 300 * MS, 100 * MS).unwrap();
+        assert_close(transform.location, (20., 0., 0.));
+    }
+
+    #[test]
+    fn extrapolation_is_clamped() {
+        let start = Instant::now();
+        let mut player = RemotePlayer::default();
+        player.push(start, 0, (0., 0., 0.), (0., 0., 0.), Some((1000., 0., -500.)));
+        let transform = player.sample(start + 200 * MS, 100 * MS).unwrap();
+        assert_close(transform.location, (100., 0., -50.));
+        assert_close(transform.velocity, (1000., 0., -500.));
+        // stands still after `MAX_EXTRAPOLATION`
+        let transform = player.sample(start + 100 * MS + MAX_EXTRAPOLATION * 2, 100 * MS).unwrap();
+        let max = MAX_EXTRAPOLATION.as_secs_f32();
+        assert_close(transform.location, (1000. * max, 0., -500. * max));
+        assert_close(transform.velocity, (0., 0., 0.));
+    }
+
+    #[test]
+    fn client_timestamps_remove_network_jitter() {
+        let start = Instant::now();
+        let mut player = RemotePlayer::default();
+        // sent every 100ms, but the second one is delayed by 40ms
+        player.push(start, 1000, (0., 0., 0.), (0., 0., 0.), None);
+        player.push(start + 140 * MS, 1100, (10., 0., 0.), (0., 0., 0.), None);
+        player.push(start + 200 * MS, 1200, (20., 0., 0.), (0., 0., 0.), None);
+        assert_close(player.sample(start + 150 * MS, 100 * MS).unwrap().location, (5., 0., 0.));
+        assert_close(player.sample(start + 250 * MS, 100 * MS).unwrap().location, (15., 0., 0.));
+    }
+
+    #[test]
+    fn faster_updates_move_earlier_ones() {
+        let start = Instant::now();
+        let mut player = RemotePlayer::default();
+        // the first update was delayed by 50ms more than the second one
+        player.push(start, 1000, (0., 0., 0.), (0., 0., 0.), None);
+        player.push(start + 50 * MS, 1100, (10., 0., 0.), (0., 0., 0.), None);
+        assert_close(player.sample(start + 100 * MS, 100 * MS).unwrap().location, (5., 0., 0.));
+    }
+
+    #[test]
+    fn old_samples_are_dropped() {
+        let start = Instant::now();
+        let mut player = RemotePlayer::default();
+        player.push(start, 0, (0., 0., 0.), (0., 0., 0.), None);
+        player.push(start + BUFFER_DURATION + MS, 0, (10., 0., 0.), (0., 0., 0.), None);
+        assert_eq!(player.samples.len(), 1);
+    }
+}
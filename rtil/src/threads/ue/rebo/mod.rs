@@ -1,7 +1,7 @@
 use std::{ptr, thread};
 use std::collections::{HashSet, HashMap, VecDeque};
 use std::error::Error;
-use std::sync::Mutex;
+use std::sync::{Mutex, TryLockError};
 use std::time::Duration;
 use std::cell::{Cell, RefCell};
 use corosensei::{CoroutineResult, Yielder};
@@ -13,14 +13,22 @@ use websocket::sync::Client;
 use websocket::stream::sync::NetworkStream;
 
 use crate::threads::{StreamToRebo, ReboToStream};
-use crate::native::{AMyCharacter, FPlatformMisc, FSlateApplication, hook_fslateapplication_onkeyup, REBO_DOESNT_START_SEMAPHORE, unhook_fslateapplication_onkeyup, UTexture2D, UWorld};
+use crate::native::{AMyCharacter, FPlatformMisc, FSlateApplication, hook_fslateapplication_onkeyup, REBO_DOESNT_START_SEMAPHORE, unhook_fslateapplication_onkeyup, UTexture2D, UWorld, WeakObjectHandle, unobserve_all_functions};
 use crate::threads::ue::{Suspend, UeEvent};
+use interpolation::RemotePlayer;
+use rebo_init::MapHistory;
 
+mod interpolation;
 mod rebo_init;
 
+pub(super) use rebo_init::FunctionCall;
+
 type Coroutine = corosensei::Coroutine<UeEvent, Suspend, ()>;
 
 static STATE: Lazy<Mutex<Option<State>>> = Lazy::new(|| Mutex::new(None));
+/// Calls of observed functions which happened while `STATE` was locked, e.g. by a rebo function
+/// calling into UE, moved to the event queue with the next event.
+static DEFERRED_EVENTS: Lazy<Mutex<VecDeque<UeEvent>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
 
 thread_local! {
     static YIELDER: Cell<*const Yielder<UeEvent, Suspend>> = Cell::new(ptr::null());
@@ -39,8 +47,15 @@ struct State {
     pressed_keys: HashSet<i32>,
     websocket: Option<Client<Box<dyn NetworkStream + Send>>>,
     local_time_offset: i32,
-    pawns: HashMap<u32, AMyCharacter>,
+    /// protocol version negotiated with the server, see `protocol::PROTOCOL_VERSION`
+    server_protocol_version: u32,
+    pawns: HashMap<u32, WeakObjectHandle>,
     pawn_id: u32,
+    /// position updates of other multiplayer players by their `PlayerId`
+    remote_players: HashMap<u32, RemotePlayer>,
+    interpolation_delay: Duration,
+    /// undo/redo history of the map currently edited in the map editor
+    map_history: Option<MapHistory>,
     minimap_texture: Option<UTexture2D>,
     minimap_image: RgbaImage,
     player_minimap_image: RgbaImage,
@@ -85,13 +100,13 @@ pub(super) fn poll(event: UeEvent) {
             };
             if let Some(co) = co.as_mut() {
                 STATE.lock().unwrap().as_mut().unwrap().event_queue.push_back(event.clone());
-                while let Some(evt) = { let foo = STATE.lock().unwrap().as_mut().unwrap().event_queue.pop_front(); foo } {
+                while let Some(evt) = next_event() {
                     match co.resume(evt) {
                         CoroutineResult::Yield(Suspend::Return) => (),
                         CoroutineResult::Yield(Suspend::Yield) => {
                             // don't return to UE, let input events be handled
                             FPlatformMisc::pump_messages();
-                            if STATE.lock().unwrap().as_ref().unwrap().event_queue.is_empty() {
+                            if STATE.lock().unwrap().as_ref().unwrap().event_queue.is_empty() && DEFERRED_EVENTS.lock().unwrap().is_empty() {
                                 thread::sleep(Duration::from_millis(5));
                                 STATE.lock().unwrap().as_mut().unwrap().event_queue.push_back(UeEvent::NothingHappened);
                             }
@@ -123,6 +138,30 @@ pub(super) fn poll(event: UeEvent) {
     }
 }
 
+/// Handle the call of an observed function, which can happen whenever UE is called.
+///
+/// If a rebo function called into UE while holding `STATE`, the call is deferred instead of
+/// polling, which would deadlock.
+pub(super) fn function_called(call: FunctionCall) {
+    let event = UeEvent::FunctionCalled(call);
+    match STATE.try_lock() {
+        Ok(state) => {
+            drop(state);
+            poll(event);
+        },
+        Err(TryLockError::WouldBlock) => DEFERRED_EVENTS.lock().unwrap().push_back(event),
+        Err(TryLockError::Poisoned(e)) => panic!("{e}"),
+    }
+}
+
+/// Next event to resume the coroutine with, after moving deferred events to the event queue.
+fn next_event() -> Option<UeEvent> {
+    let mut state = STATE.lock().unwrap();
+    let state = state.as_mut().unwrap();
+    state.event_queue.extend(DEFERRED_EVENTS.lock().unwrap().drain(..));
+    state.event_queue.pop_front()
+}
+
 pub fn init(stream_rebo_rx: Receiver<StreamToRebo>, rebo_stream_tx: Sender<ReboToStream>) {
     log!("init rebo state");
     log!("checking for a new refunct-tas release");
@@ -149,8 +188,12 @@ pub fn init(stream_rebo_rx: Receiver<StreamToRebo>, rebo_stream_tx: Sender<ReboT
         pressed_keys: HashSet::new(),
         websocket: None,
         local_time_offset: 0,
+        server_protocol_version: 1,
         pawns: HashMap::new(),
         pawn_id: 0,
+        remote_players: HashMap::new(),
+        interpolation_delay: interpolation::DEFAULT_DELAY,
+        map_history: None,
         minimap_texture: None,
         minimap_image,
         player_minimap_image,
@@ -186,26 +229,42 @@ fn poll_tool() -> Option<Coroutine> {
 
 fn cleanup_after_rebo() {
     log!("Starting rebo cleanup...");
-    // reset STATE
-    let mut state = STATE.lock().unwrap();
-    let state = state.as_mut().unwrap();
-    YIELDER.with(|yielder| yielder.set(ptr::null()));
-    COROUTINE.with(|co| *co.borrow_mut() = None);
-    state.event_queue.clear();
-    state.delta = None;
-    drop(state.websocket.take());
-    for (_id, my_character) in state.pawns.drain() {
+    // don't report calls made while cleaning up, the ProcessEvent-hook is removed with the next tick
+    unobserve_all_functions();
+    DEFERRED_EVENTS.lock().unwrap().clear();
+    // reset STATE, but don't hold the lock while calling into UE
+    let (pawns, pressed_keys) = {
+        let mut state = STATE.lock().unwrap();
+        let state = state.as_mut().unwrap();
+        YIELDER.with(|yielder| yielder.set(ptr::null()));
+        COROUTINE.with(|co| *co.borrow_mut() = None);
+        state.event_queue.clear();
+        state.delta = None;
+        drop(state.websocket.take());
+        state.pawn_id = 0;
+        state.remote_players.clear();
+        state.interpolation_delay = interpolation::DEFAULT_DELAY;
+        if let Some(mut map_history) = state.map_history.take() {
+            if let Err(e) = map_history.save(true) {
+                log!("can't save map: {e}");
+            }
+        }
+        let pawns: Vec<_> = state.pawns.drain().map(|(_id, handle)| handle).collect();
+        let pressed_keys: Vec<_> = state.pressed_keys.drain().collect();
+        (pawns, pressed_keys)
+    };
+    // pawns may already have been destroyed by a level reload
+    for my_character in pawns.into_iter().filter_map(AMyCharacter::resolve) {
         UWorld::destroy_amycharaccter(my_character);
     }
-    state.pawn_id = 0;
     // we don't want to trigger our keyevent handler for emulated presses
     unhook_fslateapplication_onkeyup();
-    for key in state.pressed_keys.drain() {
+    for key in pressed_keys {
         FSlateApplication::release_key(key, key as u32, false);
     }
     hook_fslateapplication_onkeyup();
     rebo_init::apply_map_internal(&rebo_init::ORIGINAL_MAP);
-    state.rebo_stream_tx.send(ReboToStream::MiDone).unwrap();
+    STATE.lock().unwrap().as_ref().unwrap().rebo_stream_tx.send(ReboToStream::MiDone).unwrap();
     log!("Cleanup finished.");
 }
 
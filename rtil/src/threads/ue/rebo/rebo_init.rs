@@ -1,9 +1,10 @@
 use std::collections::HashMap;
+use std::ffi::c_void;
 use std::fs::File;
-use std::io::{ErrorKind, Write};
+use std::io::{self, BufWriter, ErrorKind, Write};
 use std::ops::Deref;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use crossbeam_channel::{Sender, TryRecvError};
 use clipboard::{ClipboardProvider, ClipboardContext};
 use image::Rgba;
@@ -11,10 +12,11 @@ use rebo::{ExecError, ReboConfig, Stdlib, VmContext, Output, Value, DisplayValue
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 use websocket::{ClientBuilder, Message, OwnedMessage, WebSocketError};
-use crate::native::{AMyCharacter, AMyHud, FApp, LevelState, ObjectWrapper, UWorld, UGameplayStatics, UTexture2D, EBlendMode, LEVELS, ActorWrapper, LevelWrapper, KismetSystemLibrary, FSlateApplication, unhook_fslateapplication_onkeydown, hook_fslateapplication_onkeydown, unhook_fslateapplication_onkeyup, hook_fslateapplication_onkeyup, unhook_fslateapplication_onrawmousemove, hook_fslateapplication_onrawmousemove, UMyGameInstance, ue::FVector, character::USceneComponent, UeScope, try_find_element_index, UObject, Level, ObjectIndex, UeObjectWrapperType, AActor};
-use protocol::{Request, Response};
+use crate::native::{AMyCharacter, AMyHud, FApp, LevelState, ObjectWrapper, UWorld, UGameplayStatics, UTexture2D, EBlendMode, LEVELS, ActorWrapper, LevelWrapper, KismetSystemLibrary, FSlateApplication, unhook_fslateapplication_onkeydown, hook_fslateapplication_onkeydown, unhook_fslateapplication_onkeyup, hook_fslateapplication_onkeyup, unhook_fslateapplication_onrawmousemove, hook_fslateapplication_onrawmousemove, UMyGameInstance, ue::FVector, character::USceneComponent, UeScope, try_find_element_index, UObject, Level, ObjectIndex, UeObjectWrapperType, AActor, SPAWNED_ELEMENTS, SpawnedElementActor, RISE_DELAYS, WeakObjectHandle, InspectValue, resolve_path, read_value, write_value, format_value, ClassWrapper, FunctionWrapper, UFunction, read_arguments};
+use protocol::{Movement, Request, Response, PROTOCOL_VERSION};
 use crate::threads::{ReboToStream, StreamToRebo};
-use super::STATE;
+use super::{State, STATE};
+use super::interpolation::{RemotePlayer, Transform};
 use serde::{Serialize, Deserialize};
 use crate::threads::ue::{Suspend, UeEvent, rebo::YIELDER};
 use crate::native::{ElementIndex, ElementType, ue::FRotator};
@@ -77,6 +79,9 @@ pub fn create_config(rebo_stream_tx: Sender<ReboToStream>) -> ReboConfig {
         .add_function(move_pawn)
         .add_function(set_pawn_velocity)
         .add_function(pawn_location)
+        .add_function(set_interpolation_delay)
+        .add_function(get_interpolated_player_location)
+        .add_function(get_interpolated_player_rotation)
         .add_function(connect_to_server)
         .add_function(disconnect_from_server)
         .add_function(join_multiplayer_room)
@@ -84,6 +89,8 @@ pub fn create_config(rebo_stream_tx: Sender<ReboToStream>) -> ReboConfig {
         .add_function(press_platform_on_server)
         .add_function(press_button_on_server)
         .add_function(new_game_pressed)
+        .add_function(list_room_recordings)
+        .add_function(load_room_recording)
         .add_function(get_level)
         .add_function(set_level)
         .add_function(trigger_element)
@@ -102,13 +109,44 @@ pub fn create_config(rebo_stream_tx: Sender<ReboToStream>) -> ReboConfig {
         .add_function(load_map)
         .add_function(save_map)
         .add_function(remove_map)
+        .add_function(export_map)
+        .add_function(import_map)
+        .add_function(diff_maps)
+        .add_function(apply_map_patch)
+        .add_function(save_map_patch)
+        .add_function(map_patch_lines)
+        .add_function(open_map_history)
+        .add_function(close_map_history)
+        .add_function(edit_map)
+        .add_function(map_undo)
+        .add_function(map_redo)
         .add_function(current_map)
         .add_function(original_map)
         .add_function(apply_map)
         .add_function(get_looked_at_element_index)
         .add_function(get_element_bounds)
+        .add_function(get_cluster_element_indices)
+        .add_function(get_element_indices_in_bounds)
+        .add_function(selection_center)
+        .add_function(transform_elements)
+        .add_function(snap_element)
+        .add_function(align_element)
+        .add_function(default_reachability_params)
+        .add_function(unreachable_buttons)
+        .add_function(default_generator_params)
+        .add_function(generate_map)
         .add_function(enable_collision)
         .add_function(disable_collision)
+        .add_function(get_player_object)
+        .add_function(find_objects_by_class)
+        .add_function(find_objects_by_name)
+        .add_function(list_properties)
+        .add_function(get_property)
+        .add_function(set_property)
+        .add_function(call_function)
+        .add_function(dump_reflection)
+        .add_function(observe_function)
+        .add_function(unobserve_function)
         .add_external_type(Location)
         .add_external_type(Rotation)
         .add_external_type(Velocity)
@@ -123,14 +161,30 @@ pub fn create_config(rebo_stream_tx: Sender<ReboToStream>) -> ReboConfig {
         .add_external_type(Server)
         .add_external_type(Step)
         .add_external_type(Disconnected)
+        .add_external_type(RoomRecordingInfo)
+        .add_external_type(GhostRecording)
+        .add_external_type(Ghost)
+        .add_external_type(GhostFrame)
         .add_external_type(RecordFrame)
         .add_external_type(InputEvent)
         .add_external_type(RefunctMap)
         .add_external_type(Cluster)
+        .add_external_type(SpawnedElement)
+        .add_external_type(SnapSettings)
+        .add_external_type(Alignment)
         .add_external_type(Element)
         .add_external_type(ElementType)
         .add_external_type(ElementIndex)
         .add_external_type(Bounds)
+        .add_external_type(GroupTransform)
+        .add_external_type(ReachabilityParams)
+        .add_external_type(GeneratorParams)
+        .add_external_type(MapPatch)
+        .add_external_type(ClusterPatch)
+        .add_external_type(ElementPatch)
+        .add_external_type(UeObject)
+        .add_external_type(UeProperty)
+        .add_external_type(UePropertyValue)
         .add_required_rebo_function(element_pressed)
         .add_required_rebo_function(element_released)
         .add_required_rebo_function(on_key_down)
@@ -148,6 +202,7 @@ pub fn create_config(rebo_stream_tx: Sender<ReboToStream>) -> ReboConfig {
         .add_required_rebo_function(on_level_state_change)
         .add_required_rebo_function(on_resolution_change)
         .add_required_rebo_function(on_menu_open)
+        .add_required_rebo_function(on_function_call)
     ;
     if let Some(working_dir) = &STATE.lock().unwrap().as_ref().unwrap().working_dir {
         cfg = cfg.include_directory(IncludeDirectoryConfig::Path(PathBuf::from(working_dir)));
@@ -245,6 +300,19 @@ fn step_internal<'a, 'i>(vm: &mut VmContext<'a, '_, '_, 'i>, suspend: Suspend) -
             UeEvent::DrawHud => draw_hud(vm)?,
             UeEvent::ApplyResolutionSettings => on_resolution_change(vm)?,
             UeEvent::AddToScreen => on_menu_open(vm)?,
+            UeEvent::FunctionCalled(FunctionCall { class_names, function, object, args }) => {
+                for class_name in class_names {
+                    let args = Map::new(args.iter().cloned().collect());
+                    on_function_call(vm, class_name, function.clone(), object.clone(), args)?;
+                }
+            },
+        }
+
+        // save map edits deferred by `MapHistory::save`
+        if let Some(map_history) = STATE.lock().unwrap().as_mut().unwrap().map_history.as_mut() {
+            if let Err(e) = map_history.save(false) {
+                log!("can't save map: {e}");
+            }
         }
 
         // check websocket
@@ -256,9 +324,35 @@ fn step_internal<'a, 'i>(vm: &mut VmContext<'a, '_, '_, 'i>, suspend: Suspend) -
             };
             match response {
                 Response::ServerTime(_) => unreachable!("got Response::ServerTime in step-function"),
-                Response::PlayerJoinedRoom(id, name, red, green, blue, x, y, z, pitch, yaw, roll) => player_joined_multiplayer_room(vm, id.id(), name, Color { red, green, blue, alpha: 1. }, Location { x, y, z}, Rotation { pitch, yaw, roll })?,
-                Response::PlayerLeftRoom(id) => player_left_multiplayer_room(vm, id.id())?,
-                Response::MoveOther(id, x, y, z, pitch, yaw, roll) => player_moved(vm, id.id(), Location { x, y, z }, Rotation { pitch, yaw, roll })?,
+                Response::ProtocolVersion(_) => unreachable!("got Response::ProtocolVersion in step-function"),
+                Response::PlayerJoinedRoom(id, name, red, green, blue, x, y, z, pitch, yaw, roll) => {
+                    let mut remote_player = RemotePlayer::default();
+                    remote_player.push(Instant::now(), 0, (x, y, z), (pitch, yaw, roll), None);
+                    STATE.lock().unwrap().as_mut().unwrap().remote_players.insert(id.id(), remote_player);
+                    player_joined_multiplayer_room(vm, id.id(), name, Color { red, green, blue, alpha: 1. }, Location { x, y, z}, Rotation { pitch, yaw, roll })?
+                },
+                Response::PlayerLeftRoom(id) => {
+                    let mut state = STATE.lock().unwrap();
+                    let state = state.as_mut().unwrap();
+                    state.remote_players.remove(&id.id());
+                    drop(state);
+                    player_left_multiplayer_room(vm, id.id())?
+                },
+                Response::MoveOther(id, x, y, z, pitch, yaw, roll) => {
+                    if let Some(remote_player) = STATE.lock().unwrap().as_mut().unwrap().remote_players.get_mut(&id.id()) {
+                        remote_player.push(Instant::now(), 0, (x, y, z), (pitch, yaw, roll), None);
+                    }
+                    player_moved(vm, id.id(), Location { x, y, z }, Rotation { pitch, yaw, roll })?
+                },
+                Response::MoveOtherV2(id, movement) => {
+                    let Movement { x, y, z, pitch, yaw, roll, vel_x, vel_y, vel_z, movement_mode, timestamp, .. } = movement;
+                    // moves of old clients are forwarded without velocity
+                    let velocity = (movement_mode != Movement::MOVEMENT_MODE_NONE).then(|| (vel_x, vel_y, vel_z));
+                    if let Some(remote_player) = STATE.lock().unwrap().as_mut().unwrap().remote_players.get_mut(&id.id()) {
+                        remote_player.push(Instant::now(), timestamp, (x, y, z), (pitch, yaw, roll), velocity);
+                    }
+                    player_moved(vm, id.id(), Location { x, y, z }, Rotation { pitch, yaw, roll })?
+                },
                 Response::PressPlatform(id) => press_platform(vm, id)?,
                 Response::PressButton(id) => press_button(vm, id)?,
                 Response::NewGamePressed(id) => player_pressed_new_game(vm, id.id())?,
@@ -269,6 +363,8 @@ fn step_internal<'a, 'i>(vm: &mut VmContext<'a, '_, '_, 'i>, suspend: Suspend) -
                 Response::RoomNameTooLong => {
                     disconnected(vm, Disconnected::RoomNameTooLong)?;
                 }
+                Response::RateLimitExceeded => log!("server dropped a request because we exceeded the rate limit"),
+                Response::InvalidRequest => log!("server dropped a request because it was invalid"),
             }
         }
 
@@ -276,6 +372,9 @@ fn step_internal<'a, 'i>(vm: &mut VmContext<'a, '_, '_, 'i>, suspend: Suspend) -
             Some(ret) => {
                 // call level-state event function
                 let new_level_state = LevelState::get();
+                let delta = if matches!(ret, Step::Tick) { FApp::delta() as f32 } else { 0. };
+                UeScope::with(|scope| RISE_DELAYS.lock().unwrap()
+                    .update(scope, &LEVELS.lock().unwrap(), old_level_state.level, new_level_state.level, delta));
                 if old_level_state != new_level_state {
                     on_level_state_change(vm, old_level_state.clone(), new_level_state)?;
                 }
@@ -305,6 +404,7 @@ extern "rebo" {
     fn on_level_state_change(old: LevelState, new: LevelState);
     fn on_resolution_change();
     fn on_menu_open();
+    fn on_function_call(class_name: String, function: String, object: UeObject, args: Map<String, UePropertyValue>);
 }
 
 fn config_path() -> PathBuf {
@@ -670,35 +770,77 @@ fn spawn_pawn(loc: Location, rot: Rotation) -> u32 {
     let my_character = UWorld::spawn_amycharacter(loc.x, loc.y, loc.z, rot.pitch, rot.yaw, rot.roll);
     let id = STATE.lock().unwrap().as_mut().unwrap().pawn_id;
     STATE.lock().unwrap().as_mut().unwrap().pawn_id += 1;
-    STATE.lock().unwrap().as_mut().unwrap().pawns.insert(id, my_character);
+    STATE.lock().unwrap().as_mut().unwrap().pawns.insert(id, my_character.handle());
     id
 }
+/// Pawn of the id, or `None` if it was destroyed, e.g. by a level reload.
+///
+/// Destroyed pawns are forgotten, such that following calls with their id are no-ops.
+fn resolve_pawn(state: &mut State, pawn_id: u32) -> Option<AMyCharacter> {
+    assert!(pawn_id < state.pawn_id, "pawn_id not valid");
+    let handle = *state.pawns.get(&pawn_id)?;
+    let my_character = AMyCharacter::resolve(handle);
+    if my_character.is_none() {
+        log!("pawn {pawn_id} was destroyed, forgetting it");
+        state.pawns.remove(&pawn_id);
+    }
+    my_character
+}
 #[rebo::function("Tas::destroy_pawn")]
 fn destroy_pawn(pawn_id: u32) {
-    let my_character = STATE.lock().unwrap().as_mut().unwrap().pawns.remove(&pawn_id).expect("pawn_id not valid anymore");
-    UWorld::destroy_amycharaccter(my_character);
+    // don't hold the lock while calling into UE, which may call observed functions
+    let my_character = {
+        let mut state = STATE.lock().unwrap();
+        let state = state.as_mut().unwrap();
+        let my_character = resolve_pawn(state, pawn_id);
+        state.pawns.remove(&pawn_id);
+        my_character
+    };
+    if let Some(my_character) = my_character {
+        UWorld::destroy_amycharaccter(my_character);
+    }
 }
 #[rebo::function("Tas::move_pawn")]
 fn move_pawn(pawn_id: u32, loc: Location) {
     let mut state = STATE.lock().unwrap();
     let state = state.as_mut().unwrap();
-    let my_character = state.pawns.get_mut(&pawn_id).expect("pawn_id not valid");
-    my_character.set_location(loc.x, loc.y, loc.z);
+    if let Some(mut my_character) = resolve_pawn(state, pawn_id) {
+        my_character.set_location(loc.x, loc.y, loc.z);
+    }
 }
 #[rebo::function("Tas::set_pawn_velocity")]
 fn set_pawn_velocity(pawn_id: u32, vel: Velocity) {
     let mut state = STATE.lock().unwrap();
     let state = state.as_mut().unwrap();
-    let my_character = state.pawns.get_mut(&pawn_id).expect("pawn_id not valid");
-    my_character.set_velocity(vel.x, vel.y, vel.z);
+    if let Some(mut my_character) = resolve_pawn(state, pawn_id) {
+        my_character.set_velocity(vel.x, vel.y, vel.z);
+    }
+}
+#[rebo::function("Tas::set_interpolation_delay")]
+fn set_interpolation_delay(millis: u32) {
+    STATE.lock().unwrap().as_mut().unwrap().interpolation_delay = Duration::from_millis(millis as u64);
+}
+fn interpolated_player_transform(player_id: u32) -> Option<Transform> {
+    let state = STATE.lock().unwrap();
+    let state = state.as_ref().unwrap();
+    state.remote_players.get(&player_id)?.sample(Instant::now(), state.interpolation_delay)
+}
+#[rebo::function("Tas::get_interpolated_player_location")]
+fn get_interpolated_player_location(player_id: u32) -> Option<Location> {
+    let (x, y, z) = interpolated_player_transform(player_id)?.location;
+    Some(Location { x, y, z })
+}
+#[rebo::function("Tas::get_interpolated_player_rotation")]
+fn get_interpolated_player_rotation(player_id: u32) -> Option<Rotation> {
+    let (pitch, yaw, roll) = interpolated_player_transform(player_id)?.rotation;
+    Some(Rotation { pitch, yaw, roll })
 }
 #[rebo::function("Tas::pawn_location")]
-fn pawn_location(pawn_id: u32) -> Location {
+fn pawn_location(pawn_id: u32) -> Option<Location> {
     let mut state = STATE.lock().unwrap();
     let state = state.as_mut().unwrap();
-    let my_character = state.pawns.get_mut(&pawn_id).expect("pawn_id not valid");
-    let (x, y, z) = my_character.location();
-    Location { x, y, z }
+    let (x, y, z) = resolve_pawn(state, pawn_id)?.location();
+    Some(Location { x, y, z })
 }
 #[derive(rebo::ExternalType)]
 enum Server {
@@ -714,7 +856,8 @@ fn connect_to_server(server: Server) {
         Server::Remote => "wss://refunct-tas.oberien.de/ws",
         Server::Testing => "wss://refunct-tas-test.oberien.de/ws",
     };
-    let client = ClientBuilder::new(address).unwrap().connect(None);
+    let address = format!("{address}?version={PROTOCOL_VERSION}");
+    let client = ClientBuilder::new(&address).unwrap().connect(None);
     let client = match client {
         Ok(client) => client,
         Err(e) => {
@@ -724,7 +867,13 @@ fn connect_to_server(server: Server) {
         }
     };
     log!("connected to server, figuring out time delta");
-    STATE.lock().unwrap().as_mut().unwrap().websocket = Some(client);
+    {
+        let mut state = STATE.lock().unwrap();
+        let state = state.as_mut().unwrap();
+        state.websocket = Some(client);
+        // old servers don't tell us their version
+        state.server_protocol_version = 1;
+    }
 
     // time delta calculation
     let mut deltas: Vec<i32> = vec![0];
@@ -743,11 +892,18 @@ fn connect_to_server(server: Server) {
 
         let before = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
         send_to_server(vm, "timesync", Request::GetServerTime)?;
-        let remote_time = match receive_from_server(vm, false) {
-            Err(ReceiveError::Error) => return Ok(Value::Unit),
-            Err(ReceiveError::ExecError(err)) => return Err(err),
-            Ok(Response::ServerTime(time)) => time,
-            Ok(response) => unreachable!("got non-ServerTime during deltatime calculation: {response:?}"),
+        let remote_time = loop {
+            match receive_from_server(vm, false) {
+                Err(ReceiveError::Error) => return Ok(Value::Unit),
+                Err(ReceiveError::ExecError(err)) => return Err(err),
+                Ok(Response::ServerTime(time)) => break time,
+                // sent by new servers before anything else
+                Ok(Response::ProtocolVersion(version)) => {
+                    log!("server speaks protocol version {version}");
+                    STATE.lock().unwrap().as_mut().unwrap().server_protocol_version = version;
+                },
+                Ok(response) => unreachable!("got non-ServerTime during deltatime calculation: {response:?}"),
+            }
         };
         let after = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
         let local_time = ((before + after) / 2) as u64;
@@ -764,6 +920,7 @@ fn connect_to_server(server: Server) {
 #[rebo::function(raw("Tas::disconnect_from_server"))]
 fn disconnect_from_server() {
     STATE.lock().unwrap().as_mut().unwrap().websocket.take();
+    STATE.lock().unwrap().as_mut().unwrap().remote_players.clear();
     disconnected(vm, Disconnected::ManualDisconnect)?;
 }
 fn send_to_server<'a, 'i>(vm: &mut VmContext<'a, '_, '_, 'i>, desc: &str, request: Request) -> Result<(), ExecError<'a, 'i>> {
@@ -827,7 +984,24 @@ fn join_multiplayer_room(room: String, name: String, col: Color, loc: Location,
 }
 #[rebo::function(raw("Tas::move_on_server"))]
 fn move_on_server(loc: Location, rot: Rotation) {
-    send_to_server(vm, "move", Request::MoveSelf(loc.x, loc.y, loc.z, rot.pitch, rot.yaw, rot.roll))?;
+    let player = AMyCharacter::get_player();
+    let (vel_x, vel_y, vel_z) = player.velocity();
+    let (acc_x, acc_y, acc_z) = player.acceleration();
+    let movement = Movement {
+        x: loc.x, y: loc.y, z: loc.z,
+        pitch: rot.pitch, yaw: rot.yaw, roll: rot.roll,
+        vel_x, vel_y, vel_z,
+        acc_x, acc_y, acc_z,
+        movement_mode: player.movement_mode(),
+        timestamp: std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64,
+    };
+    let request = if STATE.lock().unwrap().as_ref().unwrap().server_protocol_version >= 2 {
+        Request::MoveSelfV2(movement)
+    } else {
+        // old servers disconnect us for requests they don't know
+        Request::MoveSelf(loc.x, loc.y, loc.z, rot.pitch, rot.yaw, rot.roll)
+    };
+    send_to_server(vm, "move", request)?;
 }
 #[rebo::function(raw("Tas::press_platform_on_server"))]
 fn press_platform_on_server(platform_id: u8) {
@@ -841,6 +1015,159 @@ fn press_button_on_server(button_id: u8) {
 fn new_game_pressed() {
     send_to_server(vm, "new game pressed", Request::NewGamePressed)?;
 }
+fn server_http_address(server: Server) -> &'static str {
+    match server {
+        Server::Localhost => "http://localhost:8080",
+        Server::Remote => "https://refunct-tas.oberien.de",
+        Server::Testing => "https://refunct-tas-test.oberien.de",
+    }
+}
+/// Fetch on another thread, stepping frames while waiting such that the game doesn't freeze.
+fn fetch_from_server<'a, 'i, T: serde::de::DeserializeOwned + Send + 'static>(vm: &mut VmContext<'a, '_, '_, 'i>, server: Server, path: &str) -> Result<Option<T>, ExecError<'a, 'i>> {
+    let url = format!("{}{path}", server_http_address(server));
+    let (tx, rx) = crossbeam_channel::bounded(1);
+    std::thread::spawn(move || {
+        let _ = tx.send(fetch_blocking(&url));
+    });
+    loop {
+        match rx.try_recv() {
+            Ok(res) => return Ok(res),
+            Err(TryRecvError::Empty) => { step_internal(vm, Suspend::Return)?; },
+            Err(TryRecvError::Disconnected) => return Ok(None),
+        }
+    }
+}
+fn fetch_blocking<T: serde::de::DeserializeOwned>(url: &str) -> Option<T> {
+    let res = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .get(url)
+        .call();
+    match res {
+        // recordings can be larger than the 10MB limit of `into_string`
+        Ok(response) => match serde_json::from_reader(response.into_reader()) {
+            Ok(res) => Some(res),
+            Err(e) => {
+                log!("invalid response from {url}: {e:?}");
+                None
+            }
+        },
+        Err(e) => {
+            log!("error fetching {url}: {e:?}");
+            None
+        }
+    }
+}
+#[derive(rebo::ExternalType)]
+struct RoomRecordingInfo {
+    id: String,
+    room: String,
+    /// unix timestamp of the server of the synchronized new game start
+    start: u64,
+    duration_millis: u64,
+    players: Vec<String>,
+}
+/// A round recorded by the server, converted to ghosts.
+///
+/// All times are in milliseconds relative to the start of the round, with events before the start being negative.
+#[derive(rebo::ExternalType)]
+struct GhostRecording {
+    info: RoomRecordingInfo,
+    ghosts: Vec<Ghost>,
+}
+#[derive(rebo::ExternalType)]
+struct Ghost {
+    id: u32,
+    name: String,
+    col: Color,
+    frames: Vec<GhostFrame>,
+}
+#[derive(rebo::ExternalType)]
+struct GhostFrame {
+    millis: i64,
+    loc: Location,
+    rot: Rotation,
+    /// `false` from the time the player left the room until it rejoins
+    visible: bool,
+}
+impl From<protocol::recording::RecordingInfo> for RoomRecordingInfo {
+    fn from(info: protocol::recording::RecordingInfo) -> Self {
+        RoomRecordingInfo {
+            id: info.id,
+            room: info.room,
+            start: info.start,
+            duration_millis: info.end.saturating_sub(info.start),
+            players: info.players,
+        }
+    }
+}
+impl From<protocol::recording::Recording> for GhostRecording {
+    fn from(recording: protocol::recording::Recording) -> Self {
+        use protocol::recording::{Event, RecordedPlayer};
+        let start = recording.info.start as i64;
+        let mut ghosts: Vec<Ghost> = Vec::new();
+        fn ghost<'a>(ghosts: &'a mut Vec<Ghost>, player: &RecordedPlayer) -> &'a mut Ghost {
+            let index = match ghosts.iter().position(|ghost| ghost.id == player.id.id()) {
+                Some(index) => index,
+                None => {
+                    ghosts.push(Ghost {
+                        id: player.id.id(),
+                        name: player.name.clone(),
+                        col: Color { red: player.red, green: player.green, blue: player.blue, alpha: 1. },
+                        frames: Vec::new(),
+                    });
+                    ghosts.len() - 1
+                }
+            };
+            &mut ghosts[index]
+        }
+        for player in &recording.players {
+            ghost(&mut ghosts, player).frames.push(GhostFrame {
+                millis: 0,
+                loc: Location { x: player.x, y: player.y, z: player.z },
+                rot: Rotation { pitch: player.pitch, yaw: player.yaw, roll: player.roll },
+                visible: true,
+            });
+        }
+        for recorded in recording.events {
+            let millis = recorded.timestamp as i64 - start;
+            match recorded.event {
+                Event::PlayerJoined(player) => ghost(&mut ghosts, &player).frames.push(GhostFrame {
+                    millis,
+                    loc: Location { x: player.x, y: player.y, z: player.z },
+                    rot: Rotation { pitch: player.pitch, yaw: player.yaw, roll: player.roll },
+                    visible: true,
+                }),
+                // hide the ghost at its last location
+                Event::PlayerLeft(id) => match ghosts.iter_mut().find(|ghost| ghost.id == id.id()) {
+                    Some(ghost) => {
+                        let last = ghost.frames.last().unwrap();
+                        let frame = GhostFrame { millis, loc: last.loc, rot: last.rot, visible: false };
+                        ghost.frames.push(frame);
+                    },
+                    None => log!("recording contains leave of unknown player {id:?}"),
+                },
+                Event::Move(id, x, y, z, pitch, yaw, roll) => match ghosts.iter_mut().find(|ghost| ghost.id == id.id()) {
+                    Some(ghost) => ghost.frames.push(GhostFrame { millis, loc: Location { x, y, z }, rot: Rotation { pitch, yaw, roll }, visible: true }),
+                    None => log!("recording contains move of unknown player {id:?}"),
+                },
+                // ghosts only show where the players were
+                Event::PressPlatform(..) | Event::PressButton(..) | Event::NewGamePressed(_) => (),
+            }
+        }
+        GhostRecording { info: recording.info.into(), ghosts }
+    }
+}
+#[rebo::function(raw("Tas::list_room_recordings"))]
+fn list_room_recordings(server: Server) -> Option<Vec<RoomRecordingInfo>> {
+    let list: Option<Vec<protocol::recording::RecordingInfo>> = fetch_from_server(vm, server, "/recordings")?;
+    list.map(|list| list.into_iter().map(RoomRecordingInfo::from).collect::<Vec<_>>())
+}
+#[rebo::function(raw("Tas::load_room_recording"))]
+fn load_room_recording(server: Server, id: String) -> Option<GhostRecording> {
+    let recording: Option<protocol::recording::Recording> = fetch_from_server(vm, server, &format!("/recordings/{id}"))?;
+    recording.map(GhostRecording::from)
+}
 #[rebo::function("Tas::get_level")]
 fn get_level() -> i32 {
     LevelState::get_level()
@@ -937,75 +1264,37 @@ fn set_all_cluster_speeds(speed: f32) {
     })
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct RefunctMapV0 {
-    clusters: Vec<ClusterV0>,
-}
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ClusterV0 {
-    platforms: Vec<ElementV0>,
-    cubes: Vec<ElementV0>,
-    buttons: Vec<ElementV0>,
-}
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ElementV0 {
-    x: f32,
-    y: f32,
-    z: f32,
-    pitch: f32,
-    yaw: f32,
-    roll: f32,
-    xscale: f32,
-    yscale: f32,
-    zscale: f32,
-}
-
-fn migrate_v0_to_v1(map: RefunctMapV0) -> RefunctMap {
-    fn migrate_element(orig: &RefunctMap, e: ElementV0, index: ElementIndex) -> Element {
-        let orig = get_indexed_element(orig, index);
-        Element {
-            x: e.x,
-            y: e.y,
-            z: e.z,
-            pitch: e.pitch,
-            yaw: e.yaw,
-            roll: e.roll,
-            sizex: e.xscale * orig.sizex,
-            sizey: e.yscale * orig.sizey,
-            sizez: e.zscale * orig.sizez,
-        }
-    }
-    let orig = &*ORIGINAL_MAP;
-    RefunctMap {
-        version: 1,
-        clusters: map.clusters.into_iter().enumerate().map(|(cluster_index, cluster)| Cluster {
-            z: orig.clusters[cluster_index].z,
-            rise_speed: orig.clusters[cluster_index].rise_speed,
-            platforms: cluster.platforms.into_iter().enumerate().map(|(element_index, e)| migrate_element(&orig, e, ElementIndex { cluster_index, element_type: ElementType::Platform, element_index })).collect(),
-            cubes: cluster.cubes.into_iter().enumerate().map(| (element_index, e)| migrate_element(&orig, e, ElementIndex { cluster_index, element_type: ElementType::Cube, element_index })).collect(),
-            buttons: cluster.buttons.into_iter().enumerate().map(| (element_index, e)| migrate_element(&orig, e, ElementIndex { cluster_index, element_type: ElementType::Button, element_index })).collect(),
-            lifts: orig.clusters[cluster_index].lifts.clone(),
-            pipes: orig.clusters[cluster_index].pipes.clone(),
-            springpads: orig.clusters[cluster_index].springpads.clone(),
-        }).collect(),
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, rebo::ExternalType)]
 pub struct RefunctMap {
     version: u32,
     clusters: Vec<Cluster>,
+    snap: SnapSettings,
+}
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, rebo::ExternalType)]
+struct SnapSettings {
+    location: f32,
+    rotation: f32,
+    size: f32,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, rebo::ExternalType)]
 struct Cluster {
     z: f32,
     rise_speed: f32,
+    rise_order: usize,
+    rise_delay: f32,
     platforms: Vec<Element>,
     cubes: Vec<Element>,
     buttons: Vec<Element>,
     lifts: Vec<Element>,
     pipes: Vec<Element>,
     springpads: Vec<Element>,
+    spawned: Vec<SpawnedElement>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize, rebo::ExternalType)]
+struct SpawnedElement {
+    element_type: ElementType,
+    #[serde(flatten)]
+    element: Element,
 }
 #[derive(Debug, Clone, Serialize, Deserialize, rebo::ExternalType)]
 struct Element {
@@ -1018,6 +1307,204 @@ struct Element {
     sizex: f32,
     sizey: f32,
     sizez: f32,
+    hidden: bool,
+}
+impl From<refunct_map::RefunctMap> for RefunctMap {
+    fn from(map: refunct_map::RefunctMap) -> Self {
+        RefunctMap {
+            version: map.version,
+            clusters: map.clusters.into_iter().map(Cluster::from).collect(),
+            snap: map.snap.into(),
+        }
+    }
+}
+impl From<RefunctMap> for refunct_map::RefunctMap {
+    fn from(map: RefunctMap) -> Self {
+        refunct_map::RefunctMap {
+            version: map.version,
+            clusters: map.clusters.into_iter().map(Into::into).collect(),
+            snap: map.snap.into(),
+        }
+    }
+}
+impl From<refunct_map::SnapSettings> for SnapSettings {
+    fn from(snap: refunct_map::SnapSettings) -> Self {
+        let refunct_map::SnapSettings { location, rotation, size } = snap;
+        SnapSettings { location, rotation, size }
+    }
+}
+impl From<SnapSettings> for refunct_map::SnapSettings {
+    fn from(snap: SnapSettings) -> Self {
+        let SnapSettings { location, rotation, size } = snap;
+        refunct_map::SnapSettings { location, rotation, size }
+    }
+}
+impl From<refunct_map::Cluster> for Cluster {
+    fn from(cluster: refunct_map::Cluster) -> Self {
+        let refunct_map::Cluster { z, rise_speed, rise_order, rise_delay, platforms, cubes, buttons, lifts, pipes, springpads, spawned } = cluster;
+        let convert = |elements: Vec<refunct_map::Element>| elements.into_iter().map(Element::from).collect();
+        Cluster {
+            z,
+            rise_speed,
+            rise_order,
+            rise_delay,
+            platforms: convert(platforms),
+            cubes: convert(cubes),
+            buttons: convert(buttons),
+            lifts: convert(lifts),
+            pipes: convert(pipes),
+            springpads: convert(springpads),
+            spawned: spawned.into_iter().map(SpawnedElement::from).collect(),
+        }
+    }
+}
+impl From<Cluster> for refunct_map::Cluster {
+    fn from(cluster: Cluster) -> Self {
+        let Cluster { z, rise_speed, rise_order, rise_delay, platforms, cubes, buttons, lifts, pipes, springpads, spawned } = cluster;
+        let convert = |elements: Vec<Element>| elements.into_iter().map(Into::into).collect();
+        refunct_map::Cluster {
+            z,
+            rise_speed,
+            rise_order,
+            rise_delay,
+            platforms: convert(platforms),
+            cubes: convert(cubes),
+            buttons: convert(buttons),
+            lifts: convert(lifts),
+            pipes: convert(pipes),
+            springpads: convert(springpads),
+            spawned: spawned.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+impl From<refunct_map::SpawnedElement> for SpawnedElement {
+    fn from(spawned: refunct_map::SpawnedElement) -> Self {
+        SpawnedElement { element_type: spawned.element_type.into(), element: spawned.element.into() }
+    }
+}
+impl From<SpawnedElement> for refunct_map::SpawnedElement {
+    fn from(spawned: SpawnedElement) -> Self {
+        refunct_map::SpawnedElement { element_type: spawned.element_type.into(), element: spawned.element.into() }
+    }
+}
+impl From<refunct_map::Element> for Element {
+    fn from(e: refunct_map::Element) -> Self {
+        let refunct_map::Element { x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden } = e;
+        Element { x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden }
+    }
+}
+impl From<Element> for refunct_map::Element {
+    fn from(e: Element) -> Self {
+        let Element { x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden } = e;
+        refunct_map::Element { x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden }
+    }
+}
+impl From<refunct_map::ElementType> for ElementType {
+    fn from(element_type: refunct_map::ElementType) -> Self {
+        match element_type {
+            refunct_map::ElementType::Platform => ElementType::Platform,
+            refunct_map::ElementType::Cube => ElementType::Cube,
+            refunct_map::ElementType::Button => ElementType::Button,
+            refunct_map::ElementType::Lift => ElementType::Lift,
+            refunct_map::ElementType::Pipe => ElementType::Pipe,
+            refunct_map::ElementType::Springpad => ElementType::Springpad,
+        }
+    }
+}
+impl From<ElementType> for refunct_map::ElementType {
+    fn from(element_type: ElementType) -> Self {
+        match element_type {
+            ElementType::Platform => refunct_map::ElementType::Platform,
+            ElementType::Cube => refunct_map::ElementType::Cube,
+            ElementType::Button => refunct_map::ElementType::Button,
+            ElementType::Lift => refunct_map::ElementType::Lift,
+            ElementType::Pipe => refunct_map::ElementType::Pipe,
+            ElementType::Springpad => refunct_map::ElementType::Springpad,
+        }
+    }
+}
+
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct MapPatch {
+    clusters: Vec<ClusterPatch>,
+}
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct ClusterPatch {
+    cluster_index: usize,
+    z: Option<f32>,
+    rise_speed: Option<f32>,
+    rise_order: Option<usize>,
+    rise_delay: Option<f32>,
+    elements: Vec<ElementPatch>,
+    spawned: Option<Vec<SpawnedElement>>,
+}
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct ElementPatch {
+    element_type: ElementType,
+    element_index: usize,
+    x: Option<f32>,
+    y: Option<f32>,
+    z: Option<f32>,
+    pitch: Option<f32>,
+    yaw: Option<f32>,
+    roll: Option<f32>,
+    sizex: Option<f32>,
+    sizey: Option<f32>,
+    sizez: Option<f32>,
+    hidden: Option<bool>,
+}
+impl From<refunct_map::MapPatch> for MapPatch {
+    fn from(patch: refunct_map::MapPatch) -> Self {
+        MapPatch { clusters: patch.clusters.into_iter().map(ClusterPatch::from).collect() }
+    }
+}
+impl From<MapPatch> for refunct_map::MapPatch {
+    fn from(patch: MapPatch) -> Self {
+        refunct_map::MapPatch {
+            patch_version: refunct_map::CURRENT_PATCH_VERSION,
+            clusters: patch.clusters.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+impl From<refunct_map::ClusterPatch> for ClusterPatch {
+    fn from(patch: refunct_map::ClusterPatch) -> Self {
+        let refunct_map::ClusterPatch { cluster, z, rise_speed, rise_order, rise_delay, elements, spawned } = patch;
+        ClusterPatch {
+            cluster_index: cluster,
+            z,
+            rise_speed,
+            rise_order,
+            rise_delay,
+            elements: elements.into_iter().map(ElementPatch::from).collect(),
+            spawned: spawned.map(|spawned| spawned.into_iter().map(SpawnedElement::from).collect()),
+        }
+    }
+}
+impl From<ClusterPatch> for refunct_map::ClusterPatch {
+    fn from(patch: ClusterPatch) -> Self {
+        let ClusterPatch { cluster_index, z, rise_speed, rise_order, rise_delay, elements, spawned } = patch;
+        refunct_map::ClusterPatch {
+            cluster: cluster_index,
+            z,
+            rise_speed,
+            rise_order,
+            rise_delay,
+            elements: elements.into_iter().map(Into::into).collect(),
+            spawned: spawned.map(|spawned| spawned.into_iter().map(Into::into).collect()),
+        }
+    }
+}
+impl From<refunct_map::ElementPatch> for ElementPatch {
+    fn from(patch: refunct_map::ElementPatch) -> Self {
+        let refunct_map::ElementPatch { element_type, index, x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden } = patch;
+        ElementPatch { element_type: element_type.into(), element_index: index, x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden }
+    }
+}
+impl From<ElementPatch> for refunct_map::ElementPatch {
+    fn from(patch: ElementPatch) -> Self {
+        let ElementPatch { element_type, element_index, x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden } = patch;
+        refunct_map::ElementPatch { element_type: element_type.into(), index: element_index, x, y, z, pitch, yaw, roll, sizex, sizey, sizez, hidden }
+    }
 }
 
 fn map_path() -> PathBuf {
@@ -1038,43 +1525,189 @@ fn list_maps() -> Vec<String> {
         }).collect()
 }
 #[rebo::function("Tas::load_map")]
-fn load_map(filename: String) -> RefunctMap {
-    #[derive(Deserialize)]
-    struct Version {
-        #[serde(default)]
-        version: u32,
-    }
+fn load_map(filename: String) -> Result<RefunctMap, String> {
     let filename = sanitize_filename::sanitize(filename);
-    let path = map_path().join(filename);
-    let content = std::fs::read_to_string(path).unwrap();
-    let version: Version = serde_json::from_str(&content).unwrap();
-    let map = match version.version {
-        0 => {
-            let map = serde_json::from_str(&content).unwrap();
-            migrate_v0_to_v1(map)
-        }
-        1 => serde_json::from_str(&content).unwrap(),
-        version => panic!("the map lives in the future (unknown map version {version})"),
-    };
-    map
+    let path = map_path().join(&filename);
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("can't read map {filename}: {e}"))?;
+    let original = refunct_map::RefunctMap::from(ORIGINAL_MAP.clone());
+    refunct_map::parse(&content, &original)
+        .map(RefunctMap::from)
+        .map_err(|e| e.to_string())
 }
 #[rebo::function("Tas::save_map")]
 fn save_map(filename: String, map: RefunctMap) {
     let filename = sanitize_filename::sanitize(filename);
     let path = map_path().join(filename);
     let file = File::create(path).unwrap();
-    serde_json::to_writer_pretty(file, &map).unwrap();
+    serde_json::to_writer_pretty(file, &refunct_map::RefunctMap::from(map)).unwrap();
+}
+#[rebo::function("Tas::export_map")]
+fn export_map(map: RefunctMap) -> String {
+    refunct_map::export(&refunct_map::RefunctMap::from(map))
+}
+#[rebo::function("Tas::import_map")]
+fn import_map(map: String) -> Result<RefunctMap, String> {
+    let original = refunct_map::RefunctMap::from(ORIGINAL_MAP.clone());
+    refunct_map::import(&map, &original)
+        .map(RefunctMap::from)
+        .map_err(|e| e.to_string())
+}
+/// patch which turns map `a` into map `b`
+#[rebo::function("Tas::diff_maps")]
+fn diff_maps(a: RefunctMap, b: RefunctMap) -> Result<MapPatch, String> {
+    refunct_map::diff(&a.into(), &b.into())
+        .map(MapPatch::from)
+        .map_err(|e| e.to_string())
+}
+#[rebo::function("Tas::apply_map_patch")]
+fn apply_map_patch(map: RefunctMap, patch: MapPatch) -> Result<RefunctMap, String> {
+    let original = refunct_map::RefunctMap::from(ORIGINAL_MAP.clone());
+    let map = refunct_map::apply_patch(&map.into(), &patch.into()).map_err(|e| e.to_string())?;
+    let diagnostics = refunct_map::validate(&map, &original);
+    if !diagnostics.is_empty() {
+        return Err(refunct_map::MapError::Invalid(diagnostics).to_string());
+    }
+    Ok(map.into())
+}
+/// Save a patch as map file, which is applied on top of the original map when loading it.
+#[rebo::function("Tas::save_map_patch")]
+fn save_map_patch(filename: String, patch: MapPatch) {
+    let filename = sanitize_filename::sanitize(filename);
+    let path = map_path().join(filename);
+    let file = File::create(path).unwrap();
+    serde_json::to_writer_pretty(file, &refunct_map::MapPatch::from(patch)).unwrap();
+}
+/// one line per changed field
+#[rebo::function("Tas::map_patch_lines")]
+fn map_patch_lines(patch: MapPatch) -> Vec<String> {
+    refunct_map::MapPatch::from(patch).to_string().lines().map(String::from).collect()
+}
+fn map_history_path(filename: &str) -> PathBuf {
+    let history_path = data_path().join("map-history/");
+    if !history_path.is_dir() {
+        std::fs::create_dir(&history_path).unwrap();
+    }
+    history_path.join(format!("{}.json", sanitize_filename::sanitize(filename)))
+}
+/// Minimum time between saves of the edited map, such that continuous edits like dragging an
+/// element don't write the map and its history every frame.
+const MAP_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+/// Map opened with `Tas::open_map_history`.
+pub(super) struct MapHistory {
+    filename: String,
+    history: refunct_map::History,
+    /// time of the oldest edit which isn't saved yet
+    unsaved_since: Option<Instant>,
+}
+impl MapHistory {
+    fn edited(&mut self) {
+        self.unsaved_since.get_or_insert_with(Instant::now);
+    }
+    /// Save the map and its history if an edit is unsaved for `MAP_SAVE_INTERVAL`, or if `force`
+    /// is set and there are any unsaved edits.
+    ///
+    /// Called each frame, so deferred edits are saved even if no further edits follow.
+    pub(super) fn save(&mut self, force: bool) -> io::Result<()> {
+        match self.unsaved_since {
+            Some(since) if force || since.elapsed() >= MAP_SAVE_INTERVAL => (),
+            _ => return Ok(()),
+        }
+        let path = map_path().join(sanitize_filename::sanitize(&self.filename));
+        let mut file = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut file, self.history.current())?;
+        file.flush()?;
+        let mut file = BufWriter::new(File::create(map_history_path(&self.filename))?);
+        serde_json::to_writer(&mut file, &self.history)?;
+        file.flush()?;
+        self.unsaved_since = None;
+        Ok(())
+    }
+}
+/// Start recording edits of the given map for `Tas::map_undo` and `Tas::map_redo`.
+///
+/// The history of the last editing session is continued if the map wasn't changed in the meantime.
+#[rebo::function("Tas::open_map_history")]
+fn open_map_history(filename: String, map: RefunctMap) {
+    let map = refunct_map::RefunctMap::from(map);
+    let history = std::fs::read_to_string(map_history_path(&filename)).ok()
+        .and_then(|content| serde_json::from_str::<refunct_map::History>(&content).ok())
+        .filter(|history| *history.current() == map)
+        .unwrap_or_else(|| refunct_map::History::new(map, refunct_map::DEFAULT_HISTORY_LIMIT));
+    STATE.lock().unwrap().as_mut().unwrap().map_history = Some(MapHistory { filename, history, unsaved_since: None });
+}
+/// Stop recording edits, saving the ones not saved yet.
+#[rebo::function("Tas::close_map_history")]
+fn close_map_history() -> Result<(), String> {
+    let map_history = STATE.lock().unwrap().as_mut().unwrap().map_history.take();
+    match map_history {
+        Some(mut map_history) => map_history.save(true).map_err(save_error),
+        None => Ok(()),
+    }
+}
+/// Record an edit in the history of the map opened with `Tas::open_map_history`, save and apply it.
+///
+/// Edits are saved at most every `MAP_SAVE_INTERVAL`.
+/// Returns an error if no history is open, if the map doesn't have the same clusters and
+/// elements as the recorded one or if it can't be saved.
+#[rebo::function("Tas::edit_map")]
+fn edit_map(map: RefunctMap) -> Result<(), String> {
+    let mut state = STATE.lock().unwrap();
+    let map_history = state.as_mut().unwrap().map_history.as_mut()
+        .ok_or_else(no_map_history)?;
+    if map_history.history.record(map.clone().into()).map_err(|e| e.to_string())? {
+        map_history.edited();
+    }
+    let saved = map_history.save(false).map_err(save_error);
+    drop(state);
+    apply_map_internal(&map);
+    saved
+}
+/// Revert the last edit, save and apply the map, returning it.
+///
+/// Returns `None` if there is nothing to undo and an error if no history is open.
+#[rebo::function("Tas::map_undo")]
+fn map_undo() -> Result<Option<RefunctMap>, String> {
+    map_undo_redo(refunct_map::History::undo)
+}
+/// Reapply the last undone edit, save and apply the map, returning it.
+///
+/// Returns `None` if there is nothing to redo and an error if no history is open.
+#[rebo::function("Tas::map_redo")]
+fn map_redo() -> Result<Option<RefunctMap>, String> {
+    map_undo_redo(refunct_map::History::redo)
+}
+fn map_undo_redo(f: fn(&mut refunct_map::History) -> Result<Option<&refunct_map::RefunctMap>, refunct_map::PatchError>) -> Result<Option<RefunctMap>, String> {
+    let mut state = STATE.lock().unwrap();
+    let map_history = state.as_mut().unwrap().map_history.as_mut()
+        .ok_or_else(no_map_history)?;
+    let map = match f(&mut map_history.history).map_err(|e| e.to_string())? {
+        Some(map) => RefunctMap::from(map.clone()),
+        None => return Ok(None),
+    };
+    map_history.edited();
+    let saved = map_history.save(false).map_err(save_error);
+    drop(state);
+    apply_map_internal(&map);
+    saved.map(|()| Some(map))
+}
+fn no_map_history() -> String {
+    "no map history is open, Tas::open_map_history must be called first".to_string()
+}
+fn save_error(e: io::Error) -> String {
+    format!("can't save map: {e}")
 }
 #[rebo::function("Tas::remove_map")]
 fn remove_map(filename: String) -> bool {
     let filename = sanitize_filename::sanitize(filename);
-    let path = map_path().join(filename);
+    let path = map_path().join(&filename);
+    let _ = std::fs::remove_file(map_history_path(&filename));
     std::fs::remove_file(path).is_ok()
 }
 
 fn get_current_map(original: bool) -> RefunctMap {
     UeScope::with(|scope| {
-        fn actor_list_to_element_list<'a, T, F>(scope: &'a UeScope, level: &LevelWrapper<'a>, list: &[ObjectIndex<T>], element_type: ElementType, get_orig_size: F) -> Vec<Element>
+        fn actor_list_to_element_list<'a, T, F>(scope: &'a UeScope, cluster_index: usize, level: &LevelWrapper<'a>, list: &[ObjectIndex<T>], element_type: ElementType, get_orig_size: F) -> Vec<Element>
         where
             T: UeObjectWrapperType,
             T::UeObjectWrapper<'a>: Deref<Target = ActorWrapper<'a>>,
@@ -1083,15 +1716,38 @@ fn get_current_map(original: bool) -> RefunctMap {
             list.iter().enumerate().map(|(element_index, actor)| {
                 let actor = scope.get(actor);
                 let actor = actor.deref();
-                let index = ElementIndex { cluster_index: level.level_index(), element_type, element_index };
+                let index = ElementIndex { cluster_index, element_type, element_index };
                 let (sizex, sizey, sizez) = get_orig_size(actor, index);
                 let (_, _, lz) = level.relative_location();
                 let (ax, ay, az) = actor.absolute_location();
                 let (pitch, yaw, roll) = actor.relative_rotation();
                 let (xscale, yscale, zscale) = actor.relative_scale();
-                Element { x: ax, y: ay, z: az - lz, pitch, yaw, roll, sizex: sizex / xscale, sizey: sizey / yscale, sizez: sizez / zscale }
+                let hidden = actor.is_hidden_in_game();
+                Element { x: ax, y: ay, z: az - lz, pitch, yaw, roll, sizex: sizex / xscale, sizey: sizey / yscale, sizez: sizez / zscale, hidden }
             }).collect()
         }
+        fn spawned_elements(scope: &UeScope, levels: &[Level], cluster_index: usize) -> Vec<SpawnedElement> {
+            let level = scope.get(levels[cluster_index].level);
+            let (_, _, lz) = level.relative_location();
+            let (lpitch, lyaw, lroll) = level.relative_rotation();
+            SPAWNED_ELEMENTS.lock().unwrap().iter()
+                .filter(|spawned| spawned.cluster_index == cluster_index)
+                // the actor may have been destroyed by the game in the meantime
+                .filter_map(|spawned| scope.try_get::<ActorWrapper>(&spawned.actor).ok().map(|actor| (spawned.element_type, actor)))
+                .map(|(element_type, actor)| {
+                    let orig = get_indexed_element(&*ORIGINAL_MAP, spawn_template(levels, element_type));
+                    let (ax, ay, az) = actor.absolute_location();
+                    let (pitch, yaw, roll) = actor.relative_rotation();
+                    let (xscale, yscale, zscale) = actor.relative_scale();
+                    let element = Element {
+                        x: ax, y: ay, z: az - lz,
+                        pitch: pitch - lpitch, yaw: yaw - lyaw, roll: roll - lroll,
+                        sizex: orig.sizex * xscale, sizey: orig.sizey * yscale, sizez: orig.sizez * zscale,
+                        hidden: actor.is_hidden_in_game(),
+                    };
+                    SpawnedElement { element_type, element }
+                }).collect()
+        }
         let get_orig_size: Box<for<'a> fn(&'a ActorWrapper, _) -> _> = if original {
             Box::new(|actor: &ActorWrapper, _index: ElementIndex| {
                 let (_, _, _, hx, hy, hz) = actor.get_actor_bounds();
@@ -1120,21 +1776,27 @@ fn get_current_map(original: bool) -> RefunctMap {
             })
         };
         let levels = LEVELS.lock().unwrap();
+        let rise_delays = RISE_DELAYS.lock().unwrap();
         let clusters: Vec<Cluster> = levels.iter()
-            .map(|level| {
+            .enumerate()
+            .map(|(cluster_index, level)| {
                 let level_wrapper = scope.get(level.level);
                 Cluster {
                     z: level_wrapper.source_location().2,
-                    rise_speed: level_wrapper.speed(),
-                    platforms: actor_list_to_element_list(scope, &level_wrapper, &level.platforms, ElementType::Platform, &get_orig_size),
-                    cubes: actor_list_to_element_list(scope, &level_wrapper, &level.cubes, ElementType::Cube, &get_orig_size),
-                    buttons: actor_list_to_element_list(scope, &level_wrapper, &level.buttons, ElementType::Button, &get_orig_size),
-                    lifts: actor_list_to_element_list(scope, &level_wrapper, &level.lifts, ElementType::Lift, &get_orig_size),
-                    pipes: actor_list_to_element_list(scope, &level_wrapper, &level.pipes, ElementType::Pipe, &get_orig_size),
-                    springpads: actor_list_to_element_list(scope, &level_wrapper, &level.springpads, ElementType::Springpad, &get_orig_size),
+                    rise_speed: rise_delays.speed(cluster_index, &level_wrapper),
+                    rise_order: level_wrapper.level_index(),
+                    rise_delay: if original { 0. } else { rise_delays.delay(cluster_index) },
+                    platforms: actor_list_to_element_list(scope, cluster_index, &level_wrapper, &level.platforms, ElementType::Platform, &get_orig_size),
+                    cubes: actor_list_to_element_list(scope, cluster_index, &level_wrapper, &level.cubes, ElementType::Cube, &get_orig_size),
+                    buttons: actor_list_to_element_list(scope, cluster_index, &level_wrapper, &level.buttons, ElementType::Button, &get_orig_size),
+                    lifts: actor_list_to_element_list(scope, cluster_index, &level_wrapper, &level.lifts, ElementType::Lift, &get_orig_size),
+                    pipes: actor_list_to_element_list(scope, cluster_index, &level_wrapper, &level.pipes, ElementType::Pipe, &get_orig_size),
+                    springpads: actor_list_to_element_list(scope, cluster_index, &level_wrapper, &level.springpads, ElementType::Springpad, &get_orig_size),
+                    // the original map never contains spawned elements
+                    spawned: if original { Vec::new() } else { spawned_elements(scope, &levels, cluster_index) },
                 }
             }).collect();
-        RefunctMap { version: 1, clusters }
+        RefunctMap { version: refunct_map::CURRENT_VERSION, clusters, snap: SnapSettings::default() }
     })
 }
 
@@ -1165,16 +1827,34 @@ pub fn apply_map_internal(map: &RefunctMap) {
         let target_scale = FVector { x: target.sizex / orig.sizex, y: target.sizey / orig.sizey, z: target.sizez / orig.sizez };
         USceneComponent::set_world_location_and_rotation(target_location, target_rotation, &actor);
         USceneComponent::set_world_scale(target_scale, &actor);
+        actor.set_hidden_in_game(target.hidden);
+    }
+    fn spawn_element(scope: &UeScope, levels: &[Level], cluster_index: usize, spawned: &SpawnedElement) -> SpawnedElementActor {
+        let level = scope.get(levels[cluster_index].level);
+        let template_index = spawn_template(levels, spawned.element_type);
+        let template = get_indexed_actor(scope, levels, template_index);
+        let orig = get_indexed_element(&*ORIGINAL_MAP, template_index);
+        let target = &spawned.element;
+        let (_, _, rz) = level.relative_location();
+        let (rpitch, ryaw, rroll) = level.relative_rotation();
+        let ptr = UWorld::spawn_from_template(&template, target.x, target.y, target.z + rz, target.pitch + rpitch, target.yaw + ryaw, target.roll + rroll);
+        let actor = unsafe { ActorWrapper::new(ptr) };
+        let target_scale = FVector { x: target.sizex / orig.sizex, y: target.sizey / orig.sizey, z: target.sizez / orig.sizez };
+        USceneComponent::set_world_scale(target_scale, &actor);
+        actor.set_hidden_in_game(target.hidden);
+        SpawnedElementActor { cluster_index, element_type: spawned.element_type, actor: scope.object_index(&actor) }
     }
 
     UeScope::with(|scope| {
         let levels = LEVELS.lock().unwrap();
         assert_eq!(map.clusters.len(), levels.len());
+        RISE_DELAYS.lock().unwrap().set_delays(scope, &levels, map.clusters.iter().map(|cluster| cluster.rise_delay).collect());
         for (cluster_index, (level, cluster)) in levels.iter().zip(&map.clusters).enumerate() {
             let level_wrapper = scope.get(level.level);
             let (rx, ry, _) = level_wrapper.source_location();
             level_wrapper.set_source_location(rx, ry, cluster.z);
             level_wrapper.set_speed(cluster.rise_speed);
+            level_wrapper.set_level_index(cluster.rise_order);
             level.platforms.iter().zip(&cluster.platforms).enumerate().map(|i| (i.0, ElementType::Platform))
                 .chain(level.cubes.iter().zip(&cluster.cubes).enumerate().map(|i| (i.0, ElementType::Cube)))
                 .chain(level.buttons.iter().zip(&cluster.buttons).enumerate().map(|i| (i.0, ElementType::Button)))
@@ -1186,6 +1866,18 @@ pub fn apply_map_internal(map: &RefunctMap) {
                     set_element(scope, &levels, &map, index);
                 });
         }
+
+        let mut spawned_elements = SPAWNED_ELEMENTS.lock().unwrap();
+        for spawned in spawned_elements.drain(..) {
+            if let Ok(actor) = scope.try_get::<ActorWrapper>(&spawned.actor) {
+                UWorld::destroy_spawned(actor.as_ptr());
+            }
+        }
+        for (cluster_index, cluster) in map.clusters.iter().enumerate() {
+            for spawned in &cluster.spawned {
+                spawned_elements.push(spawn_element(scope, &levels, cluster_index, spawned));
+            }
+        }
     })
 }
 #[rebo::function("Tas::apply_map")]
@@ -1210,6 +1902,21 @@ fn get_indexed_element(map: &RefunctMap, index: ElementIndex) -> Element {
         ElementType::Springpad => level.springpads[index.element_index].clone(),
     }
 }
+/// Original element which is copied when spawning an element of the given type.
+fn spawn_template(levels: &[Level], element_type: ElementType) -> ElementIndex {
+    levels.iter().enumerate()
+        .find_map(|(cluster_index, level)| {
+            let count = match element_type {
+                ElementType::Platform => level.platforms.len(),
+                ElementType::Cube => level.cubes.len(),
+                ElementType::Button => level.buttons.len(),
+                ElementType::Lift => level.lifts.len(),
+                ElementType::Pipe => level.pipes.len(),
+                ElementType::Springpad => level.springpads.len(),
+            };
+            (count > 0).then_some(ElementIndex { cluster_index, element_type, element_index: 0 })
+        }).unwrap_or_else(|| panic!("no {element_type:?} to spawn from"))
+}
 fn get_indexed_actor<'a>(scope: &'a UeScope, levels: &[Level], index: ElementIndex) -> ActorWrapper<'a> {
     let level = &levels[index.cluster_index];
     match index.element_type {
@@ -1242,6 +1949,206 @@ fn get_element_bounds(index: ElementIndex) -> Bounds {
     })
 }
 
+fn cluster_element_indices(level: &Level, cluster_index: usize) -> impl Iterator<Item = ElementIndex> {
+    let indices = move |element_type, count| (0..count).map(move |element_index| ElementIndex { cluster_index, element_type, element_index });
+    indices(ElementType::Platform, level.platforms.len())
+        .chain(indices(ElementType::Cube, level.cubes.len()))
+        .chain(indices(ElementType::Button, level.buttons.len()))
+        .chain(indices(ElementType::Lift, level.lifts.len()))
+        .chain(indices(ElementType::Pipe, level.pipes.len()))
+        .chain(indices(ElementType::Springpad, level.springpads.len()))
+}
+#[rebo::function("Tas::get_cluster_element_indices")]
+fn get_cluster_element_indices(cluster_index: usize) -> Vec<ElementIndex> {
+    let levels = LEVELS.lock().unwrap();
+    match levels.get(cluster_index) {
+        Some(level) => cluster_element_indices(level, cluster_index).collect(),
+        None => Vec::new(),
+    }
+}
+/// all elements whose world-space bounds intersect the given box
+#[rebo::function("Tas::get_element_indices_in_bounds")]
+fn get_element_indices_in_bounds(bounds: Bounds) -> Vec<ElementIndex> {
+    let overlaps = |origin: f32, extent: f32, bounds_origin: f32, bounds_extent: f32| (origin - bounds_origin).abs() <= extent.abs() + bounds_extent.abs();
+    UeScope::with(|scope| {
+        let levels = LEVELS.lock().unwrap();
+        levels.iter().enumerate()
+            .flat_map(|(cluster_index, level)| cluster_element_indices(level, cluster_index))
+            .filter(|&index| {
+                // actor bounds already contain the location of the cluster
+                let (x, y, z, ex, ey, ez) = get_indexed_actor(scope, &levels, index).get_actor_bounds();
+                overlaps(x, ex, bounds.originx, bounds.extentx)
+                    && overlaps(y, ey, bounds.originy, bounds.extenty)
+                    && overlaps(z, ez, bounds.originz, bounds.extentz)
+            }).collect()
+    })
+}
+
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct GroupTransform {
+    translation: Vector,
+    /// rotation around the vertical axis through the pivot in degrees
+    yaw: f32,
+    /// uniform scale relative to the pivot
+    scale: f32,
+    pivot: Location,
+}
+impl From<GroupTransform> for refunct_map::GroupTransform {
+    fn from(transform: GroupTransform) -> Self {
+        let GroupTransform { translation, yaw, scale, pivot } = transform;
+        refunct_map::GroupTransform {
+            translation: (translation.x, translation.y, translation.z),
+            yaw,
+            scale,
+            pivot: (pivot.x, pivot.y, pivot.z),
+        }
+    }
+}
+fn element_refs(selection: &[ElementIndex]) -> Vec<refunct_map::ElementRef> {
+    selection.iter().map(|index| refunct_map::ElementRef {
+        cluster: index.cluster_index,
+        element: refunct_map::ElementPath::Original(index.element_type.into(), index.element_index),
+    }).collect()
+}
+/// mean location of the selected elements, with `z` including the `z` of their clusters
+#[rebo::function("Tas::selection_center")]
+fn selection_center(map: RefunctMap, selection: Vec<ElementIndex>) -> Option<Location> {
+    let (x, y, z) = refunct_map::selection_center(&map.into(), &element_refs(&selection))?;
+    Some(Location { x, y, z })
+}
+/// Transform all selected elements of the map at once, returning the new map.
+#[rebo::function("Tas::transform_elements")]
+fn transform_elements(map: RefunctMap, selection: Vec<ElementIndex>, transform: GroupTransform) -> RefunctMap {
+    let mut map = refunct_map::RefunctMap::from(map);
+    refunct_map::transform_elements(&mut map, &element_refs(&selection), &transform.into());
+    map.into()
+}
+/// Snap the element's values to the grid sizes of the settings, returning the snapped element.
+#[rebo::function("Tas::snap_element")]
+fn snap_element(element: Element, settings: SnapSettings) -> Element {
+    let mut element = refunct_map::Element::from(element);
+    refunct_map::snap_element(&mut element, &settings.into());
+    element.into()
+}
+
+#[derive(Debug, Clone, Copy, rebo::ExternalType)]
+enum Alignment {
+    OnTop,
+    PosX,
+    NegX,
+    PosY,
+    NegY,
+}
+impl From<Alignment> for refunct_map::Alignment {
+    fn from(alignment: Alignment) -> Self {
+        match alignment {
+            Alignment::OnTop => refunct_map::Alignment::OnTop,
+            Alignment::PosX => refunct_map::Alignment::PosX,
+            Alignment::NegX => refunct_map::Alignment::NegX,
+            Alignment::PosY => refunct_map::Alignment::PosY,
+            Alignment::NegY => refunct_map::Alignment::NegY,
+        }
+    }
+}
+/// Translation which aligns the element with the target element, computed from their current bounds.
+#[rebo::function("Tas::align_element")]
+fn align_element(index: ElementIndex, target: ElementIndex, alignment: Alignment) -> Vector {
+    UeScope::with(|scope| {
+        let levels = LEVELS.lock().unwrap();
+        let aabb = |index| {
+            let (ox, oy, oz, ex, ey, ez) = get_indexed_actor(scope, &levels, index).get_actor_bounds();
+            refunct_map::Aabb { origin: (ox, oy, oz), extent: (ex, ey, ez) }
+        };
+        let (x, y, z) = refunct_map::align(&aabb(index), &aabb(target), alignment.into());
+        Vector { x, y, z }
+    })
+}
+
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct ReachabilityParams {
+    jump_height: f32,
+    jump_distance: f32,
+    springpad_height: f32,
+    /// location of the player's feet at the start, `None` to start on the clusters risen at the start
+    spawn: Option<Location>,
+}
+impl From<refunct_map::ReachabilityParams> for ReachabilityParams {
+    fn from(params: refunct_map::ReachabilityParams) -> Self {
+        let refunct_map::ReachabilityParams { jump_height, jump_distance, springpad_height, spawn } = params;
+        ReachabilityParams { jump_height, jump_distance, springpad_height, spawn: spawn.map(|(x, y, z)| Location { x, y, z }) }
+    }
+}
+impl From<ReachabilityParams> for refunct_map::ReachabilityParams {
+    fn from(params: ReachabilityParams) -> Self {
+        let ReachabilityParams { jump_height, jump_distance, springpad_height, spawn } = params;
+        refunct_map::ReachabilityParams { jump_height, jump_distance, springpad_height, spawn: spawn.map(|Location { x, y, z }| (x, y, z)) }
+    }
+}
+#[rebo::function("Tas::default_reachability_params")]
+fn default_reachability_params() -> ReachabilityParams {
+    refunct_map::ReachabilityParams::default().into()
+}
+/// Estimate which buttons can't be reached once their cluster rose.
+#[rebo::function("Tas::unreachable_buttons")]
+fn unreachable_buttons(map: RefunctMap, params: ReachabilityParams) -> Vec<ElementIndex> {
+    refunct_map::check_reachability(&map.into(), &params.into()).into_iter()
+        .map(|button| ElementIndex { cluster_index: button.cluster, element_type: ElementType::Button, element_index: button.index })
+        .collect()
+}
+
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct GeneratorParams {
+    /// area the tops of platforms are placed in, `None` for the area of the original map
+    area: Option<Bounds>,
+    min_size: f32,
+    max_size: f32,
+    grid: f32,
+    reachability: ReachabilityParams,
+    attempts: u32,
+}
+impl From<refunct_map::GeneratorParams> for GeneratorParams {
+    fn from(params: refunct_map::GeneratorParams) -> Self {
+        let refunct_map::GeneratorParams { area, min_size, max_size, grid, reachability, attempts } = params;
+        GeneratorParams {
+            area: area.map(|refunct_map::Aabb { origin: (originx, originy, originz), extent: (extentx, extenty, extentz) }| {
+                Bounds { originx, originy, originz, extentx, extenty, extentz }
+            }),
+            min_size,
+            max_size,
+            grid,
+            reachability: reachability.into(),
+            attempts,
+        }
+    }
+}
+impl From<GeneratorParams> for refunct_map::GeneratorParams {
+    fn from(params: GeneratorParams) -> Self {
+        let GeneratorParams { area, min_size, max_size, grid, reachability, attempts } = params;
+        refunct_map::GeneratorParams {
+            area: area.map(|Bounds { originx, originy, originz, extentx, extenty, extentz }| {
+                refunct_map::Aabb { origin: (originx, originy, originz), extent: (extentx, extenty, extentz) }
+            }),
+            min_size,
+            max_size,
+            grid,
+            reachability: reachability.into(),
+            attempts,
+        }
+    }
+}
+#[rebo::function("Tas::default_generator_params")]
+fn default_generator_params() -> GeneratorParams {
+    refunct_map::GeneratorParams::default().into()
+}
+/// Generate a map from the original map, the same seed and params always give the same map.
+#[rebo::function("Tas::generate_map")]
+fn generate_map(seed: i64, params: GeneratorParams) -> Result<RefunctMap, String> {
+    let original = refunct_map::RefunctMap::from(ORIGINAL_MAP.clone());
+    refunct_map::generate(&original, seed as u64, &params.into())
+        .map(RefunctMap::from)
+        .map_err(|e| e.to_string())
+}
+
 #[rebo::function("Tas::enable_collision")]
 fn enable_collision() {
     AActor::set_actor_enable_collision(AMyCharacter::get_player().as_ptr() as *const AActor, true);
@@ -1250,4 +2157,208 @@ fn enable_collision() {
 #[rebo::function("Tas::disable_collision")]
 fn disable_collision() {
     AActor::set_actor_enable_collision(AMyCharacter::get_player().as_ptr() as *const AActor, false);
-}
\ No newline at end of file
+}
+
+/// Handle to a UObject which stays valid across frames, until the object is destroyed.
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct UeObject {
+    /// raw parts of the `WeakObjectHandle`
+    index: i32,
+    serial_number: i32,
+    name: String,
+    class_name: String,
+}
+impl UeObject {
+    fn new<'a>(scope: &'a UeScope, object: &ObjectWrapper<'a>) -> UeObject {
+        let (index, serial_number) = scope.weak_handle(object).into_raw();
+        UeObject { index, serial_number, name: object.name(), class_name: object.class().name() }
+    }
+    fn resolve<'a>(&self, scope: &'a UeScope) -> Result<ObjectWrapper<'a>, String> {
+        scope.resolve(WeakObjectHandle::from_raw(self.index, self.serial_number))
+            .map_err(|_| format!("{} ({}) doesn't exist anymore", self.name, self.class_name))
+    }
+}
+#[derive(Debug, Clone, rebo::ExternalType)]
+struct UeProperty {
+    name: String,
+    kind: String,
+    /// human-readable value
+    value: String,
+}
+#[derive(Debug, Clone, rebo::ExternalType)]
+enum UePropertyValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    String(String),
+    Object(UeObject),
+    NullObject,
+    /// fields of a struct by name, can only be written
+    Struct(Map<String, UePropertyValue>),
+    /// structs, arrays, sets, maps and delegates formatted as text, can't be written
+    Other(String),
+}
+impl UePropertyValue {
+    fn new<'a>(scope: &'a UeScope, value: InspectValue<'a>) -> UePropertyValue {
+        match value {
+            InspectValue::Bool(b) => UePropertyValue::Bool(b),
+            InspectValue::Int(i) => UePropertyValue::Int(i),
+            InspectValue::Float(f) => UePropertyValue::Float(f),
+            InspectValue::String(s) => UePropertyValue::String(s),
+            InspectValue::Object(Some(object)) => UePropertyValue::Object(UeObject::new(scope, &object)),
+            InspectValue::Object(None) => UePropertyValue::NullObject,
+            InspectValue::Struct(fields) => UePropertyValue::Struct(Map::new(fields.into_iter()
+                .map(|(name, value)| (name, UePropertyValue::new(scope, value)))
+                .collect())),
+            InspectValue::Other(s) => UePropertyValue::Other(s),
+        }
+    }
+    fn resolve<'a>(self, scope: &'a UeScope) -> Result<InspectValue<'a>, String> {
+        Ok(match self {
+            UePropertyValue::Bool(b) => InspectValue::Bool(b),
+            UePropertyValue::Int(i) => InspectValue::Int(i),
+            UePropertyValue::Float(f) => InspectValue::Float(f),
+            UePropertyValue::String(s) => InspectValue::String(s),
+            UePropertyValue::Object(object) => InspectValue::Object(Some(object.resolve(scope)?)),
+            UePropertyValue::NullObject => InspectValue::Object(None),
+            UePropertyValue::Struct(fields) => InspectValue::Struct(fields.clone_btreemap().into_iter()
+                .map(|(name, value)| Ok((name, value.resolve(scope)?)))
+                .collect::<Result<_, String>>()?),
+            UePropertyValue::Other(s) => InspectValue::Other(s),
+        })
+    }
+}
+
+#[rebo::function("Tas::get_player_object")]
+fn get_player_object() -> UeObject {
+    UeScope::with(|scope| {
+        let player = unsafe { ObjectWrapper::new(AMyCharacter::get_player().as_ptr() as *mut UObject) };
+        UeObject::new(scope, &player)
+    })
+}
+/// All objects whose class is or extends from the given class, e.g. `PlayerController`.
+#[rebo::function("Tas::find_objects_by_class")]
+fn find_objects_by_class(class_name: String) -> Vec<UeObject> {
+    UeScope::with(|scope| {
+        scope.iter_global_object_array()
+            .filter_map(|item| item.try_object())
+            .filter(|object| object.class().extends_from(&class_name))
+            .map(|object| UeObject::new(scope, &object))
+            .collect()
+    })
+}
+#[rebo::function("Tas::find_objects_by_name")]
+fn find_objects_by_name(name: String) -> Vec<UeObject> {
+    UeScope::with(|scope| {
+        scope.iter_global_object_array()
+            .filter_map(|item| item.try_object())
+            .filter(|object| object.name() == name)
+            .map(|object| UeObject::new(scope, &object))
+            .collect()
+    })
+}
+/// All properties of the object including the ones of its super classes.
+#[rebo::function("Tas::list_properties")]
+fn list_properties(object: UeObject) -> Result<Vec<UeProperty>, String> {
+    UeScope::with(|scope| {
+        let object = object.resolve(scope)?;
+        let properties = object.iter_field_values()
+            .map(|value| UeProperty { name: value.prop().name(), kind: value.prop().property_kind(), value: format_value(&value) })
+            .collect();
+        Ok(properties)
+    })
+}
+/// Read a property by path like `Controller.PlayerCameraManager.DefaultFOV`.
+#[rebo::function("Tas::get_property")]
+fn get_property(object: UeObject, path: String) -> Result<UePropertyValue, String> {
+    UeScope::with(|scope| {
+        let object = object.resolve(scope)?;
+        let value = resolve_path(&object, &path).map_err(|e| e.to_string())?;
+        Ok(UePropertyValue::new(scope, read_value(&value)))
+    })
+}
+/// Write a property by path, ints are converted to floats but otherwise the types must match.
+#[rebo::function("Tas::set_property")]
+fn set_property(object: UeObject, path: String, value: UePropertyValue) -> Result<(), String> {
+    UeScope::with(|scope| {
+        let object = object.resolve(scope)?;
+        let value = value.resolve(scope)?;
+        let property = resolve_path(&object, &path).map_err(|e| e.to_string())?;
+        write_value(&property, &path, value).map_err(|e| e.to_string())
+    })
+}
+/// Call a UFunction of the object by name, returning its `ReturnValue` and out-parameters.
+#[rebo::function("Tas::call_function")]
+fn call_function(object: UeObject, name: String, args: Map<String, UePropertyValue>) -> Result<Map<String, UePropertyValue>, String> {
+    UeScope::with(|scope| {
+        let object = object.resolve(scope)?;
+        let args = args.clone_btreemap().into_iter()
+            .map(|(name, value)| Ok((name, value.resolve(scope)?)))
+            .collect::<Result<HashMap<_, _>, String>>()?;
+        let result = crate::native::call_function(&object, &name, args).map_err(|e| e.to_string())?;
+        Ok(Map::new(result.into_iter().map(|(name, value)| (name, UePropertyValue::new(scope, value))).collect()))
+    })
+}
+/// Call `on_function_call` before every call of the function through `ProcessEvent`, starting with the next frame.
+///
+/// `ProcessEvent` is used for blueprint events, delegates and `Tas::call_function`, see `docs/reflection.md`.
+#[rebo::function("Tas::observe_function")]
+fn observe_function(class_name: String, function_name: String) -> Result<(), String> {
+    UeScope::with(|scope| {
+        let function = find_class_function(scope, &class_name, &function_name)?;
+        crate::native::observe_function(&function, class_name)
+            .map_err(|e| format!("can't hook ProcessEvent: {e}"))
+    })
+}
+#[rebo::function("Tas::unobserve_function")]
+fn unobserve_function(class_name: String, function_name: String) -> Result<(), String> {
+    UeScope::with(|scope| {
+        let function = find_class_function(scope, &class_name, &function_name)?;
+        if crate::native::unobserve_function(&function, &class_name) {
+            Ok(())
+        } else {
+            Err(format!("{class_name}::{function_name} isn't observed"))
+        }
+    })
+}
+fn find_class_function<'a>(scope: &'a UeScope, class_name: &str, function_name: &str) -> Result<FunctionWrapper<'a>, String> {
+    let class = scope.iter_global_object_array()
+        .filter_map(|item| item.try_object())
+        .filter_map(|object| object.try_upcast::<ClassWrapper>())
+        .find(|class| class.name() == class_name)
+        .ok_or_else(|| format!("there is no class {class_name}"))?;
+    class.find_function(function_name).ok_or_else(|| format!("{class_name} has no function {function_name}"))
+}
+/// Call of an observed function, read before the function runs while its arguments are still alive.
+#[derive(Debug, Clone)]
+pub struct FunctionCall {
+    class_names: Vec<String>,
+    function: String,
+    object: UeObject,
+    args: Vec<(String, UePropertyValue)>,
+}
+impl FunctionCall {
+    pub fn new(class_names: Vec<String>, object: *mut UObject, function: *mut UFunction, params: *mut c_void) -> FunctionCall {
+        UeScope::with(|scope| {
+            let object = unsafe { ObjectWrapper::new(object) };
+            let function = unsafe { FunctionWrapper::new(function) };
+            let args = unsafe { read_arguments(&function, params) }.into_iter()
+                .map(|(name, value)| (name, UePropertyValue::new(scope, value)))
+                .collect();
+            FunctionCall { class_names, function: function.name(), object: UeObject::new(scope, &object), args }
+        })
+    }
+}
+/// Write all classes, structs and enums as JSON and C++-like header, returning the directory.
+#[rebo::function("Tas::dump_reflection")]
+fn dump_reflection() -> Result<String, String> {
+    let dump = UeScope::with(crate::native::dump_reflection);
+    let path = data_path().join("reflection/");
+    (|| -> std::io::Result<String> {
+        std::fs::create_dir_all(&path)?;
+        let json = File::create(path.join("dump.json"))?;
+        serde_json::to_writer_pretty(json, &dump)?;
+        std::fs::write(path.join("dump.h"), dump.to_header())?;
+        Ok(path.display().to_string())
+    })().map_err(|e| e.to_string())
+}
@@ -1,52 +1,345 @@
-use std::io::ErrorKind;
-use std::thread;
-use std::time::Duration;
-use websocket::{ClientBuilder, Message, OwnedMessage, WebSocketError};
-use protocol::{Request, Response};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use clap::{CommandFactory, ErrorKind, Parser};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::{mpsc, Barrier};
+use tokio_tungstenite::tungstenite::Message;
+use protocol::{Movement, PlayerId, Request, Response};
 
 include!("locations.in");
 
-fn main() {
-    let name = std::env::args().nth(1).unwrap_or_else(|| "Test".to_string());
-    // let mut client = ClientBuilder::new("ws://localhost:8080/ws").unwrap().connect(None).unwrap();
-    let mut client = ClientBuilder::new("wss://refunct-tas.oberien.de/ws").unwrap().connect(None).unwrap();
-    // let mut client = ClientBuilder::new("wss://refunct-tas-test.oberien.de/ws").unwrap().connect(None).unwrap();
-    let msg = Request::JoinRoom("Test".to_string(), name, 0., 0., 1., 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
-    client.send_message(&Message::text(serde_json::to_string(&msg).unwrap())).unwrap();
-    thread::sleep(Duration::new(5, 0));
-    let msg = Request::NewGamePressed;
-    client.send_message(&Message::text(serde_json::to_string(&msg).unwrap())).unwrap();
-    while let Ok(msg) = client.recv_message() {
-        let msg: Response = match msg {
-            OwnedMessage::Text(text) => serde_json::from_str(&text).unwrap(),
-            OwnedMessage::Binary(_) | OwnedMessage::Ping(_) | OwnedMessage::Pong(_) => continue,
-            OwnedMessage::Close(_) => break,
+const LOCAL_URL: &str = "ws://localhost:8080/ws";
+const REMOTE_URL: &str = "wss://refunct-tas.oberien.de/ws";
+/// Time to wait for in-flight `MoveOther`s after all clients stopped moving.
+const DRAIN_TIME: Duration = Duration::from_secs(1);
+
+/// Load generator for the multiplayer server.
+///
+/// Simulates many clients, which join rooms, press New Game together and then replay the
+/// recorded path in `locations.in`, while measuring the latencies of the server.
+#[derive(Parser, Debug)]
+struct Args {
+    /// Server to connect to: `local`, `remote` or a websocket url
+    #[clap(short, long, value_parser, default_value = "local")]
+    server: String,
+    /// Number of simulated clients
+    #[clap(short = 'n', long, value_parser, default_value_t = 10)]
+    clients: usize,
+    /// Number of rooms the clients are distributed over round-robin
+    #[clap(short, long, value_parser, default_value_t = 1)]
+    rooms: usize,
+    /// Prefix of the room names, the room index is appended
+    #[clap(long, value_parser, default_value = "load-test")]
+    room_prefix: String,
+    /// Prefix of the player names, the client index is appended
+    #[clap(long, value_parser, default_value = "Test")]
+    name_prefix: String,
+    /// Number of moves each client sends per second
+    #[clap(short, long, value_parser, default_value_t = 30.)]
+    move_rate: f64,
+    /// Number of seconds each client moves
+    #[clap(short, long, value_parser, default_value_t = 30.)]
+    duration: f64,
+    /// Don't press New Game before moving
+    #[clap(long, action)]
+    no_new_game: bool,
+    /// Send the old `MoveSelf` without velocity instead of `MoveSelfV2`, latencies aren't measured
+    /// as the moves can't be told apart
+    #[clap(long, action)]
+    legacy_moves: bool,
+}
+
+impl Args {
+    /// Parse the arguments and exit with a usage error if they are out of range.
+    fn parse_valid() -> Args {
+        let args = Args::parse();
+        let error = if args.clients == 0 {
+            Some("--clients must be at least 1".to_string())
+        } else if args.rooms == 0 || args.rooms > args.clients {
+            Some(format!("--rooms must be within 1 and the number of clients ({})", args.clients))
+        } else if !(args.move_rate > 0. && args.move_rate.is_finite()) {
+            Some("--move-rate must be positive".to_string())
+        } else if !(args.duration >= 0. && args.duration.is_finite()) {
+            Some("--duration must not be negative".to_string())
+        } else {
+            None
         };
-        match msg {
-            Response::StartNewGameAt(ts) => {
-                while (std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64) < ts {
-                    // somewhat busy wait
-                    std::thread::sleep(std::time::Duration::from_micros(500));
+        if let Some(error) = error {
+            Args::command().error(ErrorKind::ValueValidation, error).exit();
+        }
+        args
+    }
+
+    fn url(&self) -> &str {
+        match self.server.as_str() {
+            "local" => LOCAL_URL,
+            "remote" => REMOTE_URL,
+            url => url,
+        }
+    }
+
+    fn room_of(&self, client: usize) -> usize {
+        client % self.rooms
+    }
+
+    fn room_size(&self, room: usize) -> usize {
+        self.clients / self.rooms + usize::from(room < self.clients % self.rooms)
+    }
+
+    fn player_name(&self, client: usize) -> String {
+        format!("{}-{}", self.name_prefix, client)
+    }
+}
+
+/// Measurements shared between all clients.
+struct Stats {
+    client_indices: HashMap<String, usize>,
+    /// number of moves sent by each client
+    moves_sent: Vec<Mutex<usize>>,
+    /// send-time of each `MoveSelfV2` of each client by its timestamp, which is unique per client
+    move_sent_at: Vec<Mutex<HashMap<u64, Instant>>>,
+    /// latency of each `MoveSelfV2` to each `MoveOtherV2` it caused
+    move_latencies: Mutex<Vec<Duration>>,
+    /// room, timestamp, receive-time and local unix-time in ms of each `StartNewGameAt`
+    new_game_received: Mutex<Vec<(usize, u64, Instant, u64)>>,
+    rate_limit_exceeded: Mutex<usize>,
+}
+
+enum Event {
+    PlayerJoined,
+    StartNewGameAt,
+    Disconnected,
+}
+
+#[tokio::main]
+async fn main() {
+    let args = Arc::new(Args::parse_valid());
+
+    let stats = Arc::new(Stats {
+        client_indices: (0..args.clients).map(|i| (args.player_name(i), i)).collect(),
+        moves_sent: (0..args.clients).map(|_| Mutex::new(0)).collect(),
+        move_sent_at: (0..args.clients).map(|_| Mutex::new(HashMap::new())).collect(),
+        move_latencies: Mutex::new(Vec::new()),
+        new_game_received: Mutex::new(Vec::new()),
+        rate_limit_exceeded: Mutex::new(0),
+    });
+    let barrier = Arc::new(Barrier::new(args.clients));
+
+    println!("Connecting {} clients in {} rooms to {}", args.clients, args.rooms, args.url());
+    let tasks: Vec<_> = (0..args.clients)
+        .map(|i| tokio::spawn(client(i, Arc::clone(&args), Arc::clone(&stats), Arc::clone(&barrier))))
+        .collect();
+    // the other clients would wait forever for a failed one at the barrier
+    if futures::future::try_join_all(tasks).await.is_err() {
+        eprintln!("A client failed, aborting");
+        std::process::exit(1);
+    }
+
+    report(&args, &stats);
+}
+
+async fn client(index: usize, args: Arc<Args>, stats: Arc<Stats>, barrier: Arc<Barrier>) {
+    let (ws, _) = tokio_tungstenite::connect_async(args.url()).await
+        .unwrap_or_else(|e| panic!("client {index} couldn't connect: {e}"));
+    let (mut wstx, wsrx) = ws.split();
+    let (events_tx, mut events) = mpsc::unbounded_channel();
+    let room = args.room_of(index);
+    let reader = tokio::spawn(read_responses(room, wsrx, Arc::clone(&stats), events_tx));
+
+    // spread the clients along the path
+    let path = recorded_path();
+    let offset = index * path.len() / args.clients;
+    let (x, y, z, pitch, yaw, roll) = path[offset];
+    let hue = index as f32 / args.clients as f32;
+    let join = Request::JoinRoom(format!("{}{}", args.room_prefix, room), args.player_name(index), hue, 1. - hue, 1., x, y, z, pitch, yaw, roll);
+    send(&mut wstx, &join).await;
+
+    // wait until everyone else in the room is known
+    for _ in 1..args.room_size(room) {
+        match events.recv().await {
+            Some(Event::PlayerJoined) => (),
+            Some(Event::StartNewGameAt) => unreachable!(),
+            Some(Event::Disconnected) | None => panic!("client {index} got disconnected while joining"),
+        }
+    }
+    barrier.wait().await;
+
+    if !args.no_new_game {
+        send(&mut wstx, &Request::NewGamePressed).await;
+        loop {
+            match events.recv().await {
+                Some(Event::StartNewGameAt) => break,
+                Some(Event::PlayerJoined) => (),
+                Some(Event::Disconnected) | None => panic!("client {index} got disconnected while waiting for the new game"),
+            }
+        }
+        barrier.wait().await;
+    }
+
+    let mut interval = tokio::time::interval(Duration::from_secs_f64(1. / args.move_rate));
+    let end = Instant::now() + Duration::from_secs_f64(args.duration);
+    let mut last_timestamp = 0;
+    for i in offset.. {
+        interval.tick().await;
+        if Instant::now() >= end {
+            break;
+        }
+        let req = if args.legacy_moves {
+            let (x, y, z, pitch, yaw, roll) = path[i % path.len()];
+            Request::MoveSelf(x, y, z, pitch, yaw, roll)
+        } else {
+            // the timestamp identifies the move in the `MoveOtherV2`s, even if moves are dropped
+            let timestamp = now_millis().max(last_timestamp + 1);
+            last_timestamp = timestamp;
+            stats.move_sent_at[index].lock().unwrap().insert(timestamp, Instant::now());
+            move_self_v2(&path, i, args.move_rate, timestamp)
+        };
+        *stats.moves_sent[index].lock().unwrap() += 1;
+        send(&mut wstx, &req).await;
+    }
+
+    tokio::time::sleep(DRAIN_TIME).await;
+    barrier.wait().await;
+    reader.abort();
+    let _ = wstx.close().await;
+}
+
+/// Locations of the recorded `MoveSelf`s in `locations.in`, without the presses in between.
+fn recorded_path() -> Vec<(f32, f32, f32, f32, f32, f32)> {
+    REQUESTS.iter().filter_map(|request| match *request {
+        Request::MoveSelf(x, y, z, pitch, yaw, roll) => Some((x, y, z, pitch, yaw, roll)),
+        _ => None,
+    }).collect()
+}
+
+/// Convert the `i`-th location of the path into a `MoveSelfV2`, deriving the velocity from the next one.
+fn move_self_v2(path: &[(f32, f32, f32, f32, f32, f32)], i: usize, move_rate: f64, timestamp: u64) -> Request {
+    let location = |i: usize| path[i % path.len()];
+    let (x, y, z, pitch, yaw, roll) = location(i);
+    let (next_x, next_y, next_z, ..) = location(i + 1);
+    let rate = move_rate as f32;
+    let (vel_x, vel_y, vel_z) = ((next_x - x) * rate, (next_y - y) * rate, (next_z - z) * rate);
+    Request::MoveSelfV2(Movement {
+        x, y, z, pitch, yaw, roll,
+        vel_x, vel_y, vel_z,
+        acc_x: 0., acc_y: 0., acc_z: 0.,
+        // walking or falling
+        movement_mode: if vel_z == 0. { 1 } else { 3 },
+        timestamp,
+    })
+}
+
+async fn send<S: SinkExt<Message> + Unpin>(wstx: &mut S, request: &Request) {
+    if wstx.send(Message::Text(serde_json::to_string(request).unwrap())).await.is_err() {
+        panic!("couldn't send {request:?}");
+    }
+}
+
+async fn read_responses<S>(room: usize, mut wsrx: S, stats: Arc<Stats>, events: mpsc::UnboundedSender<Event>)
+where S: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin
+{
+    // only players created by the load generator, there may be others in remote rooms
+    let mut players: HashMap<PlayerId, usize> = HashMap::new();
+    while let Some(Ok(msg)) = wsrx.next().await {
+        let now = Instant::now();
+        let response: Response = match msg {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            Message::Binary(_) | Message::Ping(_) | Message::Pong(_) => continue,
+            Message::Close(_) => break,
+        };
+        match response {
+            Response::PlayerJoinedRoom(id, name, ..) => {
+                if let Some(&client) = stats.client_indices.get(&name) {
+                    players.insert(id, client);
+                    let _ = events.send(Event::PlayerJoined);
                 }
-                break
             }
-            _ => (),
+            Response::MoveOtherV2(id, movement) => {
+                let sent = players.get(&id)
+                    .and_then(|&client| stats.move_sent_at[client].lock().unwrap().get(&movement.timestamp).copied());
+                if let Some(sent) = sent {
+                    stats.move_latencies.lock().unwrap().push(now - sent);
+                }
+            }
+            Response::StartNewGameAt(timestamp) => {
+                stats.new_game_received.lock().unwrap().push((room, timestamp, now, now_millis()));
+                let _ = events.send(Event::StartNewGameAt);
+            }
+            Response::RateLimitExceeded => *stats.rate_limit_exceeded.lock().unwrap() += 1,
+            Response::MoveOther(..) | Response::ServerTime(_) | Response::PlayerLeftRoom(_) | Response::PressPlatform(_)
+            | Response::PressButton(_) | Response::NewGamePressed(_) | Response::RoomNameTooLong
+            | Response::InvalidRequest | Response::ProtocolVersion(_) => (),
         }
     }
-    for req in REQUESTS.iter().cloned() {
-        thread::sleep(Duration::new(0, (1_000_000_000 / 60) as u32));
-        let msg = req;
-        client.send_message(&Message::text(serde_json::to_string(&msg).unwrap())).unwrap();
-        loop {
-            client.set_nonblocking(true).unwrap();
-            let res = client.recv_message();
-            client.set_nonblocking(false).unwrap();
-            match res {
-                Ok(OwnedMessage::Text(_)) => (),
-                Ok(OwnedMessage::Binary(_) | OwnedMessage::Ping(_) | OwnedMessage::Pong(_)) => continue,
-                Err(WebSocketError::IoError(io)) if io.kind() == ErrorKind::WouldBlock => break,
-                Ok(OwnedMessage::Close(_)) | Err(_) => break,
+    let _ = events.send(Event::Disconnected);
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+fn report(args: &Args, stats: &Stats) {
+    let moves_sent: usize = stats.moves_sent.iter().map(|sent| *sent.lock().unwrap()).sum();
+    println!();
+    if args.legacy_moves {
+        println!("Move fan-out latency ({moves_sent} sent): not measured for legacy moves");
+    } else {
+        let mut latencies = stats.move_latencies.lock().unwrap().clone();
+        let expected: usize = (0..args.clients)
+            .map(|i| *stats.moves_sent[i].lock().unwrap() * (args.room_size(args.room_of(i)) - 1))
+            .sum();
+        println!("Move fan-out latency ({moves_sent} sent, {}/{expected} received):", latencies.len());
+        print_percentiles(&mut latencies);
+    }
+
+    if !args.no_new_game {
+        let received = stats.new_game_received.lock().unwrap();
+        let mut skews = Vec::new();
+        let mut min_lead = None;
+        for room in 0..args.rooms {
+            let room_received: Vec<_> = received.iter().filter(|(r, ..)| *r == room).collect();
+            let (_, timestamp, ..) = match room_received.first() {
+                Some(first) => first,
+                None => {
+                    println!("Room {room}: no client got a StartNewGameAt");
+                    continue
+                },
+            };
+            if room_received.iter().any(|(_, ts, ..)| ts != timestamp) {
+                println!("Room {room}: clients got different StartNewGameAt timestamps");
             }
+            let first = room_received.iter().map(|&&(_, _, received, _)| received).min().unwrap();
+            let last = room_received.iter().map(|&&(_, _, received, _)| received).max().unwrap();
+            skews.push(last - first);
+            // time left until the start when the last client received the message
+            let lead = room_received.iter().map(|&&(_, ts, _, millis)| ts as i64 - millis as i64).min().unwrap();
+            min_lead = Some(min_lead.map_or(lead, |min: i64| min.min(lead)));
+        }
+        println!();
+        println!("StartNewGameAt skew across clients of a room ({} rooms):", args.rooms);
+        print_percentiles(&mut skews);
+        if let Some(min_lead) = min_lead {
+            println!("  minimum time left until the start: {min_lead}ms");
         }
     }
+
+    let rate_limit_exceeded = *stats.rate_limit_exceeded.lock().unwrap();
+    if rate_limit_exceeded > 0 {
+        println!();
+        println!("Warning: the rate limit was exceeded {rate_limit_exceeded} times, latencies are unreliable");
+    }
+}
+
+fn print_percentiles(durations: &mut [Duration]) {
+    if durations.is_empty() {
+        println!("  no samples");
+        return;
+    }
+    durations.sort_unstable();
+    let percentile = |p: f64| durations[((durations.len() - 1) as f64 * p / 100.).round() as usize];
+    for p in [50., 90., 99., 99.9] {
+        println!("  p{p:<5} {:>10.3}ms", percentile(p).as_secs_f64() * 1000.);
+    }
+    println!("  max    {:>10.3}ms", durations[durations.len() - 1].as_secs_f64() * 1000.);
 }
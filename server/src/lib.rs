@@ -0,0 +1,507 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use axum::extract::{Path, Query, WebSocketUpgrade};
+use axum::extract::ws::{Message, WebSocket};
+use axum::http::{header, StatusCode};
+use axum::Json;
+use axum::response::{Headers, Html, IntoResponse};
+use axum::Router;
+use axum::routing::get;
+use futures::{SinkExt, StreamExt};
+use futures::stream::SplitStream;
+use std::sync::{Mutex as StdMutex, RwLock as StdRwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Mutex as TokioMutex;
+use tokio::sync::mpsc::{self, Sender};
+use tokio::sync::oneshot;
+use protocol::{Movement, PlayerId, Request, Response, PROTOCOL_VERSION};
+use protocol::recording::{Event, RecordedPlayer, RecordingInfo};
+use rate_limit::TokenBucket;
+use recording::{RecordingStore, RoomRecorder};
+
+mod rate_limit;
+mod recording;
+mod validation;
+
+/// maximum size of a single websocket message, the largest valid request (`JoinRoom`) is far below this
+const MAX_MESSAGE_SIZE: usize = 16 * 1024;
+/// time to wait for the client to acknowledge a close initiated by the server
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct State {
+    multiplayer_rooms: HashMap<String, MultiplayerRoom>,
+    recordings: Option<Arc<RecordingStore>>,
+}
+
+#[derive(Clone, Default)]
+struct MultiplayerRoom {
+    players: Arc<StdRwLock<HashMap<PlayerId, Arc<Player>>>>,
+    name: String,
+    recorder: Option<Arc<RoomRecorder>>,
+}
+
+impl MultiplayerRoom {
+    async fn broadcast(&self, sender: Option<PlayerId>, message: Response) {
+        let players = self.players.read().unwrap();
+        for (id, player) in players.iter() {
+            if Some(*id) == sender {
+                continue;
+            }
+            player.send(message.clone());
+        }
+    }
+    /// Forward a movement to all players except the sender, as `MoveOtherV2` to players understanding it
+    /// and as the legacy `MoveOther` to all others.
+    async fn broadcast_movement(&self, sender: PlayerId, movement: Movement) {
+        let Movement { x, y, z, pitch, yaw, roll, .. } = movement;
+        let players = self.players.read().unwrap();
+        for (id, player) in players.iter() {
+            if *id == sender {
+                continue;
+            }
+            if *player.understands_movement.lock().unwrap() {
+                player.send(Response::MoveOtherV2(sender, movement));
+            } else {
+                player.send(Response::MoveOther(sender, x, y, z, pitch, yaw, roll));
+            }
+        }
+    }
+    /// check if all players pressed "New Game"
+    async fn check_new_game(&self) {
+        let players: Vec<_> = self.players.read().unwrap().values().cloned().collect();
+        if players.iter().all(|p| *p.is_waiting_for_new_game.lock().unwrap()) {
+            for player in players.iter() {
+                *player.is_waiting_for_new_game.lock().unwrap() = false;
+            }
+            let time = SystemTime::now();
+            let when_to_start = time + Duration::from_millis(2500);
+            let timestamp = when_to_start.duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64;
+            if let Some(recorder) = &self.recorder {
+                recorder.start_round(timestamp, players.iter().map(|p| p.recorded()).collect());
+            }
+            self.broadcast(None, Response::StartNewGameAt(timestamp)).await;
+        }
+    }
+    fn record(&self, event: Event) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(event);
+        }
+    }
+}
+
+struct Player {
+    id: PlayerId,
+    data: StdMutex<PlayerData>,
+    sender: Sender<Response>,
+    is_waiting_for_new_game: StdMutex<bool>,
+    /// set if the client announced protocol version 2 or sent a `MoveSelfV2`, old clients only understand `MoveOther`
+    understands_movement: StdMutex<bool>,
+}
+struct PlayerData {
+    name: String,
+    red: f32,
+    green: f32,
+    blue: f32,
+    x: f32,
+    y: f32,
+    z: f32,
+    pitch: f32,
+    yaw: f32,
+    roll: f32,
+}
+impl Player {
+    fn send(&self, message: Response) {
+        let _ = self.sender.try_send(message);
+    }
+    fn recorded(&self) -> RecordedPlayer {
+        let data = self.data.lock().unwrap();
+        RecordedPlayer {
+            id: self.id,
+            name: data.name.clone(),
+            red: data.red,
+            green: data.green,
+            blue: data.blue,
+            x: data.x,
+            y: data.y,
+            z: data.z,
+            pitch: data.pitch,
+            yaw: data.yaw,
+            roll: data.roll,
+        }
+    }
+}
+
+/// Create the router serving the room overview on `/`, the multiplayer websocket on `/ws`
+/// and recorded rounds on `/recordings`.
+///
+/// If `recordings_dir` is given, every round of every room is recorded into that directory.
+pub fn router(recordings_dir: Option<PathBuf>) -> Router {
+    let recordings = recordings_dir.map(|dir| {
+        let store = RecordingStore::open(dir.clone())
+            .unwrap_or_else(|e| panic!("can't open recordings directory {dir:?}: {e}"));
+        Arc::new(store)
+    });
+    let state = Arc::new(StdMutex::new(State {
+        multiplayer_rooms: HashMap::new(),
+        recordings,
+    }));
+
+    Router::new()
+        .route("/", get({
+            let state = Arc::clone(&state);
+            move || hello_world(state)
+        })).route("/ws", get({
+            let state = Arc::clone(&state);
+            move |ws, query| handle_socket_upgrade(ws, query, state)
+        })).route("/recordings", get({
+            let state = Arc::clone(&state);
+            move || list_recordings(state)
+        })).route("/recordings/:id", get({
+            let state = Arc::clone(&state);
+            move |id| get_recording(id, state)
+        }))
+}
+
+async fn hello_world(state: Arc<StdMutex<State>>) -> Html<String> {
+    let mut res = "<html><body>Rooms:<ul>".to_string();
+    let rooms: Vec<_> = state.lock().unwrap().multiplayer_rooms.values().cloned().collect();
+    for room in rooms {
+        let players = room.players.read().unwrap();
+        res += &format!("<li>{} ({}):<ul>", room.name, players.len());
+        for player in players.values() {
+            let data = player.data.lock().unwrap();
+            let is_waiting_for_new_game = *player.is_waiting_for_new_game.lock().unwrap();
+            res += &format!("<li>{}({}): is_waiting_for_new_game: {}, location: x={} y={} z={}", data.name, player.id.id(), is_waiting_for_new_game, data.x, data.y, data.z);
+        }
+        res += "</ul></li>";
+    }
+    res += "</ul></body></html>";
+    Html(res)
+}
+
+async fn list_recordings(state: Arc<StdMutex<State>>) -> Result<Json<Vec<RecordingInfo>>, StatusCode> {
+    let recordings = state.lock().unwrap().recordings.clone().ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(recordings.list()))
+}
+
+async fn get_recording(Path(id): Path<String>, state: Arc<StdMutex<State>>) -> Result<impl IntoResponse, StatusCode> {
+    let recordings = state.lock().unwrap().recordings.clone().ok_or(StatusCode::NOT_FOUND)?;
+    let path = recordings.path(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let content = tokio::fs::read(path).await.map_err(|e| {
+        log::error!("Couldn't read recording {id}: {e}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((Headers([(header::CONTENT_TYPE, "application/json")]), content))
+}
+
+async fn handle_socket_upgrade(ws: WebSocketUpgrade, Query(query): Query<HashMap<String, String>>, state: Arc<StdMutex<State>>) -> impl IntoResponse {
+    // old clients don't announce their protocol version
+    let version = query.get("version").and_then(|version| version.parse::<u32>().ok())
+        .map(|version| version.min(PROTOCOL_VERSION));
+    ws.max_message_size(MAX_MESSAGE_SIZE)
+        .max_frame_size(MAX_MESSAGE_SIZE)
+        .on_upgrade(move |socket| async move { handle_socket(socket, version, state).await })
+}
+
+/// `version` is the negotiated protocol version if the client announced its own.
+async fn handle_socket(socket: WebSocket, version: Option<u32>, state: Arc<StdMutex<State>>) {
+    let (mut wstx, mut wsrx) = socket.split();
+
+    // spawn writing task
+    let (sender, mut receiver) = mpsc::channel(1000);
+    let (close_sender, mut close_receiver) = oneshot::channel();
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                // send everything queued before closing the connection
+                biased;
+                msg = receiver.recv() => match msg {
+                    Some(msg) => {
+                        let _ = wstx.send(Message::Text(serde_json::to_string(&msg).unwrap())).await;
+                    }
+                    None => break,
+                },
+                _ = &mut close_receiver => {
+                    let _ = wstx.send(Message::Close(None)).await;
+                    break
+                },
+            }
+        }
+    });
+
+    let local_sender = sender.clone();
+    let mut sender = Some(sender);
+    if let Some(version) = version {
+        let _ = local_sender.send(Response::ProtocolVersion(version)).await;
+    }
+
+    let player_id = PlayerId::next();
+    log::info!("Player connected: {:?}", player_id);
+    let multiplayer_room: TokioMutex<Option<MultiplayerRoom>> = TokioMutex::new(None);
+
+    let remove_from_current_room = || async {
+        if let Some(room) = multiplayer_room.lock().await.take() {
+            log::debug!("Removed {player_id:?} from room {:?}", room.name);
+            let player = room.players.write().unwrap().remove(&player_id);
+
+            if player.is_some() {
+                room.record(Event::PlayerLeft(player_id));
+                room.broadcast(Some(player_id), Response::PlayerLeftRoom(player_id)).await;
+            }
+            if room.players.read().unwrap().is_empty() {
+                if let Some(recorder) = &room.recorder {
+                    recorder.finish_round();
+                }
+            } else {
+                room.check_new_game().await;
+            }
+
+            player
+        } else {
+            None
+        }
+    };
+    let disconnect = || async {
+        remove_from_current_room().await;
+        log::info!("Player Disconnected: {player_id:?}");
+    };
+
+    let mut token_bucket = TokenBucket::new(rate_limit::BURST, rate_limit::REQUESTS_PER_SECOND);
+    let mut violations = 0;
+    // the invalid request which doesn't get a token anymore disconnects the client
+    let mut invalid_requests = TokenBucket::new(f64::from(validation::MAX_INVALID_REQUESTS - 1), validation::INVALID_REQUESTS_FORGIVEN_PER_SECOND);
+
+    while let Some(msg) = wsrx.next().await {
+        let msg = match msg {
+            Ok(Message::Close(_)) | Err(_) => {
+                disconnect().await;
+                return
+            },
+            Ok(Message::Binary(_)) => {
+                log::warn!("Got binary from player {player_id:?}, disconnecting...");
+                disconnect().await;
+                close(close_sender, &mut wsrx).await;
+                return
+            }
+            Ok(Message::Ping(_)) | Ok(Message::Pong(_)) => continue,
+            Ok(Message::Text(text)) => text,
+        };
+
+        let now = Instant::now();
+        if token_bucket.is_full(now) {
+            violations = 0;
+        }
+        if !token_bucket.try_take(now) {
+            violations += 1;
+            if violations >= rate_limit::MAX_VIOLATIONS {
+                log::warn!("Player {player_id:?} exceeded the rate limit {violations} times, disconnecting...");
+                disconnect().await;
+                close(close_sender, &mut wsrx).await;
+                return
+            }
+            log::warn!("Player {player_id:?} exceeded the rate limit, dropping request");
+            let _ = local_sender.send(Response::RateLimitExceeded).await;
+            continue
+        }
+
+        let request = match serde_json::from_str(&msg) {
+            Ok(request) => request,
+            Err(e) => {
+                log::warn!("Got invalid JSON from player {player_id:?} ({e:?}), disconnecting...");
+                disconnect().await;
+                close(close_sender, &mut wsrx).await;
+                return
+            }
+        };
+
+        if let Err(e) = validation::validate(&request) {
+            if !invalid_requests.try_take(now) {
+                log::warn!("Got invalid request from player {player_id:?} ({e}), disconnecting after {} invalid requests...", validation::MAX_INVALID_REQUESTS);
+                disconnect().await;
+                close(close_sender, &mut wsrx).await;
+                return
+            }
+            log::warn!("Got invalid request from player {player_id:?} ({e}), dropping request");
+            let _ = local_sender.send(Response::InvalidRequest).await;
+            continue
+        }
+
+        match request {
+            Request::GetServerTime => {
+                let _ = local_sender.send(Response::ServerTime(SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64)).await;
+            },
+            Request::JoinRoom(room_name, player_name, red, green, blue, x, y, z, pitch, yaw, roll) => {
+                if room_name.len() > validation::MAX_ROOM_NAME_LEN {
+                    log::warn!("Player {player_id:?} ({player_name}) tried to join room {room_name:?}, but room name is greater than {} chars.", validation::MAX_ROOM_NAME_LEN);
+                    let _ = local_sender.send(Response::RoomNameTooLong).await;
+                    continue
+                }
+                log::info!("Player {player_id:?} ({player_name}) joins room {room_name:?}");
+
+                let player = match remove_from_current_room().await {
+                    Some(player) => {
+                        {
+                            let mut data = player.data.lock().unwrap();
+                            data.red = red;
+                            data.green = green;
+                            data.blue = blue;
+                            data.x = x;
+                            data.y = y;
+                            data.z = z;
+                            data.pitch = pitch;
+                            data.yaw = yaw;
+                            data.roll = roll;
+                            data.name = player_name.clone();
+                        }
+                        player
+                    },
+                    None => Arc::new(Player {
+                        id: player_id,
+                        is_waiting_for_new_game: StdMutex::new(false),
+                        understands_movement: StdMutex::new(version >= Some(2)),
+                        data: StdMutex::new(PlayerData { name: player_name.clone(), red, green, blue, x, y, z, pitch, yaw, roll }),
+                        sender: sender.take().unwrap()
+                    }),
+                };
+                let room = {
+                    let mut state = state.lock().unwrap();
+                    let State { multiplayer_rooms, recordings } = &mut *state;
+                    multiplayer_rooms.entry(room_name)
+                        .or_insert_with_key(|key| MultiplayerRoom {
+                            players: Default::default(),
+                            name: key.clone(),
+                            recorder: recordings.as_ref().map(|store| Arc::new(RoomRecorder::new(Arc::clone(store), key.clone()))),
+                        }).clone()
+                };
+
+                {
+                    let players = room.players.read().unwrap();
+                    for (id, other_player) in &*players {
+                        let (red, green, blue, x, y, z, pitch, yaw, roll, is_waiting_for_new_game, name) = {
+                            let data = other_player.data.lock().unwrap();
+                            other_player.send(Response::PlayerJoinedRoom(player_id, player_name.clone(), red, green, blue, x, y, z, pitch, yaw, roll));
+                            (data.red, data.green, data.blue, data.x, data.y, data.z, data.pitch, data.yaw, data.roll, *other_player.is_waiting_for_new_game.lock().unwrap(), data.name.clone())
+                        };
+                        player.send(Response::PlayerJoinedRoom(*id, name, red, green, blue, x, y, z, pitch, yaw, roll));
+                        if is_waiting_for_new_game {
+                            player.send(Response::NewGamePressed(*id));
+                        }
+                    }
+                }
+
+                room.record(Event::PlayerJoined(player.recorded()));
+                room.players.write().unwrap().insert(player_id, player);
+                *multiplayer_room.lock().await = Some(room);
+            }
+            Request::MoveSelf(x, y, z, pitch, yaw, roll) => {
+                move_self(&multiplayer_room, player_id, Movement::from_location(x, y, z, pitch, yaw, roll), false).await;
+            }
+            Request::MoveSelfV2(movement) => {
+                move_self(&multiplayer_room, player_id, movement, true).await;
+            }
+            Request::PressPlatform(id) => {
+                let lock = multiplayer_room.lock().await;
+                let room = match lock.as_ref() {
+                    Some(name) => name,
+                    None => {
+                        log::warn!("Player {player_id:?} tried to press platform {id} without being in a room");
+                        continue
+                    }
+                };
+                room.record(Event::PressPlatform(player_id, id));
+                room.broadcast(Some(player_id), Response::PressPlatform(id)).await;
+            }
+            Request::PressButton(id) => {
+                let lock = multiplayer_room.lock().await;
+                let room = match lock.as_ref() {
+                    Some(name) => name,
+                    None => {
+                        log::warn!("Player {player_id:?} tried to press button {id} without being in a room");
+                        continue
+                    }
+                };
+                room.record(Event::PressButton(player_id, id));
+                room.broadcast(Some(player_id), Response::PressButton(id)).await;
+            }
+            Request::NewGamePressed => {
+                log::info!("Player {player_id:?} pressed New Game");
+                let lock = multiplayer_room.lock().await;
+                let room = match lock.as_ref() {
+                    Some(name) => name,
+                    None => {
+                        log::warn!("Player {player_id:?} tried to press new game while not in a room");
+                        continue
+                    }
+                };
+                let player = room.players.read().unwrap().get(&player_id).cloned();
+                match player {
+                    Some(player) => {
+                        *player.is_waiting_for_new_game.lock().unwrap() = true;
+                    },
+                    None => {
+                        log::error!("Player {player_id:?} pressed new game but isn't in room {:?}", room.name);
+                        continue
+                    }
+                };
+
+                room.record(Event::NewGamePressed(player_id));
+                room.broadcast(Some(player_id), Response::NewGamePressed(player_id)).await;
+                room.check_new_game().await;
+            }
+        }
+    }
+}
+
+/// Close the connection from the server side after all queued responses were sent.
+///
+/// Incoming messages are discarded until the client acknowledges the close. Dropping the socket
+/// with unread data would reset the connection, possibly before the client read our last responses.
+async fn close(close_sender: oneshot::Sender<()>, wsrx: &mut SplitStream<WebSocket>) {
+    let _ = close_sender.send(());
+    let _ = tokio::time::timeout(CLOSE_TIMEOUT, async {
+        while let Some(Ok(msg)) = wsrx.next().await {
+            if let Message::Close(_) = msg {
+                break
+            }
+        }
+    }).await;
+}
+
+/// Update the location of the player and forward the movement to all other players of its room.
+///
+/// `is_v2` is set if the client sent a `MoveSelfV2`, which means that it also understands `MoveOtherV2`.
+async fn move_self(multiplayer_room: &TokioMutex<Option<MultiplayerRoom>>, player_id: PlayerId, movement: Movement, is_v2: bool) {
+    let Movement { x, y, z, pitch, yaw, roll, .. } = movement;
+    let lock = multiplayer_room.lock().await;
+    let room = match lock.as_ref() {
+        Some(name) => name,
+        None => {
+            log::warn!("Player {player_id:?} tried to move without being in a room to {x} {y} {z}");
+            return
+        }
+    };
+    // update player's location
+    let player = room.players.read().unwrap().get(&player_id).cloned();
+    match player {
+        Some(player) => {
+            let mut data = player.data.lock().unwrap();
+            data.x = x;
+            data.y = y;
+            data.z = z;
+            data.pitch = pitch;
+            data.yaw = yaw;
+            data.roll = roll;
+            if is_v2 {
+                *player.understands_movement.lock().unwrap() = true;
+            }
+        },
+        None => {
+            log::error!("Player {player_id:?} tried to update its location without being in room {:?}", room.name);
+            return
+        }
+    };
+
+    room.record(Event::Move(player_id, x, y, z, pitch, yaw, roll));
+    room.broadcast_movement(player_id, movement).await;
+}
@@ -0,0 +1,51 @@
+use std::time::Instant;
+
+/// Maximum number of requests a client can send in a burst.
+pub const BURST: f64 = 150.;
+/// Number of requests per second a client can send sustainedly.
+/// The client sends ~30 `MoveSelf` per second plus some button presses.
+pub const REQUESTS_PER_SECOND: f64 = 60.;
+/// Number of times the rate limit may be exceeded before a client is disconnected.
+/// Violations are forgiven once the client's token bucket is full again.
+pub const MAX_VIOLATIONS: u32 = 5;
+
+/// Token bucket of a single connection.
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_second: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: f64, refill_per_second: f64) -> TokenBucket {
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            refill_per_second,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Take a token if there is one, returns `false` if the rate limit is exceeded.
+    pub fn try_take(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        if self.tokens >= 1. {
+            self.tokens -= 1.;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_full(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.tokens >= self.capacity
+    }
+}
@@ -0,0 +1,135 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::SystemTime;
+use protocol::recording::{Event, RecordedEvent, RecordedPlayer, Recording, RecordingInfo};
+
+/// Rounds with more events are cut off to bound the memory usage of long-running rooms.
+const MAX_EVENTS_PER_ROUND: usize = 1_000_000;
+
+// distinguishes rounds of different rooms starting at the same time
+static RECORDING_SERIAL: AtomicU32 = AtomicU32::new(0);
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+/// On-disk store of finished recordings, one JSON file per round.
+pub struct RecordingStore {
+    dir: PathBuf,
+    index: StdMutex<Vec<RecordingInfo>>,
+}
+
+impl RecordingStore {
+    /// Open the store in `dir`, creating the directory if needed and indexing all existing recordings.
+    pub fn open(dir: PathBuf) -> io::Result<RecordingStore> {
+        std::fs::create_dir_all(&dir)?;
+        let mut index = Vec::new();
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if !matches!(path.extension(), Some(ext) if ext == "json") {
+                continue;
+            }
+            let recording: Recording = match std::fs::read(&path).map_err(|e| e.to_string())
+                .and_then(|content| serde_json::from_slice(&content).map_err(|e| e.to_string()))
+            {
+                Ok(recording) => recording,
+                Err(e) => {
+                    log::warn!("Skipping invalid recording {path:?}: {e}");
+                    continue
+                }
+            };
+            index.push(recording.info);
+        }
+        index.sort_by_key(|info| info.start);
+        log::info!("Found {} recordings in {dir:?}", index.len());
+        Ok(RecordingStore { dir, index: StdMutex::new(index) })
+    }
+
+    pub fn list(&self) -> Vec<RecordingInfo> {
+        self.index.lock().unwrap().clone()
+    }
+
+    /// Path of the recording with the given id, if it exists.
+    pub fn path(&self, id: &str) -> Option<PathBuf> {
+        let exists = self.index.lock().unwrap().iter().any(|info| info.id == id);
+        // ids are generated by us and only consist of digits and `-`, so they are safe to use as filename
+        exists.then(|| self.dir.join(format!("{id}.json")))
+    }
+
+    fn save(&self, recording: Recording) {
+        let path = self.dir.join(format!("{}.json", recording.info.id));
+        let content = serde_json::to_vec(&recording).unwrap();
+        match std::fs::write(&path, content) {
+            Ok(()) => {
+                log::info!("Saved recording {} of room {:?} with {} events", recording.info.id, recording.info.room, recording.events.len());
+                self.index.lock().unwrap().push(recording.info);
+            }
+            Err(e) => log::error!("Couldn't save recording to {path:?}: {e}"),
+        }
+    }
+}
+
+/// Records the rounds of a single room.
+pub struct RoomRecorder {
+    store: Arc<RecordingStore>,
+    room: String,
+    current: StdMutex<Option<Recording>>,
+}
+
+impl RoomRecorder {
+    pub fn new(store: Arc<RecordingStore>, room: String) -> RoomRecorder {
+        RoomRecorder { store, room, current: StdMutex::new(None) }
+    }
+
+    /// Record an event of the current round. Events outside of a round are dropped.
+    pub fn record(&self, event: Event) {
+        let mut current = self.current.lock().unwrap();
+        let recording = match current.as_mut() {
+            Some(recording) => recording,
+            None => return,
+        };
+        if let Event::PlayerJoined(player) = &event {
+            recording.info.players.push(player.name.clone());
+        }
+        let timestamp = now_millis();
+        recording.info.end = timestamp;
+        recording.events.push(RecordedEvent { timestamp, event });
+        if recording.events.len() >= MAX_EVENTS_PER_ROUND {
+            log::warn!("Round of room {:?} exceeded {MAX_EVENTS_PER_ROUND} events, stopping its recording", self.room);
+            let recording = current.take().unwrap();
+            drop(current);
+            self.save(recording);
+        }
+    }
+
+    /// Finish the current round and start a new one at `start` with the given players.
+    pub fn start_round(&self, start: u64, players: Vec<RecordedPlayer>) {
+        self.finish_round();
+        let info = RecordingInfo {
+            id: format!("{start}-{}", RECORDING_SERIAL.fetch_add(1, Ordering::SeqCst)),
+            room: self.room.clone(),
+            start,
+            end: start,
+            players: players.iter().map(|player| player.name.clone()).collect(),
+        };
+        *self.current.lock().unwrap() = Some(Recording { info, players, events: Vec::new() });
+    }
+
+    /// Finish the current round, storing it if anything happened.
+    pub fn finish_round(&self) {
+        let recording = self.current.lock().unwrap().take();
+        if let Some(recording) = recording {
+            self.save(recording);
+        }
+    }
+
+    fn save(&self, recording: Recording) {
+        if recording.events.is_empty() {
+            return;
+        }
+        let store = Arc::clone(&self.store);
+        tokio::task::spawn_blocking(move || store.save(recording));
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::{Display, Formatter};
+use protocol::{Movement, Request};
+
+pub const MAX_ROOM_NAME_LEN: usize = 128;
+pub const MAX_PLAYER_NAME_LEN: usize = 64;
+/// number of platforms in all levels, platform-ids are `0..NUM_PLATFORMS`
+///
+/// The ids are indices into `PLATFORMS` in `tool/teleport.re`, which the tests check against.
+pub const NUM_PLATFORMS: u8 = 251;
+/// number of buttons in all levels, button-ids are `0..NUM_BUTTONS`
+///
+/// The ids are indices into `BUTTONS` in `tool/teleport.re`, which the tests check against.
+pub const NUM_BUTTONS: u8 = 37;
+/// Number of invalid requests after which a client is disconnected.
+pub const MAX_INVALID_REQUESTS: u32 = 5;
+/// Number of invalid requests forgiven per second, like rate limit violations are forgiven over time.
+pub const INVALID_REQUESTS_FORGIVEN_PER_SECOND: f64 = 1. / 60.;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidRequest {
+    NonFiniteFloat(&'static str, f32),
+    PlayerNameTooLong(usize),
+    ColorOutOfRange(&'static str, f32),
+    InvalidPlatform(u8),
+    InvalidButton(u8),
+    InvalidMovementMode(u8),
+}
+
+impl Display for InvalidRequest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidRequest::NonFiniteFloat(name, val) => write!(f, "{name} is not finite ({val})"),
+            InvalidRequest::PlayerNameTooLong(len) => write!(f, "player name has {len} bytes, but at most {MAX_PLAYER_NAME_LEN} are allowed"),
+            InvalidRequest::ColorOutOfRange(name, val) => write!(f, "{name} must be within 0.0 and 1.0, but is {val}"),
+            InvalidRequest::InvalidPlatform(id) => write!(f, "platform {id} doesn't exist (there are {NUM_PLATFORMS} platforms)"),
+            InvalidRequest::InvalidButton(id) => write!(f, "button {id} doesn't exist (there are {NUM_BUTTONS} buttons)"),
+            InvalidRequest::InvalidMovementMode(mode) => write!(f, "movement mode {mode} doesn't exist (must be below {})", Movement::MOVEMENT_MODE_MAX),
+        }
+    }
+}
+
+/// Check all fields of a request for values a well-behaved client never sends.
+///
+/// The length of the room name isn't checked here, as it has its own response (`RoomNameTooLong`).
+pub fn validate(request: &Request) -> Result<(), InvalidRequest> {
+    match *request {
+        Request::GetServerTime => Ok(()),
+        Request::JoinRoom(_, ref player_name, red, green, blue, x, y, z, pitch, yaw, roll) => {
+            if player_name.len() > MAX_PLAYER_NAME_LEN {
+                return Err(InvalidRequest::PlayerNameTooLong(player_name.len()));
+            }
+            check_color(red, green, blue)?;
+            check_location(x, y, z, pitch, yaw, roll)
+        }
+        Request::MoveSelf(x, y, z, pitch, yaw, roll) => check_location(x, y, z, pitch, yaw, roll),
+        Request::MoveSelfV2(ref movement) => check_movement(movement),
+        Request::PressPlatform(id) if id >= NUM_PLATFORMS => Err(InvalidRequest::InvalidPlatform(id)),
+        Request::PressPlatform(_) => Ok(()),
+        Request::PressButton(id) if id >= NUM_BUTTONS => Err(InvalidRequest::InvalidButton(id)),
+        Request::PressButton(_) => Ok(()),
+        Request::NewGamePressed => Ok(()),
+    }
+}
+
+fn check_finite(name: &'static str, val: f32) -> Result<(), InvalidRequest> {
+    if val.is_finite() {
+        Ok(())
+    } else {
+        Err(InvalidRequest::NonFiniteFloat(name, val))
+    }
+}
+
+fn check_color(red: f32, green: f32, blue: f32) -> Result<(), InvalidRequest> {
+    for (name, val) in [("red", red), ("green", green), ("blue", blue)] {
+        // also catches NaN
+        if !(0.0..=1.0).contains(&val) {
+            return Err(InvalidRequest::ColorOutOfRange(name, val));
+        }
+    }
+    Ok(())
+}
+
+fn check_location(x: f32, y: f32, z: f32, pitch: f32, yaw: f32, roll: f32) -> Result<(), InvalidRequest> {
+    check_finite("x", x)?;
+    check_finite("y", y)?;
+    check_finite("z", z)?;
+    check_finite("pitch", pitch)?;
+    check_finite("yaw", yaw)?;
+    check_finite("roll", roll)
+}
+
+fn check_movement(movement: &Movement) -> Result<(), InvalidRequest> {
+    let Movement { x, y, z, pitch, yaw, roll, vel_x, vel_y, vel_z, acc_x, acc_y, acc_z, movement_mode, timestamp: _ } = *movement;
+    check_location(x, y, z, pitch, yaw, roll)?;
+    check_finite("vel_x", vel_x)?;
+    check_finite("vel_y", vel_y)?;
+    check_finite("vel_z", vel_z)?;
+    check_finite("acc_x", acc_x)?;
+    check_finite("acc_y", acc_y)?;
+    check_finite("acc_z", acc_z)?;
+    if movement_mode >= Movement::MOVEMENT_MODE_MAX {
+        return Err(InvalidRequest::InvalidMovementMode(movement_mode));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn movement() -> Movement {
+        Movement {
+            x: 0., y: 0., z: 0., pitch: 0., yaw: 0., roll: 0.,
+            vel_x: 0., vel_y: 0., vel_z: 0., acc_x: 0., acc_y: 0., acc_z: 0.,
+            movement_mode: 0, timestamp: 0,
+        }
+    }
+
+    /// number of entries of a `static NAME = List::of(...)` in `tool/teleport.re`
+    fn count_list_entries(name: &str, entry: &str) -> usize {
+        let teleport = include_str!("../../tool/teleport.re");
+        let start = teleport.find(&format!("static {name} = List::of(")).unwrap();
+        let end = start + teleport[start..].find("\n);").unwrap();
+        teleport[start..end].lines().filter(|line| line.trim_start().starts_with(entry)).count()
+    }
+
+    #[test]
+    fn ids_match_the_game_data() {
+        assert_eq!(count_list_entries("PLATFORMS", "Platform {"), usize::from(NUM_PLATFORMS));
+        assert_eq!(count_list_entries("BUTTONS", "Button {"), usize::from(NUM_BUTTONS));
+    }
+
+    #[test]
+    fn boundary_ids() {
+        assert_eq!(validate(&Request::PressPlatform(0)), Ok(()));
+        assert_eq!(validate(&Request::PressPlatform(NUM_PLATFORMS - 1)), Ok(()));
+        assert_eq!(validate(&Request::PressPlatform(NUM_PLATFORMS)), Err(InvalidRequest::InvalidPlatform(NUM_PLATFORMS)));
+        assert_eq!(validate(&Request::PressPlatform(u8::MAX)), Err(InvalidRequest::InvalidPlatform(u8::MAX)));
+        assert_eq!(validate(&Request::PressButton(0)), Ok(()));
+        assert_eq!(validate(&Request::PressButton(NUM_BUTTONS - 1)), Ok(()));
+        assert_eq!(validate(&Request::PressButton(NUM_BUTTONS)), Err(InvalidRequest::InvalidButton(NUM_BUTTONS)));
+        let mode = Movement::MOVEMENT_MODE_MAX;
+        assert_eq!(validate(&Request::MoveSelfV2(Movement { movement_mode: mode - 1, ..movement() })), Ok(()));
+        assert_eq!(validate(&Request::MoveSelfV2(Movement { movement_mode: mode, ..movement() })), Err(InvalidRequest::InvalidMovementMode(mode)));
+    }
+
+    #[test]
+    fn non_finite_floats() {
+        assert_eq!(validate(&Request::MoveSelf(0., 0., 0., 0., 0., 0.)), Ok(()));
+        assert!(matches!(validate(&Request::MoveSelf(f32::NAN, 0., 0., 0., 0., 0.)), Err(InvalidRequest::NonFiniteFloat("x", _))));
+        assert_eq!(validate(&Request::MoveSelf(0., 0., 0., 0., 0., f32::INFINITY)), Err(InvalidRequest::NonFiniteFloat("roll", f32::INFINITY)));
+        assert_eq!(validate(&Request::MoveSelfV2(Movement { vel_z: f32::NEG_INFINITY, ..movement() })), Err(InvalidRequest::NonFiniteFloat("vel_z", f32::NEG_INFINITY)));
+        assert!(matches!(validate(&Request::MoveSelfV2(Movement { acc_x: f32::NAN, ..movement() })), Err(InvalidRequest::NonFiniteFloat("acc_x", _))));
+        let join = |red, z| Request::JoinRoom("room".to_string(), "player".to_string(), red, 0., 1., 0., 0., z, 0., 0., 0.);
+        assert_eq!(validate(&join(1., 0.)), Ok(()));
+        assert!(matches!(validate(&join(f32::NAN, 0.)), Err(InvalidRequest::ColorOutOfRange("red", _))));
+        assert_eq!(validate(&join(1.5, 0.)), Err(InvalidRequest::ColorOutOfRange("red", 1.5)));
+        assert_eq!(validate(&join(0., f32::INFINITY)), Err(InvalidRequest::NonFiniteFloat("z", f32::INFINITY)));
+    }
+
+    #[test]
+    fn player_name_length() {
+        let join = |len| Request::JoinRoom("room".to_string(), "a".repeat(len), 0., 0., 0., 0., 0., 0., 0., 0., 0.);
+        assert_eq!(validate(&join(MAX_PLAYER_NAME_LEN)), Ok(()));
+        assert_eq!(validate(&join(MAX_PLAYER_NAME_LEN + 1)), Err(InvalidRequest::PlayerNameTooLong(MAX_PLAYER_NAME_LEN + 1)));
+    }
+}
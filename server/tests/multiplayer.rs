@@ -0,0 +1,544 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
+use protocol::{Movement, PlayerId, Request, Response, PROTOCOL_VERSION};
+use protocol::recording::{Event, Recording, RecordingInfo};
+
+/// Time to wait for a response before considering it missing.
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Time to wait for unexpected responses before considering a client silent.
+const SILENCE_TIMEOUT: Duration = Duration::from_millis(200);
+/// Delay between all players pressing New Game and the synchronized start, as used by the server.
+const NEW_GAME_DELAY_MS: u64 = 2500;
+
+/// Start the server on an ephemeral port, returning the address to connect to.
+async fn start_server() -> SocketAddr {
+    start_server_with_recordings(None).await
+}
+
+async fn start_server_with_recordings(recordings_dir: Option<PathBuf>) -> SocketAddr {
+    let server = axum::Server::bind(&"127.0.0.1:0".parse().unwrap())
+        .serve(server::router(recordings_dir).into_make_service());
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64
+}
+
+struct Client {
+    name: &'static str,
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl Client {
+    async fn connect(addr: SocketAddr, name: &'static str) -> Client {
+        Client::connect_to(format!("ws://{addr}/ws"), name).await
+    }
+
+    /// Connect announcing the protocol version `version`.
+    async fn connect_with_version(addr: SocketAddr, name: &'static str, version: u32) -> Client {
+        Client::connect_to(format!("ws://{addr}/ws?version={version}"), name).await
+    }
+
+    async fn connect_to(url: String, name: &'static str) -> Client {
+        let (ws, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+        Client { name, ws }
+    }
+
+    async fn send(&mut self, request: Request) {
+        self.ws.send(Message::Text(serde_json::to_string(&request).unwrap())).await.unwrap();
+    }
+
+    /// Receive the next response, or `None` if the connection was closed.
+    async fn try_recv(&mut self, timeout: Duration) -> Option<Response> {
+        loop {
+            let msg = match tokio::time::timeout(timeout, self.ws.next()).await {
+                Ok(Some(Ok(msg))) => msg,
+                Ok(Some(Err(_)) | None) => return None,
+                Err(_) => panic!("{}: timed out waiting for a response", self.name),
+            };
+            match msg {
+                Message::Text(text) => return Some(serde_json::from_str(&text).unwrap()),
+                Message::Close(_) => return None,
+                Message::Binary(_) | Message::Ping(_) | Message::Pong(_) => continue,
+            }
+        }
+    }
+
+    async fn recv(&mut self) -> Response {
+        match self.try_recv(RECEIVE_TIMEOUT).await {
+            Some(response) => response,
+            None => panic!("{}: connection closed while waiting for a response", self.name),
+        }
+    }
+
+    async fn expect(&mut self, expected: &[Response]) {
+        for expected in expected {
+            let response = self.recv().await;
+            assert_eq!(&response, expected, "{}: unexpected response", self.name);
+        }
+    }
+
+    async fn expect_silence(&mut self) {
+        if let Ok(msg) = tokio::time::timeout(SILENCE_TIMEOUT, self.ws.next()).await {
+            panic!("{}: expected no response, got {msg:?}", self.name);
+        }
+    }
+
+    async fn expect_closed(&mut self) {
+        loop {
+            match self.try_recv(RECEIVE_TIMEOUT).await {
+                None => return,
+                // warnings are sent before the disconnect
+                Some(Response::RateLimitExceeded | Response::InvalidRequest) => continue,
+                Some(response) => panic!("{}: expected the connection to be closed, got {response:?}", self.name),
+            }
+        }
+    }
+
+    async fn join(&mut self, room: &str, location: (f32, f32, f32)) {
+        let (x, y, z) = location;
+        self.send(Request::JoinRoom(room.to_string(), self.name.to_string(), 1., 0., 0., x, y, z, 0., 90., 0.)).await;
+    }
+
+    /// Receive a `PlayerJoinedRoom` of the given player and return its id.
+    async fn expect_joined(&mut self, name: &str, location: (f32, f32, f32)) -> PlayerId {
+        let (x, y, z) = location;
+        match self.recv().await {
+            Response::PlayerJoinedRoom(id, n, 1., 0., 0., px, py, pz, 0., 90., 0.) if n == name && (px, py, pz) == (x, y, z) => id,
+            response => panic!("{}: expected {name} to join at {location:?}, got {response:?}", self.name),
+        }
+    }
+
+    /// Receive a `StartNewGameAt` and check that it's the expected time in the future.
+    async fn expect_start_new_game(&mut self) -> u64 {
+        let before = now_millis();
+        let timestamp = match self.recv().await {
+            Response::StartNewGameAt(timestamp) => timestamp,
+            response => panic!("{}: expected StartNewGameAt, got {response:?}", self.name),
+        };
+        let after = now_millis();
+        assert!(
+            before.saturating_sub(1000) + NEW_GAME_DELAY_MS <= timestamp && timestamp <= after + NEW_GAME_DELAY_MS,
+            "{}: new game starts at {timestamp}, expected ~{NEW_GAME_DELAY_MS}ms after {before}..{after}", self.name,
+        );
+        timestamp
+    }
+}
+
+/// Connect `names.len()` clients, let them join `room` one after another and return them with their ids.
+///
+/// Player `i` joins at location `(i, 0, 0)`.
+async fn join_all(addr: SocketAddr, room: &str, names: &[&'static str]) -> (Vec<Client>, Vec<PlayerId>) {
+    let mut clients: Vec<Client> = Vec::new();
+    // the id of a player is only known once another player was told about it
+    let mut ids: Vec<Option<PlayerId>> = Vec::new();
+    for (i, &name) in names.iter().enumerate() {
+        let location = (i as f32, 0., 0.);
+        let mut client = Client::connect(addr, name).await;
+        client.join(room, location).await;
+        // the new player is announced to everyone already in the room
+        let mut id = None;
+        for other in &mut clients {
+            let joined_id = other.expect_joined(name, location).await;
+            assert!(id.is_none() || id == Some(joined_id), "{name} was announced with different ids");
+            id = Some(joined_id);
+        }
+        // and everyone already in the room is announced to the new player, in no particular order
+        for _ in 0..clients.len() {
+            let (other_id, other_name) = match client.recv().await {
+                Response::PlayerJoinedRoom(id, name, ..) => (id, name),
+                response => panic!("{name}: expected PlayerJoinedRoom, got {response:?}"),
+            };
+            let j = names.iter().position(|&n| n == other_name).unwrap();
+            assert!(ids[j].is_none() || ids[j] == Some(other_id), "{other_name} was announced with different ids");
+            ids[j] = Some(other_id);
+        }
+        clients.push(client);
+        ids.push(id);
+    }
+    let ids = ids.into_iter().map(|id| id.expect("a single player's id is unknown")).collect();
+    (clients, ids)
+}
+
+#[tokio::test]
+async fn server_time() {
+    let addr = start_server().await;
+    let mut client = Client::connect(addr, "a").await;
+    let before = now_millis();
+    client.send(Request::GetServerTime).await;
+    let time = match client.recv().await {
+        Response::ServerTime(time) => time,
+        response => panic!("expected ServerTime, got {response:?}"),
+    };
+    assert!(before <= time && time <= now_millis());
+}
+
+#[tokio::test]
+async fn join_and_leave() {
+    let addr = start_server().await;
+    let mut a = Client::connect(addr, "a").await;
+    let mut b = Client::connect(addr, "b").await;
+
+    a.join("room", (1., 2., 3.)).await;
+    a.expect_silence().await;
+    b.join("room", (4., 5., 6.)).await;
+    let b_id = a.expect_joined("b", (4., 5., 6.)).await;
+    let a_id = b.expect_joined("a", (1., 2., 3.)).await;
+    assert_ne!(a_id, b_id);
+
+    drop(a);
+    b.expect(&[Response::PlayerLeftRoom(a_id)]).await;
+    b.expect_silence().await;
+}
+
+#[tokio::test]
+async fn rooms_are_isolated() {
+    let addr = start_server().await;
+    let mut a = Client::connect(addr, "a").await;
+    let mut b = Client::connect(addr, "b").await;
+
+    a.join("room a", (0., 0., 0.)).await;
+    b.join("room b", (0., 0., 0.)).await;
+    a.send(Request::MoveSelf(1., 2., 3., 0., 0., 0.)).await;
+    a.send(Request::PressPlatform(3)).await;
+    a.send(Request::NewGamePressed).await;
+    // a is alone in its room, so the new game starts immediately
+    a.expect_start_new_game().await;
+    a.expect_silence().await;
+    b.expect_silence().await;
+}
+
+#[tokio::test]
+async fn switch_room() {
+    let addr = start_server().await;
+    let mut a = Client::connect(addr, "a").await;
+    let mut b = Client::connect(addr, "b").await;
+    let mut c = Client::connect(addr, "c").await;
+
+    a.join("first", (0., 0., 0.)).await;
+    c.join("second", (0., 0., 0.)).await;
+    b.join("first", (1., 1., 1.)).await;
+    let b_id = a.expect_joined("b", (1., 1., 1.)).await;
+    let a_id = b.expect_joined("a", (0., 0., 0.)).await;
+
+    b.join("second", (2., 2., 2.)).await;
+    a.expect(&[Response::PlayerLeftRoom(b_id)]).await;
+    assert_eq!(c.expect_joined("b", (2., 2., 2.)).await, b_id);
+    b.expect_joined("c", (0., 0., 0.)).await;
+
+    a.send(Request::MoveSelf(3., 3., 3., 0., 0., 0.)).await;
+    b.expect_silence().await;
+    c.expect_silence().await;
+    assert_ne!(a_id, b_id);
+}
+
+#[tokio::test]
+async fn move_and_press() {
+    let addr = start_server().await;
+    let (mut clients, ids) = join_all(addr, "room", &["a", "b", "c"]).await;
+
+    clients[0].send(Request::MoveSelf(10., 20., 30., 1., 2., 3.)).await;
+    clients[1].send(Request::PressPlatform(42)).await;
+    clients[2].send(Request::PressButton(7)).await;
+
+    clients[0].expect(&[Response::PressPlatform(42), Response::PressButton(7)]).await;
+    clients[1].expect(&[Response::MoveOther(ids[0], 10., 20., 30., 1., 2., 3.), Response::PressButton(7)]).await;
+    clients[2].expect(&[Response::MoveOther(ids[0], 10., 20., 30., 1., 2., 3.), Response::PressPlatform(42)]).await;
+    for client in &mut clients {
+        client.expect_silence().await;
+    }
+}
+
+#[tokio::test]
+async fn movement_with_old_clients() {
+    let addr = start_server().await;
+    let (mut clients, ids) = join_all(addr, "room", &["new", "old"]).await;
+    let movement = Movement {
+        x: 10., y: 20., z: 30., pitch: 1., yaw: 2., roll: 3.,
+        vel_x: 100., vel_y: -100., vel_z: 50.,
+        acc_x: 1000., acc_y: 0., acc_z: -980.,
+        movement_mode: 3,
+        timestamp: now_millis(),
+    };
+
+    // old clients get the legacy message
+    clients[0].send(Request::MoveSelfV2(movement)).await;
+    clients[1].expect(&[Response::MoveOther(ids[0], 10., 20., 30., 1., 2., 3.)]).await;
+
+    // new clients get moves of old clients without velocity
+    clients[1].send(Request::MoveSelf(4., 5., 6., 7., 8., 9.)).await;
+    clients[0].expect(&[Response::MoveOtherV2(ids[1], Movement::from_location(4., 5., 6., 7., 8., 9.))]).await;
+
+    // once a client sends the new message, it gets the new message
+    clients[1].send(Request::MoveSelfV2(movement)).await;
+    clients[0].expect(&[Response::MoveOtherV2(ids[1], movement)]).await;
+    clients[0].send(Request::MoveSelfV2(movement)).await;
+    clients[1].expect(&[Response::MoveOtherV2(ids[0], movement)]).await;
+
+    clients[0].send(Request::MoveSelfV2(Movement { movement_mode: Movement::MOVEMENT_MODE_MAX, ..movement })).await;
+    clients[0].expect(&[Response::InvalidRequest]).await;
+    for client in &mut clients {
+        client.expect_silence().await;
+    }
+}
+
+#[tokio::test]
+async fn protocol_version_is_negotiated() {
+    let addr = start_server().await;
+    let mut new = Client::connect_with_version(addr, "new", 2).await;
+    new.expect(&[Response::ProtocolVersion(2)]).await;
+    let mut newer = Client::connect_with_version(addr, "newer", PROTOCOL_VERSION + 1).await;
+    newer.expect(&[Response::ProtocolVersion(PROTOCOL_VERSION)]).await;
+    let mut old = Client::connect(addr, "old").await;
+    old.expect_silence().await;
+
+    new.join("room", (0., 0., 0.)).await;
+    old.join("room", (1., 0., 0.)).await;
+    let old_id = new.expect_joined("old", (1., 0., 0.)).await;
+    old.expect_joined("new", (0., 0., 0.)).await;
+
+    // the announced version is enough to get the new message, without sending a `MoveSelfV2` first
+    old.send(Request::MoveSelf(4., 5., 6., 7., 8., 9.)).await;
+    new.expect(&[Response::MoveOtherV2(old_id, Movement::from_location(4., 5., 6., 7., 8., 9.))]).await;
+    for client in [&mut new, &mut newer, &mut old] {
+        client.expect_silence().await;
+    }
+}
+
+#[tokio::test]
+async fn new_game_waits_for_everyone() {
+    let addr = start_server().await;
+    let (mut clients, ids) = join_all(addr, "room", &["a", "b", "c"]).await;
+
+    clients[0].send(Request::NewGamePressed).await;
+    clients[1].expect(&[Response::NewGamePressed(ids[0])]).await;
+    clients[2].expect(&[Response::NewGamePressed(ids[0])]).await;
+    clients[0].expect_silence().await;
+
+    clients[1].send(Request::NewGamePressed).await;
+    clients[0].expect(&[Response::NewGamePressed(ids[1])]).await;
+    clients[2].expect(&[Response::NewGamePressed(ids[1])]).await;
+    clients[2].expect_silence().await;
+
+    clients[2].send(Request::NewGamePressed).await;
+    clients[0].expect(&[Response::NewGamePressed(ids[2])]).await;
+    clients[1].expect(&[Response::NewGamePressed(ids[2])]).await;
+    let timestamps = [
+        clients[0].expect_start_new_game().await,
+        clients[1].expect_start_new_game().await,
+        clients[2].expect_start_new_game().await,
+    ];
+    assert!(timestamps.iter().all(|&ts| ts == timestamps[0]), "clients got different start times: {timestamps:?}");
+
+    // the waiting state is reset after the start
+    clients[0].send(Request::NewGamePressed).await;
+    clients[1].expect(&[Response::NewGamePressed(ids[0])]).await;
+    clients[1].expect_silence().await;
+}
+
+#[tokio::test]
+async fn new_game_starts_when_last_waiting_player_leaves() {
+    let addr = start_server().await;
+    let (mut clients, ids) = join_all(addr, "room", &["a", "b"]).await;
+
+    clients[0].send(Request::NewGamePressed).await;
+    clients[1].expect(&[Response::NewGamePressed(ids[0])]).await;
+
+    let b = clients.pop().unwrap();
+    drop(b);
+    clients[0].expect(&[Response::PlayerLeftRoom(ids[1])]).await;
+    clients[0].expect_start_new_game().await;
+}
+
+#[tokio::test]
+async fn late_joiner_sees_waiting_players() {
+    let addr = start_server().await;
+    let mut a = Client::connect(addr, "a").await;
+    let mut b = Client::connect(addr, "b").await;
+
+    a.join("room", (0., 0., 0.)).await;
+    a.send(Request::NewGamePressed).await;
+    a.expect_start_new_game().await;
+    // pressing again while alone starts again
+    a.send(Request::NewGamePressed).await;
+    a.expect_start_new_game().await;
+
+    b.join("room", (1., 1., 1.)).await;
+    let b_id = a.expect_joined("b", (1., 1., 1.)).await;
+    let a_id = b.expect_joined("a", (0., 0., 0.)).await;
+    b.expect_silence().await;
+
+    a.send(Request::NewGamePressed).await;
+    b.expect(&[Response::NewGamePressed(a_id)]).await;
+
+    let mut c = Client::connect(addr, "c").await;
+    c.join("room", (2., 2., 2.)).await;
+    a.expect_joined("c", (2., 2., 2.)).await;
+    b.expect_joined("c", (2., 2., 2.)).await;
+    let mut joined = Vec::new();
+    for _ in 0..3 {
+        joined.push(c.recv().await);
+    }
+    // the order of other players isn't specified, but a's waiting state must follow a's join
+    let a_pos = joined.iter().position(|r| matches!(r, Response::PlayerJoinedRoom(id, ..) if *id == a_id)).unwrap();
+    assert_eq!(joined[a_pos + 1], Response::NewGamePressed(a_id));
+    assert!(joined.iter().any(|r| matches!(r, Response::PlayerJoinedRoom(id, ..) if *id == b_id)));
+    c.expect_silence().await;
+}
+
+#[tokio::test]
+async fn request_without_room_is_ignored() {
+    let addr = start_server().await;
+    let mut a = Client::connect(addr, "a").await;
+    a.send(Request::MoveSelf(0., 0., 0., 0., 0., 0.)).await;
+    a.send(Request::PressPlatform(0)).await;
+    a.send(Request::PressButton(0)).await;
+    a.send(Request::NewGamePressed).await;
+    a.expect_silence().await;
+}
+
+#[tokio::test]
+async fn room_name_too_long() {
+    let addr = start_server().await;
+    let mut a = Client::connect(addr, "a").await;
+    a.send(Request::JoinRoom("x".repeat(129), "a".to_string(), 0., 0., 0., 0., 0., 0., 0., 0., 0.)).await;
+    a.expect(&[Response::RoomNameTooLong]).await;
+}
+
+#[tokio::test]
+async fn invalid_requests_are_dropped_and_disconnected() {
+    let addr = start_server().await;
+    let (mut clients, ids) = join_all(addr, "room", &["a", "b"]).await;
+
+    // non-finite floats can't be serialized, but out-of-range ones are parsed as infinity
+    let request = serde_json::to_string(&Request::MoveSelf(0., 0., 0., 0., 0., 0.)).unwrap().replacen("0.0", "1e39", 1);
+    clients[0].ws.send(Message::Text(request)).await.unwrap();
+    clients[0].expect(&[Response::InvalidRequest]).await;
+    clients[0].send(Request::PressPlatform(255)).await;
+    clients[0].expect(&[Response::InvalidRequest]).await;
+    clients[0].send(Request::PressButton(37)).await;
+    clients[0].expect(&[Response::InvalidRequest]).await;
+    clients[0].send(Request::JoinRoom("room".to_string(), "a".repeat(1000), 0., 0., 0., 0., 0., 0., 0., 0., 0.)).await;
+    clients[0].expect(&[Response::InvalidRequest]).await;
+    clients[1].expect_silence().await;
+
+    // valid requests in between don't forgive earlier invalid ones
+    clients[0].send(Request::GetServerTime).await;
+    assert!(matches!(clients[0].recv().await, Response::ServerTime(_)));
+    clients[0].send(Request::JoinRoom("room".to_string(), "a".to_string(), 2., 0., 0., 0., 0., 0., 0., 0., 0.)).await;
+    clients[0].expect_closed().await;
+    clients[1].expect(&[Response::PlayerLeftRoom(ids[0])]).await;
+}
+
+#[tokio::test]
+async fn rate_limit() {
+    let addr = start_server().await;
+    let mut a = Client::connect(addr, "a").await;
+    a.join("room", (0., 0., 0.)).await;
+    for _ in 0..1000 {
+        // the server closes the connection at some point, ignore errors sending afterwards
+        let request = serde_json::to_string(&Request::MoveSelf(0., 0., 0., 0., 0., 0.)).unwrap();
+        if a.ws.send(Message::Text(request)).await.is_err() {
+            break;
+        }
+    }
+    a.expect(&[Response::RateLimitExceeded]).await;
+    a.expect_closed().await;
+}
+
+/// Minimal HTTP/1.0 GET, returning the status code and body.
+async fn http_get(addr: SocketAddr, path: &str) -> (u16, Vec<u8>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(format!("GET {path} HTTP/1.0\r\nHost: {addr}\r\n\r\n").as_bytes()).await.unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await.unwrap();
+    let header_end = response.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+    let status = std::str::from_utf8(&response[9..12]).unwrap().parse().unwrap();
+    (status, response[header_end + 4..].to_vec())
+}
+
+#[tokio::test]
+async fn recordings() {
+    let dir = std::env::temp_dir().join(format!("refunct-tas-server-recordings-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let addr = start_server_with_recordings(Some(dir.clone())).await;
+    let (mut clients, ids) = join_all(addr, "recorded", &["a", "b"]).await;
+
+    // nothing is recorded before the first round starts
+    clients[0].send(Request::MoveSelf(-1., -1., -1., 0., 0., 0.)).await;
+    clients[1].expect(&[Response::MoveOther(ids[0], -1., -1., -1., 0., 0., 0.)]).await;
+
+    clients[0].send(Request::NewGamePressed).await;
+    clients[1].expect(&[Response::NewGamePressed(ids[0])]).await;
+    clients[1].send(Request::NewGamePressed).await;
+    clients[0].expect(&[Response::NewGamePressed(ids[1])]).await;
+    let start = clients[0].expect_start_new_game().await;
+    clients[1].expect_start_new_game().await;
+
+    clients[0].send(Request::MoveSelf(1., 2., 3., 4., 5., 6.)).await;
+    clients[1].expect(&[Response::MoveOther(ids[0], 1., 2., 3., 4., 5., 6.)]).await;
+    clients[1].send(Request::PressPlatform(7)).await;
+    clients[0].expect(&[Response::PressPlatform(7)]).await;
+    clients[1].send(Request::PressButton(8)).await;
+    clients[0].expect(&[Response::PressButton(8)]).await;
+
+    // the round ends once the room is empty
+    drop(clients);
+
+    let mut list = Vec::new();
+    for _ in 0..50 {
+        let (status, body) = http_get(addr, "/recordings").await;
+        assert_eq!(status, 200);
+        list = serde_json::from_slice::<Vec<RecordingInfo>>(&body).unwrap();
+        if !list.is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(list.len(), 1, "the round wasn't recorded");
+    let info = &list[0];
+    assert_eq!(info.room, "recorded");
+    assert_eq!(info.start, start);
+    let mut players = info.players.clone();
+    players.sort();
+    assert_eq!(players, ["a", "b"]);
+
+    let (status, body) = http_get(addr, &format!("/recordings/{}", info.id)).await;
+    assert_eq!(status, 200);
+    let recording: Recording = serde_json::from_slice(&body).unwrap();
+    assert_eq!(&recording.info, info);
+    let events: Vec<_> = recording.events.into_iter().map(|e| e.event).collect();
+    assert_eq!(&events[..3], &[
+        Event::Move(ids[0], 1., 2., 3., 4., 5., 6.),
+        Event::PressPlatform(ids[1], 7),
+        Event::PressButton(ids[1], 8),
+    ]);
+    // both players leave, in any order
+    assert_eq!(events.len(), 5);
+    assert!(events[3..].iter().all(|e| matches!(e, Event::PlayerLeft(_))));
+    assert!(dir.join(format!("{}.json", info.id)).is_file());
+
+    assert_eq!(http_get(addr, "/recordings/does-not-exist").await.0, 404);
+    assert_eq!(http_get(addr, "/recordings/..%2F..%2Fetc%2Fpasswd").await.0, 404);
+
+    // recordings are indexed on startup
+    let addr = start_server_with_recordings(Some(dir.clone())).await;
+    let (_, body) = http_get(addr, "/recordings").await;
+    assert_eq!(serde_json::from_slice::<Vec<RecordingInfo>>(&body).unwrap(), list);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn recordings_disabled() {
+    let addr = start_server().await;
+    assert_eq!(http_get(addr, "/recordings").await.0, 404);
+}